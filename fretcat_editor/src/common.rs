@@ -17,22 +17,48 @@ lazy_static::lazy_static! {
         hashmap.insert(EffectKind::Distortion, vec![
             GAIN_BOOSTER_CARD,
             OVERDRIVE_CARD,
+            TUBE_SCREAMER_CARD,
+            WAVESHAPER_CARD,
+            MULTIBAND_CARD,
             FUZZ_CARD,
             BIT_CRUSHER_CARD
         ]);
 
         hashmap.insert(EffectKind::Delay, vec![
             DELAY_CARD,
-            TWIN_DELAY_CARD
+            TWIN_DELAY_CARD,
+            STEREO_DELAY_CARD,
+            TAPE_ECHO_CARD,
+            LOOPER_CARD
         ]);
         hashmap.insert(EffectKind::Dynamics, vec![
             LOW_PASS_CARD,
             HIGH_PASS_CARD,
-            BAND_PASS_CARD
+            BAND_PASS_CARD,
+            PARAMETRIC_EQ_CARD,
+            GRAPHIC_EQ_CARD
         ]);
         hashmap.insert(EffectKind::Reverb, vec![
             REVERB_CARD
         ]);
+        hashmap.insert(EffectKind::Modulation, vec![
+            CHORUS_CARD,
+            FLANGER_CARD,
+            PHASER_CARD,
+            TREMOLO_CARD,
+            VIBRATO_CARD
+        ]);
+        hashmap.insert(EffectKind::Amp, vec![
+            AMP_CARD,
+            CAB_IR_CARD,
+            CAB_SIM_CARD,
+            NAM_CARD
+        ]);
+        hashmap.insert(EffectKind::Pitch, vec![
+            OCTAVER_CARD,
+            PITCH_SHIFTER_CARD,
+            HARMONIZER_CARD
+        ]);
 
         hashmap
     };
@@ -44,6 +70,9 @@ pub enum EffectKind {
     Delay,
     Dynamics,
     Reverb,
+    Modulation,
+    Amp,
+    Pitch,
 }
 
 impl EffectKind {
@@ -5,10 +5,13 @@ mod card_list;
 mod preset_list;
 mod audio_slider;
 mod mono_control;
+mod noise_gate_control;
 mod preset_control;
+mod tuner_view;
 
 pub use effect_list::{EffectList, EffectListEvent};
 pub use sidebar::{Sidebar, SidebarTab, SidebarMessage};
 pub use card_list::*;
 pub use preset_list::*;
-pub use preset_control::{PresetMessage, PresetControl};
\ No newline at end of file
+pub use preset_control::{PresetMessage, PresetControl};
+pub use tuner_view::TunerView;
\ No newline at end of file
@@ -0,0 +1,140 @@
+use fretcat_effects::{
+    effects::{NoiseGate, PreFX},
+    prelude::{LabeledKnobModifier, NamedKnob},
+    ChainCommand, ChainData,
+};
+use nih_plug::vizia::prelude::*;
+
+enum NoiseGateMessage {
+    Toggle,
+    Threshold(f32),
+    Hysteresis(f32),
+    Attack(f32),
+    Hold(f32),
+    Release(f32),
+    Range(f32),
+}
+
+#[derive(Debug, Clone, Lens)]
+pub struct NoiseGateControl {
+    enabled: bool,
+    threshold: f32,
+    hysteresis: f32,
+    attack: f32,
+    hold: f32,
+    release: f32,
+    range: f32,
+    update_counter: u64,
+}
+
+impl NoiseGateControl {
+    pub fn new(cx: &mut Context) -> Handle<Self> {
+        let gate = Self::noise_gate(ChainData::as_mut_cx(cx)).clone();
+
+        Self::from(&gate).build(cx, |cx| {
+            // Presets carry their own gate settings, so resync once the chain is reloaded
+            cx.add_listener(|view: &mut Self, ex, event| {
+                event.map(|event, _| match event {
+                    ChainCommand::Load(_) => {
+                        let update_counter = view.update_counter + 1;
+                        *view = Self::from(&*Self::noise_gate(ChainData::as_mut_ex(ex)));
+                        view.update_counter = update_counter;
+                    }
+                    _ => {}
+                });
+            });
+
+            HStack::new(cx, |cx| {
+                Button::new(
+                    cx,
+                    |ex| ex.emit(NoiseGateMessage::Toggle),
+                    |cx| Label::new(cx, "GATE"),
+                )
+                .class("gate-toggle")
+                .toggle_class("gate-enabled", Self::enabled);
+
+                Binding::new(cx, Self::update_counter, |cx, _| {
+                    HStack::new(cx, |cx| {
+                        NamedKnob::new(cx, "Thresh", Self::threshold, false, -100.0..0.0)
+                            .on_changing(|ex, val| ex.emit(NoiseGateMessage::Threshold(val)));
+                        NamedKnob::new(cx, "Hyst", Self::hysteresis, false, 0.0..20.0)
+                            .on_changing(|ex, val| ex.emit(NoiseGateMessage::Hysteresis(val)));
+                        NamedKnob::new(cx, "Attack", Self::attack, false, 0.1..50.0)
+                            .on_changing(|ex, val| ex.emit(NoiseGateMessage::Attack(val)));
+                        NamedKnob::new(cx, "Hold", Self::hold, false, 0.0..500.0)
+                            .on_changing(|ex, val| ex.emit(NoiseGateMessage::Hold(val)));
+                        NamedKnob::new(cx, "Release", Self::release, false, 5.0..1000.0)
+                            .on_changing(|ex, val| ex.emit(NoiseGateMessage::Release(val)));
+                        NamedKnob::new(cx, "Range", Self::range, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(NoiseGateMessage::Range(val)));
+                    })
+                    .class("gate-knobs")
+                    .disabled(Self::enabled.map(|enabled| !*enabled));
+                });
+            });
+        })
+    }
+
+    fn noise_gate(chain: &mut fretcat_effects::Chain) -> &mut NoiseGate {
+        chain
+            .get_pre_fx::<NoiseGate>(&PreFX("noise_gate"))
+            .expect("No noise gate")
+    }
+}
+
+impl From<&NoiseGate> for NoiseGateControl {
+    fn from(gate: &NoiseGate) -> Self {
+        Self {
+            enabled: gate.enabled(),
+            threshold: gate.threshold(),
+            hysteresis: gate.hysteresis(),
+            attack: gate.attack_ms(),
+            hold: gate.hold_ms(),
+            release: gate.release_ms(),
+            range: gate.range(),
+            update_counter: 0,
+        }
+    }
+}
+
+impl View for NoiseGateControl {
+    fn element(&self) -> Option<&'static str> {
+        Some("noise-gate-control")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| {
+            let gate = Self::noise_gate(ChainData::as_mut_ex(cx));
+            match event {
+                NoiseGateMessage::Toggle => {
+                    self.enabled = !self.enabled;
+                    gate.set_enabled(self.enabled);
+                }
+                NoiseGateMessage::Threshold(val) => {
+                    self.threshold = *val;
+                    gate.set_threshold(*val);
+                }
+                NoiseGateMessage::Hysteresis(val) => {
+                    self.hysteresis = *val;
+                    gate.set_hysteresis(*val);
+                }
+                NoiseGateMessage::Attack(val) => {
+                    self.attack = *val;
+                    gate.set_attack_ms(*val);
+                }
+                NoiseGateMessage::Hold(val) => {
+                    self.hold = *val;
+                    gate.set_hold_ms(*val);
+                }
+                NoiseGateMessage::Release(val) => {
+                    self.release = *val;
+                    gate.set_release_ms(*val);
+                }
+                NoiseGateMessage::Range(val) => {
+                    self.range = *val;
+                    gate.set_range(*val);
+                }
+            }
+        });
+    }
+}
@@ -1,7 +1,4 @@
-use fretcat_effects::{
-    effects::{NoiseGate, PreFX},
-    ChainData,
-};
+use fretcat_effects::ChainData;
 use fretcat_serialization::Preset;
 pub use nih_plug::vizia::prelude::*;
 
@@ -10,11 +7,10 @@ use crate::{
     EditorData, EditorEvent,
 };
 
-use super::PresetListEvent;
+use super::{noise_gate_control::NoiseGateControl, PresetListEvent};
 
 #[derive(Debug, Clone, Lens)]
 pub struct PresetControl {
-    pub noise_gate: f32,
     color: Color,
 }
 
@@ -24,14 +20,12 @@ pub enum PresetMessage {
     Delete,
     TextChange(String),
     ChangeColor(Color),
-    NoiseGate(f32),
 }
 
 impl PresetControl {
     pub fn new(cx: &mut Context) -> Handle<Self> {
         Self {
             color: Color::transparent(),
-            noise_gate: 0.0,
         }
         .build(cx, |cx| {
             HStack::new(cx, |cx| {
@@ -56,6 +50,7 @@ impl PresetControl {
                     });
                 })
                 .class("name-wrapper");
+                NoiseGateControl::new(cx);
                 HStack::new(cx, |cx| {
                     Button::new(
                         cx,
@@ -179,13 +174,6 @@ impl View for PresetControl {
                 PresetMessage::ChangeColor(color) => {
                     self.color = *color;
                 }
-                PresetMessage::NoiseGate(val) => {
-                    self.noise_gate = *val;
-                    ChainData::as_mut_ex(cx)
-                        .get_pre_fx::<NoiseGate>(&PreFX("noise_gate"))
-                        .unwrap()
-                        .set_threshold(*val);
-                }
             }
         });
     }
@@ -4,7 +4,7 @@ use fretcat_effects::{
 };
 use nih_plug::vizia::prelude::*;
 
-use crate::common::EffectKind;
+use crate::{common::EffectKind, EditorData};
 
 use super::{audio_slider::AudioSlider, mono_control::MonoControl};
 
@@ -22,6 +22,7 @@ pub struct Sidebar {
 
 pub enum SidebarMessage {
     ChangeTab(SidebarTab),
+    ToggleTuner,
 }
 
 impl Sidebar {
@@ -55,9 +56,18 @@ impl Sidebar {
                     "tab-selected-btn",
                     Self::current_tab.map(|tab| *tab == SidebarTab::Preset),
                 );
+
+                Button::new(
+                    cx,
+                    |ex| ex.emit(SidebarMessage::ToggleTuner),
+                    |cx| Label::new(cx, "󰝚"),
+                )
+                .class("tab-btn")
+                .class("tuner-tab-btn")
+                .toggle_class("tab-selected-btn", EditorData::tuner_open);
             })
             .class("sidebar-buttons-wrapper")
-            .height(Percentage(14.0));
+            .height(Percentage(20.0));
 
             VStack::new(cx, |cx| {
                 MonoControl::new(cx)
@@ -98,7 +108,7 @@ impl Sidebar {
             })
             .child_space(Stretch(0.5))
             .row_between(Percentage(10.0))
-            .height(Percentage(75.0))
+            .height(Percentage(69.0))
             .width(Stretch(1.0));
         })
     }
@@ -114,6 +124,7 @@ impl View for Sidebar {
             SidebarMessage::ChangeTab(tab) => {
                 self.current_tab = *tab;
             }
+            SidebarMessage::ToggleTuner => {}
         });
     }
 }
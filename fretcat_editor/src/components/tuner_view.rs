@@ -0,0 +1,277 @@
+use std::time::Duration;
+
+use fretcat_effects::{
+    effects::{PreFX, Tuner},
+    prelude::{frequency_to_note, NOTE_NAMES},
+    ChainData,
+};
+use nih_plug::vizia::prelude::*;
+
+use super::SidebarMessage;
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(30);
+// Close enough to call a string in tune
+const IN_TUNE_CENTS: f32 = 2.0;
+// Below this the reading is shown but the needle dims
+const CONFIDENT: f32 = 0.9;
+// Strobe turns per second for every cent off
+const STROBE_SPEED: f32 = 0.1;
+const STROBE_BARS: usize = 16;
+// The bars are twice as wide as the meter, a lit and a dark one span this much of it
+const STROBE_PERIOD: f32 = 200.0 * 2.0 / STROBE_BARS as f32;
+
+// Name and MIDI notes from the lowest string up, chromatic has no strings to snap to
+const TUNINGS: [(&str, &[i32]); 10] = [
+    ("CHROMATIC", &[]),
+    ("STANDARD", &[40, 45, 50, 55, 59, 64]),
+    ("DROP D", &[38, 45, 50, 55, 59, 64]),
+    ("EB STANDARD", &[39, 44, 49, 54, 58, 63]),
+    ("D STANDARD", &[38, 43, 48, 53, 57, 62]),
+    ("DROP C", &[36, 43, 48, 53, 57, 62]),
+    ("DADGAD", &[38, 45, 50, 55, 57, 62]),
+    ("OPEN G", &[38, 43, 50, 55, 59, 62]),
+    ("7 STRING", &[35, 40, 45, 50, 55, 59, 64]),
+    ("BASS", &[28, 33, 38, 43]),
+];
+
+fn note_name(note: i32) -> String {
+    format!("{}{}", NOTE_NAMES[note.rem_euclid(12) as usize], note.div_euclid(12) - 1)
+}
+
+enum TunerMessage {
+    Refresh,
+    ToggleMute,
+    ToggleStrobe,
+    CycleTuning,
+    Reference(f32),
+}
+
+#[derive(Debug, Clone, Lens)]
+pub struct TunerView {
+    note: String,
+    target: i32,
+    cents: f32,
+    frequency: f32,
+    confidence: f32,
+    detected: bool,
+    in_tune: bool,
+    strobe: bool,
+    strobe_phase: f32,
+    mute: bool,
+    reference: f32,
+    tuning: usize,
+}
+
+impl TunerView {
+    pub fn new(cx: &mut Context) -> Handle<Self> {
+        let tuner = Self::tuner(ChainData::as_mut_cx(cx));
+        let (mute, reference) = (tuner.mute(), tuner.reference());
+
+        Self {
+            note: String::from("-"),
+            target: -1,
+            cents: 0.0,
+            frequency: 0.0,
+            confidence: 0.0,
+            detected: false,
+            in_tune: false,
+            strobe: false,
+            strobe_phase: 0.0,
+            mute,
+            reference,
+            tuning: 0,
+        }
+        .build(cx, |cx| {
+            let timer = cx.add_timer(REFRESH_INTERVAL, None, |ex, action| {
+                if let TimerAction::Tick(_) = action {
+                    ex.emit(TunerMessage::Refresh);
+                }
+            });
+            cx.start_timer(timer);
+
+            HStack::new(cx, |cx| {
+                Label::new(cx, "TUNER").class("tuner-title");
+                Button::new(
+                    cx,
+                    |ex| ex.emit(SidebarMessage::ToggleTuner),
+                    |cx| Label::new(cx, ""),
+                )
+                .class("tuner-close");
+            })
+            .class("tuner-header");
+
+            Label::new(cx, Self::note)
+                .class("tuner-note")
+                .toggle_class("in-tune", Self::in_tune);
+            Label::new(
+                cx,
+                Self::cents.map(|cents| format!("{:+.1} cents", cents)),
+            )
+            .class("tuner-cents")
+            .toggle_class("tuner-idle", Self::detected.map(|detected| !*detected));
+            Label::new(
+                cx,
+                Self::frequency.map(|frequency| format!("{:.1} Hz", frequency)),
+            )
+            .class("tuner-frequency")
+            .toggle_class("tuner-idle", Self::detected.map(|detected| !*detected));
+
+            Binding::new(cx, Self::strobe, |cx, strobe| {
+                ZStack::new(cx, |cx| {
+                    if strobe.get(cx) {
+                        HStack::new(cx, |cx| {
+                            for bar in 0..STROBE_BARS {
+                                Element::new(cx)
+                                    .class("strobe-bar")
+                                    .toggle_class("strobe-lit", bar % 2 == 0);
+                            }
+                        })
+                        .class("tuner-strobe")
+                        .left(Self::strobe_phase.map(|phase| Percentage(-phase * STROBE_PERIOD)));
+                    } else {
+                        Element::new(cx).class("tuner-center");
+                        Element::new(cx)
+                            .class("tuner-needle")
+                            .toggle_class("in-tune", Self::in_tune)
+                            .toggle_class(
+                                "tuner-idle",
+                                Self::confidence.map(|confidence| *confidence < CONFIDENT),
+                            )
+                            .left(Self::cents.map(|cents| {
+                                Percentage(50.0 + cents.clamp(-50.0, 50.0))
+                            }));
+                    }
+                })
+                .class("tuner-meter");
+            });
+
+            Binding::new(cx, Self::tuning, |cx, tuning| {
+                HStack::new(cx, |cx| {
+                    for &string in TUNINGS[tuning.get(cx)].1 {
+                        Label::new(cx, &note_name(string))
+                            .class("tuner-string")
+                            .toggle_class(
+                                "string-target",
+                                Self::target.map(move |target| *target == string),
+                            );
+                    }
+                })
+                .class("tuner-strings");
+            });
+
+            HStack::new(cx, |cx| {
+                Button::new(
+                    cx,
+                    |ex| ex.emit(TunerMessage::CycleTuning),
+                    |cx| Label::new(cx, Self::tuning.map(|tuning| TUNINGS[*tuning].0)),
+                )
+                .class("tuner-option")
+                .class("tuning-option");
+                HStack::new(cx, |cx| {
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(TunerMessage::Reference(-1.0)),
+                        |cx| Label::new(cx, "-"),
+                    )
+                    .class("tuner-step");
+                    Label::new(
+                        cx,
+                        Self::reference.map(|reference| format!("A4 = {:.0} Hz", reference)),
+                    )
+                    .class("tuner-reference");
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(TunerMessage::Reference(1.0)),
+                        |cx| Label::new(cx, "+"),
+                    )
+                    .class("tuner-step");
+                })
+                .class("reference-selector");
+                Button::new(
+                    cx,
+                    |ex| ex.emit(TunerMessage::ToggleStrobe),
+                    |cx| Label::new(cx, "STROBE"),
+                )
+                .class("tuner-option")
+                .toggle_class("option-enabled", Self::strobe);
+                Button::new(
+                    cx,
+                    |ex| ex.emit(TunerMessage::ToggleMute),
+                    |cx| Label::new(cx, "MUTE"),
+                )
+                .class("tuner-option")
+                .toggle_class("option-enabled", Self::mute);
+            })
+            .class("tuner-controls");
+        })
+    }
+
+    fn tuner(chain: &mut fretcat_effects::Chain) -> &mut Tuner {
+        chain
+            .get_pre_fx::<Tuner>(&PreFX("tuner"))
+            .expect("No tuner")
+    }
+
+    fn refresh(&mut self, tuner: &Tuner) {
+        let Some(reading) = tuner.reading() else {
+            self.note = String::from("-");
+            self.target = -1;
+            self.cents = 0.0;
+            self.confidence = 0.0;
+            self.detected = false;
+            self.in_tune = false;
+            return;
+        };
+
+        // With a tuning picked the needle points at the closest string instead of the closest note
+        let strings = TUNINGS[self.tuning].1;
+        let exact = frequency_to_note(reading.frequency, tuner.reference());
+        let target = strings
+            .iter()
+            .copied()
+            .min_by(|a, b| (exact - *a as f32).abs().total_cmp(&(exact - *b as f32).abs()))
+            .unwrap_or(reading.note);
+
+        self.cents = (exact - target as f32) * 100.0;
+        self.note = note_name(target);
+        self.target = target;
+        self.frequency = reading.frequency;
+        self.confidence = reading.confidence;
+        self.detected = true;
+        self.in_tune = self.cents.abs() < IN_TUNE_CENTS;
+        self.strobe_phase = (self.strobe_phase
+            + self.cents * STROBE_SPEED * REFRESH_INTERVAL.as_secs_f32())
+        .rem_euclid(1.0);
+    }
+}
+
+impl View for TunerView {
+    fn element(&self) -> Option<&'static str> {
+        Some("tuner-view")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| {
+            let tuner = Self::tuner(ChainData::as_mut_ex(cx));
+            match event {
+                TunerMessage::Refresh => {
+                    self.refresh(tuner);
+                }
+                TunerMessage::ToggleMute => {
+                    self.mute = !self.mute;
+                    tuner.set_mute(self.mute);
+                }
+                TunerMessage::ToggleStrobe => {
+                    self.strobe = !self.strobe;
+                }
+                TunerMessage::CycleTuning => {
+                    self.tuning = (self.tuning + 1) % TUNINGS.len();
+                }
+                TunerMessage::Reference(delta) => {
+                    tuner.set_reference(self.reference + *delta);
+                    self.reference = tuner.reference();
+                }
+            }
+        });
+    }
+}
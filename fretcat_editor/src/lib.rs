@@ -11,7 +11,10 @@ mod hot_lib {
 use std::sync::{Arc, Mutex};
 
 use common::{EDITOR_HEIGHT, EDITOR_WIDTH};
-use fretcat_effects::{Chain, ChainCommand, ChainData};
+use fretcat_effects::{
+    effects::{EffectError, NoiseGate, PreFX, Tuner},
+    Chain, ChainCommand, ChainData,
+};
 
 use fretcat_serialization::{Preset, ShallowPreset};
 use nih_plug::prelude::*;
@@ -30,6 +33,7 @@ pub fn default_state() -> Arc<EditorState> {
 #[derive(Debug, Lens, Clone)]
 pub struct EditorData {
     pub(crate) current_tab: SidebarTab,
+    pub(crate) tuner_open: bool,
     pub(crate) current_preset: Arc<Mutex<Preset>>,
 }
 
@@ -38,25 +42,56 @@ pub enum EditorEvent {
     LoadShallowPreset(ShallowPreset),
 }
 
+impl EditorData {
+    fn load_noise_gate(cx: &mut EventContext, preset: &Preset) {
+        let chain = ChainData::as_mut_ex(cx);
+        if let Some(noise_gate) = chain.get_pre_fx::<NoiseGate>(&PreFX("noise_gate")) {
+            *noise_gate = preset.noise_gate().clone();
+        }
+    }
+
+    fn report_missing_files(cx: &mut EventContext, preset: &Preset) {
+        for path in preset.missing_files() {
+            cx.emit(MessageEvent::Error(format!("File not found: {}", path)));
+        }
+    }
+}
+
 impl Model for EditorData {
     fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
         event.map(|event, _| match event {
             SidebarMessage::ChangeTab(tab) => {
                 self.current_tab = *tab;
             }
+            SidebarMessage::ToggleTuner => {
+                self.tuner_open = !self.tuner_open;
+                // The tuner only listens, and mutes, while it's on screen
+                let chain = ChainData::as_mut_ex(cx);
+                if let Some(tuner) = chain.get_pre_fx::<Tuner>(&PreFX("tuner")) {
+                    tuner.set_enabled(self.tuner_open);
+                }
+            }
         });
 
         event.map(|event, _| match event {
             EditorEvent::LoadPreset(p) => {
                 *self.current_preset.lock().unwrap() = p.clone();
+                Self::load_noise_gate(cx, p);
+                Self::report_missing_files(cx, p);
                 cx.emit(ChainCommand::Load(p.clone().into()));
             }
             EditorEvent::LoadShallowPreset(p) => {
                 let p = p.clone().load();
                 *self.current_preset.lock().unwrap() = p.clone();
+                Self::load_noise_gate(cx, &p);
+                Self::report_missing_files(cx, &p);
                 cx.emit(ChainCommand::Load(p.into()));
             }
         });
+
+        event.map(|error: &EffectError, _| {
+            cx.emit(MessageEvent::Error(error.0.clone()));
+        });
     }
 }
 
@@ -71,8 +106,14 @@ pub fn create(
         }
         .build(cx);
 
+        // The tuner outlives the editor, it's still on if the window was closed with it open
+        let tuner_open = chain
+            .get_pre_fx_ref::<Tuner>(&PreFX("tuner"))
+            .is_some_and(Tuner::enabled);
+
         EditorData {
             current_tab: SidebarTab::Effect,
+            tuner_open,
             current_preset: preset.clone(),
         }
         .build(cx);
@@ -108,6 +149,11 @@ pub fn create(
         })
         .class("main");
 
+        TunerView::new(cx)
+            .position_type(PositionType::SelfDirected)
+            .z_index(300)
+            .display(EditorData::tuner_open);
+
         CardSystem::view(cx);
     })
 }
@@ -146,6 +146,48 @@ pub const TWIN_DELAY_CARD: Card = Card {
     spawn: || Arc::new(TwinDelay::default()),
 };
 
+pub const STEREO_DELAY_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "STEREO DELAY");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(STEREO_DELAY_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(StereoDelay::default()),
+};
+
+pub const TAPE_ECHO_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "TAPE ECHO");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(TAPE_ECHO_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(TapeEcho::default()),
+};
+
+pub const LOOPER_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "LOOPER");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(LOOPER_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(Looper::default()),
+};
+
 pub const AUTO_WAH_CARD: Card = Card {
     content: |cx| {
         VStack::new(cx, |cx| {
@@ -200,4 +242,242 @@ pub const BAND_PASS_CARD: Card = Card {
         ex.set_drop_data(ex.current());
     },
     spawn: || Arc::new(BandPass::default()),
-};
\ No newline at end of file
+};
+
+pub const PARAMETRIC_EQ_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "PARAMETRIC EQ");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(PARAMETRIC_EQ_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(ParametricEq::default()),
+};
+
+pub const GRAPHIC_EQ_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "GRAPHIC EQ");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(GRAPHIC_EQ_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(GraphicEq::default()),
+};
+
+pub const CHORUS_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "CHORUS");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(CHORUS_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(Chorus::default()),
+};
+
+pub const FLANGER_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "FLANGER");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(FLANGER_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(Flanger::default()),
+};
+
+pub const PHASER_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "PHASER");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(PHASER_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(Phaser::default()),
+};
+
+pub const TREMOLO_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "TREMOLO");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(TREMOLO_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(Tremolo::default()),
+};
+
+pub const VIBRATO_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "VIBRATO");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(VIBRATO_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(Vibrato::default()),
+};
+
+pub const AMP_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "AMP");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(AMP_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(Amp::default()),
+};
+
+pub const CAB_IR_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "CAB IR");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(CAB_IR_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(CabIr::default()),
+};
+
+pub const CAB_SIM_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "CABINET");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(CAB_SIM_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(CabSim::default()),
+};
+
+pub const NAM_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "NAM");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(NAM_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(NamPlayer::default()),
+};
+
+pub const TUBE_SCREAMER_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "SCREAMER");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(TUBE_SCREAMER_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(TubeScreamer::default()),
+};
+
+pub const WAVESHAPER_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "WAVESHAPER");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(WAVESHAPER_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(Waveshaper::default()),
+};
+
+pub const MULTIBAND_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "MULTIBAND");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(MULTIBAND_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(MultibandDistortion::default()),
+};
+
+pub const OCTAVER_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "OCTAVER");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(OCTAVER_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(Octaver::default()),
+};
+
+pub const PITCH_SHIFTER_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "PITCH SHIFT");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(PITCH_SHIFTER_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(PitchShifter::default()),
+};
+
+pub const HARMONIZER_CARD: Card = Card {
+    content: |cx| {
+        VStack::new(cx, |cx| {
+            Label::new(cx, "HARMONIZER");
+        })
+        .child_space(Stretch(1.0));
+    },
+    drag: |ex| {
+        ex.emit(CardEvent::DragChange(Some(HARMONIZER_CARD)));
+        ex.set_drop_data(ex.current());
+    },
+    spawn: || Arc::new(Harmonizer::default()),
+};
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use indexmap::IndexMap;
 
-use crate::effects::{Gain, Mono, NoiseGate};
+use crate::effects::{Gain, Mono, NoiseGate, Tuner};
 
 pub use super::prelude::*;
 
@@ -73,6 +73,7 @@ pub struct Chain {
     pub post_fx: IndexMap<PostFX, Box<dyn AudioEffect>>,
     pub in_avg_amplitude: (f32, f32),
     pub out_avg_amplitude: (f32, f32),
+    pub sample_rate: f32,
 }
 
 impl Chain {
@@ -101,6 +102,43 @@ impl Chain {
         self.out_avg_amplitude = Self::get_rms(&frame);
     }
 
+    /// Delay of the whole chain in samples, bypassed effects don't add to it.
+    pub fn latency(&self) -> u32 {
+        let pre_fx = self.pre_fx.values().map(|fx| fx.latency()).sum::<u32>();
+        let effects = self
+            .effects
+            .iter()
+            .filter(|effect| effect.active())
+            .map(|effect| effect.latency())
+            .sum::<u32>();
+        let post_fx = self.post_fx.values().map(|fx| fx.latency()).sum::<u32>();
+
+        pre_fx + effects + post_fx
+    }
+
+    /// Lets every effect size its buffers for `sample_rate`, effects added later are
+    /// prepared as they come in. Never call it from the audio thread.
+    pub fn prepare(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.pre_fx
+            .values_mut()
+            .for_each(|fx| fx.prepare(sample_rate));
+        self.effects
+            .iter_mut()
+            .for_each(|effect| effect.prepare(sample_rate));
+        self.post_fx
+            .values_mut()
+            .for_each(|fx| fx.prepare(sample_rate));
+    }
+
+    /// Hands a MIDI control change to every active effect.
+    pub fn midi_cc(&mut self, cc: u8, value: f32) {
+        self.effects
+            .iter_mut()
+            .filter(|effect| effect.active())
+            .for_each(|effect| effect.midi_cc(cc, value));
+    }
+
     #[inline]
     fn get_rms(frame: &Frame) -> (f32, f32) {
         (
@@ -114,6 +152,11 @@ impl Chain {
         self.pre_fx.get_mut(fx)?.downcast_mut::<T>()
     }
 
+    #[inline]
+    pub fn get_pre_fx_ref<T: AudioEffect>(&self, fx: &PreFX) -> Option<&T> {
+        self.pre_fx.get(fx)?.downcast_ref::<T>()
+    }
+
     #[inline]
     pub fn get_post_fx<T: AudioEffect>(&mut self, fx: &PostFX) -> Option<&mut T> {
         self.post_fx.get_mut(fx)?.downcast_mut::<T>()
@@ -125,11 +168,17 @@ impl Chain {
             .into_iter()
             .map(|effect| EffectHandle::from(effect))
             .collect::<Vec<_>>();
+        let sample_rate = self.sample_rate;
+        self.effects
+            .iter_mut()
+            .for_each(|effect| effect.prepare(sample_rate));
     }
 
     #[inline]
     pub fn insert(&mut self, audio_effect: Arc<dyn AudioEffect>) -> usize {
-        self.effects.push(EffectHandle::from(audio_effect));
+        let mut effect = EffectHandle::from(audio_effect);
+        effect.prepare(self.sample_rate);
+        self.effects.push(effect);
         self.effects
             .clone()
             .into_iter()
@@ -141,7 +190,9 @@ impl Chain {
 
     #[inline]
     pub fn insert_at(&mut self, index: usize, audio_effect: Arc<dyn AudioEffect>) {
-        self.effects.insert(index, EffectHandle::from(audio_effect));
+        let mut effect = EffectHandle::from(audio_effect);
+        effect.prepare(self.sample_rate);
+        self.effects.insert(index, effect);
     }
 
     #[inline]
@@ -196,6 +247,7 @@ impl Default for Chain {
             post_fx: IndexMap::new(),
             in_avg_amplitude: (0.0, 0.0),
             out_avg_amplitude: (0.0, 0.0),
+            sample_rate: 44100.0,
         };
 
         chain
@@ -204,6 +256,10 @@ impl Default for Chain {
         chain
             .pre_fx
             .insert(PreFX("in_gain"), Box::new(Gain::default()));
+        // Ahead of the gate, so it doesn't cut off the decay of the note being tuned
+        chain
+            .pre_fx
+            .insert(PreFX("tuner"), Box::new(Tuner::default()));
         chain
             .pre_fx
             .insert(PreFX("noise_gate"), Box::new(NoiseGate::default()));
@@ -1,7 +1,10 @@
 use std::f32::consts::PI;
 
+use rustfft::num_complex::Complex32;
 use serde::{Serialize, Deserialize};
 
+use super::BiquadCoefs;
+
 // this is from the fundsp crate
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
 pub struct SvfCoeffs {
@@ -163,6 +166,29 @@ impl SvfCoeffs {
         }
     }
 
+    /// Calculate coefficients for a constant Q bell filter, the kind found on graphic equalizers.
+    /// Boosts keep the bandwidth set by `q` at any gain and cuts mirror them exactly.
+    /// Gain is amplitude gain (gain > 0).
+    pub fn constant_q_bell(sample_rate: f32, cutoff: f32, q: f32, gain: f32) -> Self {
+        let g = (PI * cutoff / sample_rate).tan();
+        let k = if gain >= 1.0 { 1.0 / q } else { 1.0 / (q * gain) };
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+        let m0 = 1.0;
+        let m1 = k * (gain - 1.0);
+        let m2 = 0.0;
+
+        SvfCoeffs {
+            a1,
+            a2,
+            a3,
+            m0,
+            m1,
+            m2,
+        }
+    }
+
     /// Calculate coefficients for a low shelf filter.
     /// Gain is amplitude gain (gain > 0).
     pub fn lowshelf(sample_rate: f32, cutoff: f32, q: f32, gain: f32) -> Self {
@@ -190,7 +216,7 @@ impl SvfCoeffs {
     /// Gain is amplitude gain (gain > 0).
     pub fn highshelf(sample_rate: f32, cutoff: f32, q: f32, gain: f32) -> Self {
         let a = gain.sqrt();
-        let g = (PI * cutoff / sample_rate).sqrt() * a.sqrt();
+        let g = (PI * cutoff / sample_rate).tan() * a.sqrt();
         let k = 1.0 / q;
         let a1 = 1.0 / (1.0 + g * (g + k));
         let a2 = g * a1;
@@ -208,4 +234,24 @@ impl SvfCoeffs {
             m2,
         }
     }
-}
\ No newline at end of file
+
+    /// Equivalent direct form coefficients of the trapezoidal SVF.
+    pub fn to_biquad(&self) -> BiquadCoefs {
+        let g = self.a2 / self.a1;
+        let g2 = g * g;
+        let k = (1.0 / self.a1 - 1.0 - g2) / g;
+
+        let b0 = (self.m0 * (1.0 + k * g + g2) + self.m1 * g + self.m2 * g2) * self.a1;
+        let b1 = (self.m0 * 2.0 * (g2 - 1.0) + self.m2 * 2.0 * g2) * self.a1;
+        let b2 = (self.m0 * (1.0 - k * g + g2) - self.m1 * g + self.m2 * g2) * self.a1;
+        let a1 = 2.0 * (g2 - 1.0) * self.a1;
+        let a2 = (1.0 - k * g + g2) * self.a1;
+
+        BiquadCoefs::arbitrary(a1, a2, b0, b1, b2)
+    }
+
+    /// Frequency response at frequency `omega` expressed as fraction of sampling rate.
+    pub fn response(&self, omega: f32) -> Complex32 {
+        self.to_biquad().response(omega)
+    }
+}
@@ -0,0 +1,193 @@
+use std::{fmt, sync::Arc};
+
+use rustfft::{num_complex::Complex32, Fft, FftPlanner};
+
+/// Uniformly partitioned overlap-save convolution for long kernels like impulse responses.
+/// Buffers are sized up front, so [`Convolver::set_kernel`] never allocates and is safe
+/// to call from the audio thread.
+#[derive(Clone)]
+pub struct Convolver {
+    block_size: usize,
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+    scratch: Vec<Complex32>,
+
+    // Partitions are stored back to back, each one the size of the FFT
+    kernel: Vec<Complex32>,
+    spectra: Vec<Complex32>,
+    accumulator: Vec<Complex32>,
+    partitions: usize,
+    active_partitions: usize,
+    spectrum_position: usize,
+
+    // The previous block followed by the one being filled
+    input: Vec<f32>,
+    output: Vec<f32>,
+    position: usize,
+}
+
+impl fmt::Debug for Convolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Convolver")
+            .field("block_size", &self.block_size)
+            .field("partitions", &self.partitions)
+            .field("active_partitions", &self.active_partitions)
+            .finish()
+    }
+}
+
+impl Convolver {
+    /// `block_size` is also the latency in samples.
+    pub fn new(block_size: usize, max_kernel_len: usize) -> Self {
+        let fft_size = 2 * block_size;
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(fft_size);
+        let ifft = planner.plan_fft_inverse(fft_size);
+        let scratch_len = fft
+            .get_inplace_scratch_len()
+            .max(ifft.get_inplace_scratch_len());
+        let partitions = max_kernel_len.div_ceil(block_size).max(1);
+
+        Self {
+            block_size,
+            fft,
+            ifft,
+            scratch: vec![Complex32::default(); scratch_len],
+            kernel: vec![Complex32::default(); partitions * fft_size],
+            spectra: vec![Complex32::default(); partitions * fft_size],
+            accumulator: vec![Complex32::default(); fft_size],
+            partitions,
+            active_partitions: 0,
+            spectrum_position: 0,
+            input: vec![0.0; fft_size],
+            output: vec![0.0; block_size],
+            position: 0,
+        }
+    }
+
+    pub fn latency(&self) -> usize {
+        self.block_size
+    }
+
+    /// Longest kernel that fits in the buffers allocated by [`Convolver::new`].
+    pub fn max_kernel_len(&self) -> usize {
+        self.partitions * self.block_size
+    }
+
+    /// Length of the kernel currently loaded, rounded up to whole partitions.
+    pub fn kernel_len(&self) -> usize {
+        self.active_partitions * self.block_size
+    }
+
+    /// Replaces the kernel with `length` samples taken from `sample`.
+    /// Anything past [`Convolver::max_kernel_len`] is dropped.
+    pub fn set_kernel(&mut self, length: usize, sample: impl Fn(usize) -> f32) {
+        let length = length.min(self.max_kernel_len());
+        let block_size = self.block_size;
+        let fft_size = 2 * block_size;
+        // The inverse transform isn't normalized, the kernel carries the scale instead
+        let scale = 1.0 / fft_size as f32;
+
+        self.active_partitions = length.div_ceil(block_size);
+        for partition in 0..self.active_partitions {
+            let bins = &mut self.kernel[partition * fft_size..(partition + 1) * fft_size];
+            for (offset, bin) in bins.iter_mut().enumerate() {
+                let index = partition * block_size + offset;
+                *bin = if offset < block_size && index < length {
+                    Complex32::new(sample(index) * scale, 0.0)
+                } else {
+                    Complex32::default()
+                };
+            }
+            self.fft.process_with_scratch(bins, &mut self.scratch);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.spectra.fill(Complex32::default());
+        self.input.fill(0.0);
+        self.output.fill(0.0);
+        self.position = 0;
+    }
+
+    /// Picks up where `previous` left off, so swapping in a convolver with a new kernel
+    /// doesn't drop the signal already in flight. Both need the same block size.
+    pub fn carry_over(&mut self, previous: &Self) {
+        debug_assert_eq!(self.block_size, previous.block_size);
+        let fft_size = 2 * self.block_size;
+
+        self.spectra.fill(Complex32::default());
+        self.spectrum_position = self.partitions - 1;
+        for age in 0..self.partitions.min(previous.partitions) {
+            let from = (previous.spectrum_position + previous.partitions - age) % previous.partitions;
+            let to = self.spectrum_position - age;
+            self.spectra[to * fft_size..(to + 1) * fft_size]
+                .copy_from_slice(&previous.spectra[from * fft_size..(from + 1) * fft_size]);
+        }
+        self.input.copy_from_slice(&previous.input);
+        self.output.copy_from_slice(&previous.output);
+        self.position = previous.position;
+    }
+
+    /// Returns the input delayed by [`Convolver::latency`] alongside the convolved output,
+    /// so the two can be mixed without comb filtering.
+    #[inline]
+    pub fn tick(&mut self, input: f32) -> (f32, f32) {
+        let dry = self.input[self.position];
+        let wet = self.output[self.position];
+        self.input[self.block_size + self.position] = input;
+
+        self.position += 1;
+        if self.position == self.block_size {
+            self.process_block();
+            self.position = 0;
+        }
+
+        (dry, wet)
+    }
+
+    fn process_block(&mut self) {
+        let Self {
+            block_size,
+            fft,
+            ifft,
+            scratch,
+            kernel,
+            spectra,
+            accumulator,
+            partitions,
+            active_partitions,
+            spectrum_position,
+            input,
+            output,
+            ..
+        } = self;
+        let fft_size = 2 * *block_size;
+
+        *spectrum_position = (*spectrum_position + 1) % *partitions;
+        let spectrum =
+            &mut spectra[*spectrum_position * fft_size..(*spectrum_position + 1) * fft_size];
+        for (bin, sample) in spectrum.iter_mut().zip(input.iter()) {
+            *bin = Complex32::new(*sample, 0.0);
+        }
+        fft.process_with_scratch(spectrum, scratch);
+
+        // Each older input spectrum meets the kernel partition that far into the response
+        accumulator.fill(Complex32::default());
+        for partition in 0..*active_partitions {
+            let index = (*spectrum_position + *partitions - partition) % *partitions;
+            let spectrum = &spectra[index * fft_size..(index + 1) * fft_size];
+            let bins = &kernel[partition * fft_size..(partition + 1) * fft_size];
+            for ((sum, x), h) in accumulator.iter_mut().zip(spectrum).zip(bins) {
+                *sum += x * h;
+            }
+        }
+        ifft.process_with_scratch(accumulator, scratch);
+
+        // The first half wrapped around from the circular convolution
+        for (sample, bin) in output.iter_mut().zip(&accumulator[*block_size..]) {
+            *sample = bin.re;
+        }
+        input.copy_within(*block_size.., 0);
+    }
+}
@@ -0,0 +1,149 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use rustfft::num_complex::Complex32;
+use serde::{Deserialize, Serialize};
+
+use super::{FilterMode, SvfFilter};
+
+pub const MAX_BANDS: usize = 3;
+
+/// Fourth order Linkwitz-Riley split, two Butterworth sections on each side.
+/// Both halves are in phase and sum back to a flat magnitude.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct LinkwitzRiley {
+    lowpass: [SvfFilter; 2],
+    highpass: [SvfFilter; 2],
+}
+
+impl LinkwitzRiley {
+    pub fn new(cutoff: f32, sample_rate: f32) -> Self {
+        let section = |mode| SvfFilter::new(mode, sample_rate, cutoff, FRAC_1_SQRT_2);
+        Self {
+            lowpass: [section(FilterMode::Lowpass); 2],
+            highpass: [section(FilterMode::Highpass); 2],
+        }
+    }
+
+    pub fn cutoff(&self) -> f32 {
+        self.lowpass[0].cutoff()
+    }
+
+    pub fn set_cutoff(&mut self, cutoff: f32) {
+        for filter in self.lowpass.iter_mut().chain(self.highpass.iter_mut()) {
+            filter.set_cutoff(cutoff);
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        for filter in self.lowpass.iter_mut().chain(self.highpass.iter_mut()) {
+            filter.set_sample_rate(sample_rate);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for filter in self.lowpass.iter_mut().chain(self.highpass.iter_mut()) {
+            filter.reset();
+        }
+    }
+
+    /// Returns the low and high half of `input`.
+    #[inline]
+    pub fn split(&mut self, input: f32) -> (f32, f32) {
+        let low = self.lowpass[1].tick(self.lowpass[0].tick(input));
+        let high = self.highpass[1].tick(self.highpass[0].tick(input));
+        (low, high)
+    }
+
+    /// Complex response of the low and high half at `frequency`.
+    pub fn response(&self, frequency: f32) -> (Complex32, Complex32) {
+        let low = self.lowpass[0].response(frequency) * self.lowpass[1].response(frequency);
+        let high = self.highpass[0].response(frequency) * self.highpass[1].response(frequency);
+        (low, high)
+    }
+}
+
+/// Splits a signal into two or three bands that add back up to the input, phase aside.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Crossover {
+    bands: usize,
+    splits: [LinkwitzRiley; MAX_BANDS - 1],
+    // The low band never goes through the upper split, so it gets that split's phase here
+    compensation: SvfFilter,
+}
+
+impl Crossover {
+    /// `frequencies` are the split points from low to high.
+    pub fn new(bands: usize, frequencies: [f32; MAX_BANDS - 1], sample_rate: f32) -> Self {
+        Self {
+            bands: bands.clamp(2, MAX_BANDS),
+            splits: frequencies.map(|frequency| LinkwitzRiley::new(frequency, sample_rate)),
+            compensation: SvfFilter::new(
+                FilterMode::AllPass,
+                sample_rate,
+                frequencies[1],
+                FRAC_1_SQRT_2,
+            ),
+        }
+    }
+
+    pub fn bands(&self) -> usize {
+        self.bands
+    }
+
+    pub fn frequency(&self, index: usize) -> f32 {
+        self.splits[index].cutoff()
+    }
+
+    pub fn set_bands(&mut self, bands: usize) {
+        self.bands = bands.clamp(2, MAX_BANDS);
+        self.reset();
+    }
+
+    pub fn set_frequency(&mut self, index: usize, frequency: f32) {
+        self.splits[index].set_cutoff(frequency);
+        if index == MAX_BANDS - 2 {
+            self.compensation.set_cutoff(frequency);
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        for split in self.splits.iter_mut() {
+            split.set_sample_rate(sample_rate);
+        }
+        self.compensation.set_sample_rate(sample_rate);
+    }
+
+    pub fn reset(&mut self) {
+        for split in self.splits.iter_mut() {
+            split.reset();
+        }
+        self.compensation.reset();
+    }
+
+    /// Bands from low to high, the ones past [`Crossover::bands`] stay silent.
+    #[inline]
+    pub fn split(&mut self, input: f32) -> [f32; MAX_BANDS] {
+        let (low, rest) = self.splits[0].split(input);
+        if self.bands == 2 {
+            return [low, rest, 0.0];
+        }
+
+        let (mid, high) = self.splits[1].split(rest);
+        [self.compensation.tick(low), mid, high]
+    }
+
+    /// Complex response of every band at `frequency`.
+    pub fn response(&self, frequency: f32) -> [Complex32; MAX_BANDS] {
+        let (low, rest) = self.splits[0].response(frequency);
+        if self.bands == 2 {
+            return [low, rest, Complex32::new(0.0, 0.0)];
+        }
+
+        let (mid, high) = self.splits[1].response(frequency);
+        [
+            low * self.compensation.response(frequency),
+            rest * mid,
+            rest * high,
+        ]
+    }
+}
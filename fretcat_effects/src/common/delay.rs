@@ -1,49 +1,148 @@
-use std::time::Duration;
-
+use nih_plug::vizia::prelude::Data;
 use serde::{Deserialize, Serialize};
 
-use super::interpolate;
+use super::{interpolate, one_pole_coeff, MAX_SAMPLE_RATE};
+
+/// Longest time the delay effects offer. Presets saved before the max was configurable load with it.
+pub const MAX_DELAY_SECS: f32 = 4.0;
+const DEFAULT_MAX_DELAY_SECS: f32 = 1.0;
+const GLIDE_MS: f32 = 120.0;
+const CROSSFADE_MS: f32 = 50.0;
+
+/// What the read head does when the delay time changes.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum DelayTimeMode {
+    /// Slides to the new time, the repeats bend in pitch on the way like a tape machine.
+    #[default]
+    Glide,
+    /// Fades from the old time to the new one, the pitch never changes.
+    Crossfade,
+    /// Moves straight to the new time. Clicks, but some people want that.
+    Jump,
+}
+
+impl DelayTimeMode {
+    pub const ALL: [DelayTimeMode; 3] = [Self::Glide, Self::Crossfade, Self::Jump];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Glide => "GLIDE",
+            Self::Crossfade => "FADE",
+            Self::Jump => "JUMP",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct DelaySettings {
+    feedback: f32,
+    delay_time_secs: f32,
+    #[serde(default = "max_delay_secs")]
+    max_delay_secs: f32,
+    #[serde(default)]
+    time_mode: DelayTimeMode,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+fn max_delay_secs() -> f32 {
+    MAX_DELAY_SECS
+}
+
+/// Delay line with a feedback path. The buffer is sized for the longest time at
+/// [`MAX_SAMPLE_RATE`] up front, so nothing reallocates once audio is running.
+/// Hosts running faster than that get times up to what the buffer holds, the rest is cut short.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "DelaySettings", into = "DelaySettings")]
 pub struct Delay {
     feedback: f32,
     delay_time_secs: f32,
-    current_write_position: usize,
-    current_read_position: usize,
+    max_delay_secs: f32,
+    time_mode: DelayTimeMode,
+
     sample_rate: f32,
-    buffer_size: usize,
+    glide_coeff: f32,
+    fade_step: f32,
+    current_write_position: usize,
     delay_buffer: Vec<f32>,
-    max_delay_time: Duration,
+    // Delay in samples the read head is at, and where it was when the last crossfade started
+    current_delay: f32,
+    previous_delay: f32,
+    fade: f32,
 }
 
 impl Default for Delay {
     fn default() -> Self {
+        Self::new(DEFAULT_MAX_DELAY_SECS)
+    }
+}
+
+impl PartialEq for Delay {
+    fn eq(&self, other: &Self) -> bool {
+        self.feedback == other.feedback
+            && self.delay_time_secs == other.delay_time_secs
+            && self.max_delay_secs == other.max_delay_secs
+            && self.time_mode == other.time_mode
+    }
+}
+
+impl From<DelaySettings> for Delay {
+    fn from(settings: DelaySettings) -> Self {
+        let mut delay = Self::new(settings.max_delay_secs);
+        delay.set_feedback(settings.feedback);
+        delay.set_time_mode(settings.time_mode);
+        delay.set_delay_time_secs(settings.delay_time_secs);
+        delay.current_delay = delay.target_delay();
+        delay
+    }
+}
+
+impl From<Delay> for DelaySettings {
+    fn from(delay: Delay) -> Self {
+        Self {
+            feedback: delay.feedback,
+            delay_time_secs: delay.delay_time_secs,
+            max_delay_secs: delay.max_delay_secs,
+            time_mode: delay.time_mode,
+        }
+    }
+}
+
+impl Delay {
+    /// Allocates room for `max_delay_secs` at any sample rate, call it off the audio thread.
+    /// Anything past [`MAX_DELAY_SECS`] is cut down to it.
+    pub fn new(max_delay_secs: f32) -> Self {
+        // Presets can be edited by hand, a huge value here would try to allocate gigabytes
+        let max_delay_secs = max_delay_secs.max(0.001).min(MAX_DELAY_SECS);
+        let buffer_size = (max_delay_secs * MAX_SAMPLE_RATE) as usize + 2;
         let sample_rate = 44100.0;
-        let max_delay_secs = 1;
-        let delay_time_secs = 0.2;
+        let delay_time_secs = 0.2_f32.min(max_delay_secs);
         Self {
             feedback: 0.3,
             delay_time_secs,
-            current_write_position: (delay_time_secs * sample_rate) as usize,
+            max_delay_secs,
+            time_mode: DelayTimeMode::default(),
             sample_rate,
-            buffer_size: max_delay_secs * sample_rate as usize,
-            current_read_position: 0,
-            delay_buffer: Self::make_vec(max_delay_secs * sample_rate as usize),
-            max_delay_time: Duration::from_secs(1),
+            glide_coeff: one_pole_coeff(GLIDE_MS, sample_rate),
+            fade_step: Self::fade_step(sample_rate),
+            current_write_position: 0,
+            delay_buffer: vec![0.0; buffer_size],
+            current_delay: delay_time_secs * sample_rate,
+            previous_delay: delay_time_secs * sample_rate,
+            fade: 1.0,
         }
     }
-}
 
-impl Delay {
     pub fn sample_rate(&self) -> f32 {
         self.sample_rate
     }
 
+    /// Never reallocates, the buffer already fits the longest time at [`MAX_SAMPLE_RATE`].
+    /// Above that rate the times still come out right, but only up to what the buffer holds.
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         self.sample_rate = sample_rate;
-        self.buffer_size = self.max_delay_time.as_secs() as usize * self.sample_rate as usize;
-        self.delay_buffer = Self::make_vec(self.buffer_size);
-        self.current_write_position = self.delay_time_secs as usize * self.sample_rate as usize;
+        self.glide_coeff = one_pole_coeff(GLIDE_MS, sample_rate);
+        self.fade_step = Self::fade_step(sample_rate);
+        self.current_delay = self.target_delay();
+        self.fade = 1.0;
     }
 
     pub fn feedback(&self) -> f32 {
@@ -54,46 +153,75 @@ impl Delay {
         self.delay_time_secs
     }
 
-    fn make_vec(max_delay_time: usize) -> Vec<f32> {
-        let mut v = Vec::with_capacity(max_delay_time);
-        v.resize(max_delay_time, 0.0.into());
-        v
+    pub fn max_delay_secs(&self) -> f32 {
+        self.max_delay_secs
+    }
+
+    pub fn time_mode(&self) -> DelayTimeMode {
+        self.time_mode
     }
 
     pub fn set_feedback(&mut self, value: f32) {
         self.feedback = value;
     }
 
+    /// The read head gets there the way [`Delay::time_mode`] says, on the following reads.
     pub fn set_delay_time_secs(&mut self, value: f32) {
-        self.delay_time_secs = value;
+        self.delay_time_secs = value.clamp(0.0, self.max_delay_secs);
+    }
+
+    pub fn set_time_mode(&mut self, time_mode: DelayTimeMode) {
+        self.time_mode = time_mode;
+    }
 
-        let write_position = self.current_write_position as i64;
-        let sample_rate = self.sample_rate;
-        let buffer_size = self.buffer_size as i64;
-        let offset = (sample_rate * value) as i64;
-        let cursor = write_position - offset + buffer_size;
-        self.current_read_position = (cursor % buffer_size) as usize;
+    /// Clears the buffer, nothing is left to repeat.
+    pub fn reset(&mut self) {
+        self.delay_buffer.iter_mut().for_each(|sample| *sample = 0.0);
     }
 
+    /// Reads the tap at the delay time, moving the read head one sample towards it.
     pub fn read(&mut self) -> f32 {
-        let delay_samples = self.delay_samples();
-        let offset = delay_samples - delay_samples.floor();
-        let buffer_size = self.buffer_size;
-
-        let mut current_read_position = self.current_read_position;
-        let delay_output = interpolate(
-            self.delay_buffer[current_read_position],
-            self.delay_buffer[(current_read_position + 1) % buffer_size],
-            offset,
-        );
-
-        current_read_position += 1;
-        if current_read_position >= buffer_size {
-            current_read_position = 0;
+        let target = self.target_delay();
+
+        match self.time_mode {
+            DelayTimeMode::Glide => {
+                self.current_delay = target + self.glide_coeff * (self.current_delay - target);
+                self.read_at(self.current_delay)
+            }
+            DelayTimeMode::Crossfade => {
+                // A change in the middle of a fade waits for it to finish
+                if self.fade >= 1.0 && self.current_delay != target {
+                    self.previous_delay = self.current_delay;
+                    self.current_delay = target;
+                    self.fade = 0.0;
+                }
+
+                if self.fade >= 1.0 {
+                    return self.read_at(self.current_delay);
+                }
+
+                let output = self.read_at(self.previous_delay) * (1.0 - self.fade)
+                    + self.read_at(self.current_delay) * self.fade;
+                self.fade = (self.fade + self.fade_step).min(1.0);
+                output
+            }
+            DelayTimeMode::Jump => {
+                self.current_delay = target;
+                self.read_at(self.current_delay)
+            }
         }
-        self.current_read_position = current_read_position;
+    }
+
+    /// Reads `delay_samples` behind the write head, interpolating between samples.
+    /// Unlike [`Delay::read`] the tap can move every sample, which modulated effects rely on.
+    pub fn read_at(&self, delay_samples: f32) -> f32 {
+        let buffer_size = self.delay_buffer.len();
+        let delay_samples = delay_samples.clamp(1.0, (buffer_size - 2) as f32);
+        let whole = delay_samples.floor();
 
-        delay_output
+        let newer = (self.current_write_position + buffer_size - whole as usize) % buffer_size;
+        let older = (newer + buffer_size - 1) % buffer_size;
+        interpolate(self.delay_buffer[newer], self.delay_buffer[older], delay_samples - whole)
     }
 
     pub fn write(&mut self, sample: f32) {
@@ -101,13 +229,17 @@ impl Delay {
         self.delay_buffer[current_write_position] = sample;
 
         current_write_position += 1;
-        if current_write_position >= self.buffer_size {
+        if current_write_position >= self.delay_buffer.len() {
             current_write_position = 0;
         }
         self.current_write_position = current_write_position;
     }
 
-    fn delay_samples(&self) -> f32 {
+    fn fade_step(sample_rate: f32) -> f32 {
+        1.0 / (CROSSFADE_MS * 0.001 * sample_rate)
+    }
+
+    fn target_delay(&self) -> f32 {
         self.delay_time_secs * self.sample_rate
     }
 
@@ -0,0 +1,91 @@
+use std::{fmt, sync::Arc};
+
+use crossbeam::queue::ArrayQueue;
+
+// Replaced values wait here until the sending side frees them. The audio thread only
+// frees one itself if this many pile up without anything being sent in between.
+const RETURN_CAPACITY: usize = 8;
+
+/// Passes values built away from the audio thread over to it without locking.
+///
+/// The audio thread installs the newest value between blocks with [`Handoff::receive`] and
+/// sends back the one it replaced, which is freed the next time something is sent or
+/// [`Handoff::collect`] is called. Neither side ever waits on the other.
+pub struct Handoff<T> {
+    incoming: Arc<ArrayQueue<T>>,
+    returned: Arc<ArrayQueue<T>>,
+}
+
+impl<T> Default for Handoff<T> {
+    fn default() -> Self {
+        Self {
+            incoming: Arc::new(ArrayQueue::new(1)),
+            returned: Arc::new(ArrayQueue::new(RETURN_CAPACITY)),
+        }
+    }
+}
+
+/// A copy gets queues of its own, values sent to the original never reach it.
+impl<T> Clone for Handoff<T> {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl<T> fmt::Debug for Handoff<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handoff")
+            .field("pending", &self.incoming.len())
+            .field("returned", &self.returned.len())
+            .finish()
+    }
+}
+
+impl<T> Handoff<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues `value` for the audio thread. A value it hasn't picked up yet is replaced
+    /// and freed here. Never call it from the audio thread.
+    pub fn send(&self, value: T) {
+        self.collect();
+        drop(self.incoming.force_push(value));
+    }
+
+    /// Frees whatever the audio thread has handed back. Never call it from the audio thread.
+    pub fn collect(&self) {
+        while self.returned.pop().is_some() {}
+    }
+
+    /// Drops a value that was sent but not picked up yet, and everything handed back.
+    /// For when the receiving side is about to be replaced directly, away from the audio thread.
+    pub fn discard(&self) {
+        while self.incoming.pop().is_some() {}
+        self.collect();
+    }
+
+    /// Swaps the newest value sent into `current` and hands the old one back.
+    /// Returns whether there was anything to install.
+    #[inline]
+    pub fn receive(&self, current: &mut T) -> bool {
+        self.receive_with(current, |_, _| {})
+    }
+
+    /// Like [`Handoff::receive`], but lets the new value take over state from the old one
+    /// before it's handed back.
+    #[inline]
+    pub fn receive_with(&self, current: &mut T, carry_over: impl FnOnce(&mut T, &T)) -> bool {
+        let Some(mut value) = self.incoming.pop() else {
+            return false;
+        };
+
+        std::mem::swap(current, &mut value);
+        carry_over(current, &value);
+        if let Err(value) = self.returned.push(value) {
+            // Nothing was collected in a long while, freeing it here beats keeping it forever
+            nih_plug::util::permit_alloc(|| drop(value));
+        }
+        true
+    }
+}
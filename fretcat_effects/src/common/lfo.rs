@@ -0,0 +1,226 @@
+use std::f32::consts::PI;
+
+use nih_plug::{prelude::Transport, vizia::prelude::Data};
+use serde::{Deserialize, Serialize};
+
+use crate::NUM_CHANNELS;
+
+const DEFAULT_TEMPO: f64 = 120.0;
+
+/// Note lengths tempo synced effects offer, in quarter notes, with their display names.
+pub const SYNC_DIVISIONS: [(f32, &str); 7] = [
+    (0.25, "1/16"),
+    (0.5, "1/8"),
+    (1.0, "1/4"),
+    (2.0, "1/2"),
+    (4.0, "1/1"),
+    (8.0, "2/1"),
+    (16.0, "4/1"),
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum LfoShape {
+    Sine,
+    Triangle,
+    Saw,
+    Square,
+    SampleAndHold,
+    SmoothRandom,
+}
+
+/// How fast an [`Lfo`] cycles.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum LfoRate {
+    /// Free running, in Hz.
+    Free(f32),
+    /// Locked to the host tempo, one cycle every this many quarter notes.
+    Synced(f32),
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct RandomState {
+    last_phase: f32,
+    from: f32,
+    to: f32,
+}
+
+/// Low frequency oscillator with one output per channel.
+/// Outputs are bipolar, between -1 and 1.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Lfo {
+    shape: LfoShape,
+    rate: LfoRate,
+    phase_offset: f32,
+    stereo_spread: f32,
+    sample_rate: f32,
+    tempo: f64,
+
+    phase: f32,
+    increment: f32,
+    random: [RandomState; NUM_CHANNELS],
+    seed: u32,
+}
+
+impl Default for Lfo {
+    fn default() -> Self {
+        Self::new(LfoShape::Sine, LfoRate::Free(1.0))
+    }
+}
+
+impl PartialEq for Lfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.shape == other.shape
+            && self.rate == other.rate
+            && self.phase_offset == other.phase_offset
+            && self.stereo_spread == other.stereo_spread
+    }
+}
+
+impl Lfo {
+    pub fn new(shape: LfoShape, rate: LfoRate) -> Self {
+        let mut lfo = Self {
+            shape,
+            rate,
+            phase_offset: 0.0,
+            stereo_spread: 0.0,
+            sample_rate: 44100.0,
+            tempo: DEFAULT_TEMPO,
+            phase: 0.0,
+            increment: 0.0,
+            random: [RandomState::default(); NUM_CHANNELS],
+            seed: 0x9E37_79B9,
+        };
+        lfo.update_increment();
+        lfo
+    }
+
+    pub fn shape(&self) -> LfoShape {
+        self.shape
+    }
+
+    pub fn rate(&self) -> LfoRate {
+        self.rate
+    }
+
+    pub fn phase_offset(&self) -> f32 {
+        self.phase_offset
+    }
+
+    pub fn stereo_spread(&self) -> f32 {
+        self.stereo_spread
+    }
+
+    pub fn set_shape(&mut self, shape: LfoShape) {
+        self.shape = shape;
+    }
+
+    pub fn set_rate(&mut self, rate: LfoRate) {
+        self.rate = match rate {
+            LfoRate::Free(hz) => LfoRate::Free(hz.max(0.0)),
+            LfoRate::Synced(beats) => LfoRate::Synced(beats.max(1.0 / 64.0)),
+        };
+        self.update_increment();
+    }
+
+    /// Phase offset in cycles, 0.5 starts the LFO half way through its cycle.
+    pub fn set_phase_offset(&mut self, phase_offset: f32) {
+        self.phase_offset = phase_offset.rem_euclid(1.0);
+    }
+
+    /// Phase difference between the left and right outputs in cycles, 0.25 is 90 degrees.
+    pub fn set_stereo_spread(&mut self, stereo_spread: f32) {
+        self.stereo_spread = stereo_spread.clamp(0.0, 1.0);
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_increment();
+    }
+
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+
+    /// Follows the host, call once per block before ticking.
+    /// Synced rates pick up the tempo and snap to the song position while playing.
+    pub fn update(&mut self, transport: &Transport) {
+        self.sample_rate = transport.sample_rate;
+        self.tempo = transport.tempo.unwrap_or(DEFAULT_TEMPO);
+        self.update_increment();
+
+        if let LfoRate::Synced(beats) = self.rate {
+            if let (true, Some(position)) = (transport.playing, transport.pos_beats()) {
+                self.phase = (position / beats as f64).rem_euclid(1.0) as f32;
+            }
+        }
+    }
+
+    /// Advances one sample and returns the value of every channel.
+    #[inline]
+    pub fn tick(&mut self) -> [f32; NUM_CHANNELS] {
+        let mut output = [0.0; NUM_CHANNELS];
+        for (channel, value) in output.iter_mut().enumerate() {
+            let phase = (self.phase + self.phase_offset + self.stereo_spread * channel as f32).fract();
+            *value = self.value_at(channel, phase);
+        }
+
+        self.phase = (self.phase + self.increment).fract();
+        output
+    }
+
+    fn update_increment(&mut self) {
+        let hz = match self.rate {
+            LfoRate::Free(hz) => hz,
+            LfoRate::Synced(beats) => (self.tempo / 60.0) as f32 / beats,
+        };
+        self.increment = hz / self.sample_rate;
+    }
+
+    #[inline]
+    fn value_at(&mut self, channel: usize, phase: f32) -> f32 {
+        match self.shape {
+            LfoShape::Sine => (2.0 * PI * phase).sin(),
+            LfoShape::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            LfoShape::Saw => 2.0 * phase - 1.0,
+            LfoShape::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            LfoShape::SampleAndHold => {
+                self.advance_random(channel, phase);
+                self.random[channel].to
+            }
+            LfoShape::SmoothRandom => {
+                self.advance_random(channel, phase);
+                let random = &self.random[channel];
+                // Cosine interpolation so the value glides without corners at each new target
+                let t = 0.5 - 0.5 * (PI * phase).cos();
+                random.from + (random.to - random.from) * t
+            }
+        }
+    }
+
+    /// Picks a new random target every time the channel's phase wraps around.
+    #[inline]
+    fn advance_random(&mut self, channel: usize, phase: f32) {
+        if phase < self.random[channel].last_phase {
+            let next = self.next_random();
+            let random = &mut self.random[channel];
+            random.from = random.to;
+            random.to = next;
+        }
+        self.random[channel].last_phase = phase;
+    }
+
+    // xorshift, enough for modulation and never touches the allocator
+    #[inline]
+    fn next_random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
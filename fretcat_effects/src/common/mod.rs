@@ -16,15 +16,41 @@ mod reverb;
 mod adsr;
 #[allow(unused)]
 mod delay;
+mod lfo;
+mod modulated_delay;
+mod tone_stack;
+mod convolver;
+mod wdf;
+mod crossover;
+mod pitch_shift;
+mod pitch_detector;
+mod handoff;
 
 use std::ops::Range;
 
+/// Highest rate a host will run us at. Buffers are sized for it so a sample rate
+/// change never has to reallocate on the audio thread.
+pub const MAX_SAMPLE_RATE: f32 = 192000.0;
+
 pub use self::{
     allpass::AllPass,
+    coeffs::SvfCoeffs,
     svf_filter::{SvfFilter, FilterMode},
     reverb::Freeverb,
     adsr::Envelope,
-    delay::Delay,
+    delay::{Delay, DelayTimeMode, MAX_DELAY_SECS},
+    lfo::{Lfo, LfoRate, LfoShape, SYNC_DIVISIONS},
+    modulated_delay::ModulatedDelay,
+    tone_stack::{ToneStack, ToneStackModel},
+    convolver::Convolver,
+    wdf::{
+        WdfElement, Resistor, Capacitor, Inductor, ResistiveVoltageSource, ResistiveCurrentSource,
+        Series, Parallel, Inverter, IdealVoltageSource, DiodePair, DiodeSolver,
+    },
+    crossover::{Crossover, LinkwitzRiley, MAX_BANDS},
+    pitch_shift::{PhaseVocoder, GrainShifter},
+    pitch_detector::{PitchDetector, NOTE_NAMES, frequency_to_note, note_to_frequency},
+    handoff::Handoff,
     iir_filter::*,
 };
 
@@ -58,6 +84,16 @@ pub fn rms(buffer: &[f32]) -> f32 {
     (buffer.iter().map(|sample| sample * sample).sum::<f32>() / buffer.len() as f32).sqrt()
 }
 
+/// Smoothing coefficient for a one pole filter that settles in `time_ms` milliseconds.
+#[inline]
+pub fn one_pole_coeff(time_ms: f32, sample_rate: f32) -> f32 {
+    if time_ms <= 0.0 {
+        return 0.0;
+    }
+
+    (-1.0 / (time_ms * 0.001 * sample_rate)).exp()
+}
+
 #[inline]
 fn interpolate(s1: f32, s2: f32, offset: f32) -> f32 {
     let one = 1.0_f32;
@@ -65,4 +101,14 @@ fn interpolate(s1: f32, s2: f32, offset: f32) -> f32 {
     let rhs = offset * s2;
     let lhs = (one - offset) * s1;
     lhs + rhs
-}
\ No newline at end of file
+}
+
+/// 4-point Hermite interpolation between `s0` and `s1`, with `s_1` before and `s2` after them.
+/// Keeps the top end that linear interpolation dulls when the read position moves every sample.
+#[inline]
+pub fn interpolate_cubic(s_1: f32, s0: f32, s1: f32, s2: f32, offset: f32) -> f32 {
+    let c1 = 0.5 * (s1 - s_1);
+    let c2 = s_1 - 2.5 * s0 + 2.0 * s1 - 0.5 * s2;
+    let c3 = 0.5 * (s2 - s_1) + 1.5 * (s0 - s1);
+    ((c3 * offset + c2) * offset + c1) * offset + s0
+}
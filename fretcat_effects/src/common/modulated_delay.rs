@@ -0,0 +1,54 @@
+use super::{interpolate_cubic, MAX_SAMPLE_RATE};
+
+/// Short delay line for taps that move every sample, like flangers and choruses.
+/// Holds nothing worth saving, effects rebuild it from their settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModulatedDelay {
+    buffer: Vec<f32>,
+    mask: usize,
+    write_position: usize,
+}
+
+impl ModulatedDelay {
+    pub fn new(max_delay_ms: f32) -> Self {
+        let size = ((max_delay_ms * 0.001 * MAX_SAMPLE_RATE) as usize + 4).next_power_of_two();
+        Self {
+            buffer: vec![0.0; size],
+            mask: size - 1,
+            write_position: 0,
+        }
+    }
+
+    /// Longest delay in samples [`ModulatedDelay::read`] can reach.
+    pub fn max_delay_samples(&self) -> f32 {
+        (self.buffer.len() - 3) as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|sample| *sample = 0.0);
+    }
+
+    #[inline]
+    pub fn write(&mut self, sample: f32) {
+        self.buffer[self.write_position] = sample;
+        self.write_position = (self.write_position + 1) & self.mask;
+    }
+
+    /// Reads `delay_samples` behind the last written sample with cubic interpolation.
+    /// The interpolator needs a sample on either side, so the shortest delay is 2.
+    #[inline]
+    pub fn read(&self, delay_samples: f32) -> f32 {
+        let delay_samples = delay_samples.clamp(2.0, self.max_delay_samples());
+        let whole = delay_samples.floor();
+
+        let index = |delay: usize| self.buffer[self.write_position.wrapping_sub(delay) & self.mask];
+        let whole_samples = whole as usize;
+        interpolate_cubic(
+            index(whole_samples - 1),
+            index(whole_samples),
+            index(whole_samples + 1),
+            index(whole_samples + 2),
+            delay_samples - whole,
+        )
+    }
+}
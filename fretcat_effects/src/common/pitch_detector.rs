@@ -0,0 +1,240 @@
+use std::{fmt, sync::Arc};
+
+use rustfft::{num_complex::Complex32, Fft, FftPlanner};
+
+/// Note names starting from C, index them with the MIDI note modulo 12.
+pub const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Fractional MIDI note of `frequency`, with A4 (note 69) tuned to `reference` Hz.
+#[inline]
+pub fn frequency_to_note(frequency: f32, reference: f32) -> f32 {
+    69.0 + 12.0 * (frequency / reference).log2()
+}
+
+/// Frequency of a possibly fractional MIDI note, with A4 tuned to `reference` Hz.
+#[inline]
+pub fn note_to_frequency(note: f32, reference: f32) -> f32 {
+    reference * 2.0_f32.powf((note - 69.0) / 12.0)
+}
+
+// Detection runs at this rate or under it, faster hosts are averaged down first
+const DETECTION_RATE: f32 = 48000.0;
+const DEFAULT_THRESHOLD: f32 = 0.15;
+// Windows with a lower RMS than this count as silence
+const SILENCE: f32 = 0.001;
+
+/// YIN pitch detector for monophonic signals, with the difference function done through
+/// FFT correlation so it's cheap enough to run every few milliseconds.
+/// Buffers are sized up front, a sample rate change never allocates.
+#[derive(Clone)]
+pub struct PitchDetector {
+    window: usize,
+    hop: usize,
+    min_frequency: f32,
+    max_frequency: f32,
+    threshold: f32,
+
+    decimation: usize,
+    rate: f32,
+    accumulated: f32,
+    count: usize,
+
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+    scratch: Vec<Complex32>,
+    correlation: Vec<Complex32>,
+    spectrum: Vec<Complex32>,
+    difference: Vec<f32>,
+    buffer: Vec<f32>,
+    position: usize,
+
+    frequency: Option<f32>,
+    clarity: f32,
+}
+
+impl fmt::Debug for PitchDetector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PitchDetector")
+            .field("window", &self.window)
+            .field("min_frequency", &self.min_frequency)
+            .field("max_frequency", &self.max_frequency)
+            .field("frequency", &self.frequency)
+            .finish()
+    }
+}
+
+impl PitchDetector {
+    /// The lowest frequency sets the window, and with it how quickly notes are picked up.
+    pub fn new(min_frequency: f32, max_frequency: f32) -> Self {
+        let window = (DETECTION_RATE / min_frequency).ceil() as usize;
+        let size = 2 * window;
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(size);
+        let ifft = planner.plan_fft_inverse(size);
+        let scratch_len = fft
+            .get_inplace_scratch_len()
+            .max(ifft.get_inplace_scratch_len());
+
+        let mut detector = Self {
+            window,
+            hop: window / 4,
+            min_frequency,
+            max_frequency,
+            threshold: DEFAULT_THRESHOLD,
+            decimation: 1,
+            rate: 44100.0,
+            accumulated: 0.0,
+            count: 0,
+            fft,
+            ifft,
+            scratch: vec![Complex32::default(); scratch_len],
+            correlation: vec![Complex32::default(); size],
+            spectrum: vec![Complex32::default(); size],
+            difference: vec![0.0; window],
+            buffer: vec![0.0; size],
+            position: 0,
+            frequency: None,
+            clarity: 0.0,
+        };
+        detector.set_sample_rate(44100.0);
+        detector
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.decimation = (sample_rate / DETECTION_RATE).ceil().max(1.0) as usize;
+        self.rate = sample_rate / self.decimation as f32;
+        self.reset();
+    }
+
+    /// How far from periodic a window can be and still count, YIN suggests 0.1 to 0.15.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold.clamp(0.01, 1.0);
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.position = 0;
+        self.accumulated = 0.0;
+        self.count = 0;
+        self.frequency = None;
+        self.clarity = 0.0;
+    }
+
+    /// Last detected frequency in Hz, `None` for silence or anything without a clear pitch.
+    pub fn frequency(&self) -> Option<f32> {
+        self.frequency
+    }
+
+    /// How periodic the last window was, from 0 to 1.
+    pub fn clarity(&self) -> f32 {
+        self.clarity
+    }
+
+    /// Feeds one sample, returns true when a new estimate is ready.
+    #[inline]
+    pub fn push(&mut self, sample: f32) -> bool {
+        self.accumulated += sample;
+        self.count += 1;
+        if self.count < self.decimation {
+            return false;
+        }
+
+        self.buffer[self.position] = self.accumulated / self.decimation as f32;
+        self.accumulated = 0.0;
+        self.count = 0;
+
+        self.position += 1;
+        if self.position < self.buffer.len() {
+            return false;
+        }
+
+        self.analyze();
+        self.buffer.copy_within(self.hop.., 0);
+        self.position = self.buffer.len() - self.hop;
+        true
+    }
+
+    fn analyze(&mut self) {
+        let Self {
+            window,
+            threshold,
+            rate,
+            fft,
+            ifft,
+            scratch,
+            correlation,
+            spectrum,
+            difference,
+            buffer,
+            ..
+        } = self;
+        let window = *window;
+        let size = 2 * window;
+
+        let energy = buffer[..window].iter().map(|x| x * x).sum::<f32>();
+        if (energy / window as f32).sqrt() < SILENCE {
+            self.frequency = None;
+            self.clarity = 0.0;
+            return;
+        }
+
+        // Correlating the first half against the whole buffer, no lag reaches past the end
+        for (i, (bin, sample)) in correlation.iter_mut().zip(buffer.iter()).enumerate() {
+            *bin = Complex32::new(if i < window { *sample } else { 0.0 }, 0.0);
+        }
+        for (bin, sample) in spectrum.iter_mut().zip(buffer.iter()) {
+            *bin = Complex32::new(*sample, 0.0);
+        }
+        fft.process_with_scratch(correlation, scratch);
+        fft.process_with_scratch(spectrum, scratch);
+        for (a, b) in correlation.iter_mut().zip(spectrum.iter()) {
+            *a = a.conj() * b;
+        }
+        ifft.process_with_scratch(correlation, scratch);
+
+        // Squared difference between the window and itself `tau` samples later
+        let scale = 1.0 / size as f32;
+        let mut shifted = energy;
+        for (tau, (d, bin)) in difference.iter_mut().zip(correlation.iter()).enumerate() {
+            *d = (energy + shifted - 2.0 * bin.re * scale).max(0.0);
+            shifted += buffer[tau + window] * buffer[tau + window] - buffer[tau] * buffer[tau];
+        }
+
+        // Cumulative mean normalization, which keeps lag zero from always winning
+        difference[0] = 1.0;
+        let mut running = 0.0;
+        for (tau, d) in difference.iter_mut().enumerate().skip(1) {
+            running += *d;
+            *d = if running > 0.0 {
+                *d * tau as f32 / running
+            } else {
+                1.0
+            };
+        }
+
+        let min_lag = ((*rate / self.max_frequency) as usize).max(2);
+        let max_lag = ((*rate / self.min_frequency).ceil() as usize).min(window - 2);
+        let Some(mut tau) = (min_lag..max_lag).find(|&tau| difference[tau] < *threshold) else {
+            self.frequency = None;
+            self.clarity = 0.0;
+            return;
+        };
+        while tau + 1 < max_lag && difference[tau + 1] < difference[tau] {
+            tau += 1;
+        }
+
+        // Parabola through the minimum and its neighbours for a fractional lag
+        let (a, b, c) = (difference[tau - 1], difference[tau], difference[tau + 1]);
+        let curvature = a - 2.0 * b + c;
+        let offset = if curvature.abs() > f32::EPSILON {
+            0.5 * (a - c) / curvature
+        } else {
+            0.0
+        };
+
+        self.frequency = Some(*rate / (tau as f32 + offset));
+        self.clarity = (1.0 - b).clamp(0.0, 1.0);
+    }
+}
@@ -0,0 +1,373 @@
+use std::{
+    f32::consts::{PI, TAU},
+    fmt,
+    sync::Arc,
+};
+
+use rustfft::{num_complex::Complex32, Fft, FftPlanner};
+
+use super::ModulatedDelay;
+
+// Bins quieter than this, relative to the loudest one, can't be a peak
+const PEAK_THRESHOLD: f32 = 1e-4;
+
+/// Phase vocoder pitch shifter. Every partial is moved by the same ratio, so chords
+/// survive, and formants move along with them.
+///
+/// Peaks are shifted together with the bins around them and those bins keep their phase
+/// relative to the peak, which avoids most of the phasiness of a plain vocoder.
+/// Buffers are sized up front, nothing allocates once it's built.
+#[derive(Clone)]
+pub struct PhaseVocoder {
+    frame_size: usize,
+    hop: usize,
+    ratio: f32,
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+    scratch: Vec<Complex32>,
+    window: Vec<f32>,
+    output_scale: f32,
+
+    analysis: Vec<Complex32>,
+    synthesis: Vec<Complex32>,
+    magnitude: Vec<f32>,
+    // True frequency of every bin, in bins
+    frequency: Vec<f32>,
+    last_phase: Vec<f32>,
+    synthesis_phase: Vec<f32>,
+    peaks: Vec<usize>,
+
+    input: Vec<f32>,
+    output: Vec<f32>,
+    accumulator: Vec<f32>,
+    position: usize,
+    // The input again, a whole frame back to line up with the output
+    dry: Vec<f32>,
+    dry_position: usize,
+}
+
+impl fmt::Debug for PhaseVocoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PhaseVocoder")
+            .field("frame_size", &self.frame_size)
+            .field("hop", &self.hop)
+            .field("ratio", &self.ratio)
+            .finish()
+    }
+}
+
+impl PhaseVocoder {
+    /// `frame_size` has to be even, `overlap` is how many frames cover each sample.
+    pub fn new(frame_size: usize, overlap: usize) -> Self {
+        let hop = frame_size / overlap;
+        let half = frame_size / 2;
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(frame_size);
+        let ifft = planner.plan_fft_inverse(frame_size);
+        let scratch_len = fft
+            .get_inplace_scratch_len()
+            .max(ifft.get_inplace_scratch_len());
+
+        let window = (0..frame_size)
+            .map(|i| 0.5 - 0.5 * (TAU * i as f32 / frame_size as f32).cos())
+            .collect::<Vec<_>>();
+        // Windowed on the way in and out, the overlapping squared windows sum to this
+        let overlap_gain = window.iter().map(|w| w * w).sum::<f32>() / hop as f32;
+
+        Self {
+            frame_size,
+            hop,
+            ratio: 1.0,
+            fft,
+            ifft,
+            scratch: vec![Complex32::default(); scratch_len],
+            window,
+            output_scale: 1.0 / (frame_size as f32 * overlap_gain),
+            analysis: vec![Complex32::default(); frame_size],
+            synthesis: vec![Complex32::default(); frame_size],
+            magnitude: vec![0.0; half + 1],
+            frequency: vec![0.0; half + 1],
+            last_phase: vec![0.0; half + 1],
+            synthesis_phase: vec![0.0; half + 1],
+            peaks: Vec::with_capacity(half),
+            input: vec![0.0; frame_size],
+            output: vec![0.0; hop],
+            accumulator: vec![0.0; frame_size],
+            position: frame_size - hop,
+            dry: vec![0.0; frame_size],
+            dry_position: 0,
+        }
+    }
+
+    /// Delay in samples between a sample going in and its shifted version coming out.
+    /// A sample reaches the output once the frame it entered at the end of has moved past it.
+    pub fn latency(&self) -> usize {
+        self.frame_size
+    }
+
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+
+    /// Frequency ratio, 2 is an octave up and 0.5 an octave down.
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.max(0.0);
+    }
+
+    pub fn reset(&mut self) {
+        self.last_phase.fill(0.0);
+        self.synthesis_phase.fill(0.0);
+        self.input.fill(0.0);
+        self.output.fill(0.0);
+        self.accumulator.fill(0.0);
+        self.position = self.frame_size - self.hop;
+        self.dry.fill(0.0);
+        self.dry_position = 0;
+    }
+
+    /// Returns the input delayed by [`PhaseVocoder::latency`] alongside the shifted output,
+    /// so the two can be mixed without comb filtering.
+    #[inline]
+    pub fn tick(&mut self, input: f32) -> (f32, f32) {
+        let dry = self.dry[self.dry_position];
+        self.dry[self.dry_position] = input;
+        self.dry_position = (self.dry_position + 1) % self.frame_size;
+
+        let wet = self.output[self.position + self.hop - self.frame_size];
+        self.input[self.position] = input;
+
+        self.position += 1;
+        if self.position == self.frame_size {
+            self.process_frame();
+            self.position = self.frame_size - self.hop;
+        }
+
+        (dry, wet)
+    }
+
+    fn process_frame(&mut self) {
+        let Self {
+            frame_size,
+            hop,
+            ratio,
+            fft,
+            ifft,
+            scratch,
+            window,
+            output_scale,
+            analysis,
+            synthesis,
+            magnitude,
+            frequency,
+            last_phase,
+            synthesis_phase,
+            peaks,
+            input,
+            output,
+            accumulator,
+            ..
+        } = self;
+        let (frame_size, hop, ratio) = (*frame_size, *hop, *ratio);
+        let half = frame_size / 2;
+        // Phase a bin's center frequency moves by over one hop, per bin
+        let expected = TAU * hop as f32 / frame_size as f32;
+
+        // Rotated by half a frame so the window is zero phase, then the bins around a
+        // peak are in phase with it and can be interpolated
+        for (i, bin) in analysis.iter_mut().enumerate() {
+            let index = (i + half) % frame_size;
+            *bin = Complex32::new(input[index] * window[index], 0.0);
+        }
+        fft.process_with_scratch(analysis, scratch);
+
+        let mut loudest = 0.0_f32;
+        for (k, (((bin, magnitude), frequency), last_phase)) in analysis[..=half]
+            .iter()
+            .zip(magnitude.iter_mut())
+            .zip(frequency.iter_mut())
+            .zip(last_phase.iter_mut())
+            .enumerate()
+        {
+            let phase = bin.arg();
+            let deviation = phase - *last_phase - k as f32 * expected;
+            *last_phase = phase;
+            let deviation = deviation - TAU * (deviation / TAU).round();
+
+            *magnitude = bin.norm();
+            *frequency = k as f32 + deviation / expected;
+            loudest = loudest.max(*magnitude);
+        }
+
+        let threshold = loudest * PEAK_THRESHOLD;
+        peaks.clear();
+        synthesis.fill(Complex32::default());
+        if ratio == 1.0 {
+            // At unison the analysis goes straight back out, moving peaks around would only smear it
+            synthesis[..=half].copy_from_slice(&analysis[..=half]);
+        } else {
+            peaks.extend(magnitude.windows(3).enumerate().filter_map(|(k, bins)| {
+                (bins[1] > threshold && bins[1] > bins[0] && bins[1] >= bins[2]).then_some(k + 1)
+            }));
+        }
+
+        let mut start = 0;
+        for (i, &peak) in peaks.iter().enumerate() {
+            // Each peak takes the bins up to the quietest one before the next peak
+            let end = peaks.get(i + 1).map_or(half + 1, |&next| {
+                (peak..next)
+                    .min_by(|a, b| magnitude[*a].total_cmp(&magnitude[*b]))
+                    .unwrap_or(next)
+            });
+            let region = start..end;
+            start = end;
+
+            let target_frequency = frequency[peak] * ratio;
+            let target = target_frequency.round().max(0.0) as usize;
+            if target > half {
+                continue;
+            }
+
+            let phase = synthesis_phase[target] + target_frequency * expected;
+            let rotation = Complex32::from_polar(1.0, phase - analysis[peak].arg());
+            let shift = target_frequency - frequency[peak];
+
+            // The shift is fractional, so the region is resampled onto the new bins
+            let first = (region.start as f32 + shift).ceil().max(0.0) as usize;
+            let last = ((region.end - 1) as f32 + shift).floor().min(half as f32);
+            if last < first as f32 {
+                continue;
+            }
+
+            for (j, bin) in synthesis
+                .iter_mut()
+                .enumerate()
+                .take(last as usize + 1)
+                .skip(first)
+            {
+                let source = (j as f32 - shift).max(region.start as f32);
+                let index = source as usize;
+                let fraction = source - index as f32;
+                let next = analysis[(index + 1).min(region.end - 1)];
+                *bin += (analysis[index] + (next - analysis[index]) * fraction) * rotation;
+            }
+        }
+
+        for (phase, bin) in synthesis_phase.iter_mut().zip(synthesis.iter()) {
+            *phase = bin.arg().rem_euclid(TAU);
+        }
+
+        // Mirrored so the inverse transform comes out real
+        let (positive, negative) = synthesis.split_at_mut(half + 1);
+        for (mirror, bin) in negative.iter_mut().rev().zip(&positive[1..half]) {
+            *mirror = bin.conj();
+        }
+        ifft.process_with_scratch(synthesis, scratch);
+
+        for (i, (sample, w)) in accumulator.iter_mut().zip(window.iter()).enumerate() {
+            *sample += synthesis[(i + half) % frame_size].re * w * *output_scale;
+        }
+
+        output.copy_from_slice(&accumulator[..hop]);
+        accumulator.copy_within(hop.., 0);
+        accumulator[frame_size - hop..].fill(0.0);
+        input.copy_within(hop.., 0);
+    }
+}
+
+/// Two taps sweeping through a short delay line and crossfading, like a rotating tape
+/// head. It warbles more than [`PhaseVocoder`] but only delays by half a grain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrainShifter {
+    delay: ModulatedDelay,
+    max_grain_ms: f32,
+    grain: f32,
+    ratio: f32,
+    phase: f32,
+}
+
+impl GrainShifter {
+    pub fn new(max_grain_ms: f32) -> Self {
+        Self {
+            delay: ModulatedDelay::new(max_grain_ms),
+            max_grain_ms,
+            grain: 0.0,
+            ratio: 1.0,
+            phase: 0.0,
+        }
+    }
+
+    /// Average delay of the taps in samples.
+    pub fn latency(&self) -> usize {
+        (self.grain * 0.5) as usize
+    }
+
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+
+    /// Frequency ratio, 2 is an octave up and 0.5 an octave down.
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.max(0.0);
+    }
+
+    /// Grain length in ms, clamped to what the delay line was built for.
+    pub fn set_grain(&mut self, grain_ms: f32, sample_rate: f32) {
+        self.grain = (grain_ms.min(self.max_grain_ms) * 0.001 * sample_rate)
+            .min(self.delay.max_delay_samples());
+    }
+
+    pub fn reset(&mut self) {
+        self.delay.reset();
+        self.phase = 0.0;
+    }
+
+    /// Returns the input delayed by [`GrainShifter::latency`] alongside the shifted output.
+    #[inline]
+    pub fn tick(&mut self, input: f32) -> (f32, f32) {
+        self.delay.write(input);
+        let dry = self.delay.read(self.latency() as f32);
+
+        // The delay grows for lower pitches and shrinks for higher ones
+        self.phase = (self.phase + (1.0 - self.ratio) / self.grain.max(1.0)).rem_euclid(1.0);
+
+        // Half a grain apart, the squared sines always add up to one
+        let wet = [0.0, 0.5]
+            .into_iter()
+            .map(|offset| {
+                let phase = (self.phase + offset).fract();
+                let gain = (PI * phase).sin().powi(2);
+                self.delay.read(phase * self.grain) * gain
+            })
+            .sum::<f32>();
+
+        (dry, wet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PhaseVocoder;
+
+    #[test]
+    fn phase_vocoder_latency_matches_output() {
+        let mut vocoder = PhaseVocoder::new(1024, 4);
+        vocoder.set_ratio(1.0);
+
+        let output = (0..4 * 1024)
+            .map(|i| vocoder.tick(if i == 0 { 1.0 } else { 0.0 }))
+            .collect::<Vec<_>>();
+        let loudest = |samples: Vec<f32>| {
+            samples
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+                .map(|(index, _)| index)
+                .unwrap()
+        };
+
+        let wet = loudest(output.iter().map(|(_, wet)| *wet).collect());
+        let dry = loudest(output.iter().map(|(dry, _)| *dry).collect());
+        assert_eq!(wet, vocoder.latency());
+        assert_eq!(dry, vocoder.latency());
+    }
+}
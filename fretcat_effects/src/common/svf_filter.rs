@@ -1,5 +1,6 @@
 use nih_plug::util::gain_to_db;
-use rustfft::{num_complex::Complex, FftPlanner};
+use nih_plug::vizia::prelude::Data;
+use rustfft::{num_complex::{Complex, Complex32}, FftPlanner};
 use serde::{de, Deserialize, Serialize};
 use std::{f32::consts::PI, fmt::Debug};
 use textplots::Plot;
@@ -8,11 +9,29 @@ use crate::components::Point;
 
 use super::{coeffs::SvfCoeffs, normalize_value};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
 pub enum FilterMode {
     Highpass,
     Lowpass,
     BandPass,
+    Notch,
+    Peak,
+    AllPass,
+    Bell,
+    ConstantQBell,
+    LowShelf,
+    HighShelf,
+}
+
+impl FilterMode {
+    /// Whether the filter shape depends on [`SvfFilter::gain`].
+    pub fn has_gain(&self) -> bool {
+        matches!(self, FilterMode::Bell | FilterMode::ConstantQBell | FilterMode::LowShelf | FilterMode::HighShelf)
+    }
+}
+
+fn unity_gain() -> f32 {
+    1.0
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -24,11 +43,18 @@ pub struct SvfFilter {
     cutoff: f32,
     q: f32,
     mode: FilterMode,
+    #[serde(default = "unity_gain")]
+    gain: f32,
 }
 
 impl SvfFilter {
     pub fn new(mode: FilterMode, sample_rate: f32, cutoff: f32, q: f32) -> Self {
-        let coeffs = SvfFilter::get_coeffs(mode, sample_rate, cutoff, q);
+        Self::with_gain(mode, sample_rate, cutoff, q, 1.0)
+    }
+
+    /// Gain is amplitude gain (gain > 0) and only affects bell and shelf modes.
+    pub fn with_gain(mode: FilterMode, sample_rate: f32, cutoff: f32, q: f32, gain: f32) -> Self {
+        let coeffs = SvfFilter::get_coeffs(mode, sample_rate, cutoff, q, gain);
 
         Self {
             coeffs,
@@ -38,6 +64,7 @@ impl SvfFilter {
             q,
             sample_rate,
             mode,
+            gain,
         }
     }
 
@@ -67,7 +94,23 @@ impl SvfFilter {
     pub fn recalculate_coeffs(&mut self, cutoff: f32, q: f32, sample_rate: f32) {
         self.cutoff = cutoff;
         self.q = q;
-        self.coeffs = SvfFilter::get_coeffs(self.mode, sample_rate, cutoff, q);
+        self.sample_rate = sample_rate;
+        self.coeffs = SvfFilter::get_coeffs(self.mode, sample_rate, cutoff, q, self.gain);
+    }
+
+    pub fn reset(&mut self) {
+        self.ic1eq = 0.0;
+        self.ic2eq = 0.0;
+    }
+
+    pub fn set_mode(&mut self, mode: FilterMode) {
+        self.mode = mode;
+        self.recalculate_coeffs(self.cutoff(), self.q(), self.sample_rate());
+    }
+
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain;
+        self.recalculate_coeffs(self.cutoff(), self.q(), self.sample_rate());
     }
 
     pub fn set_cutoff(&mut self, cutoff: f32) {
@@ -82,14 +125,30 @@ impl SvfFilter {
         self.recalculate_coeffs(self.cutoff(), self.q(), sample_rate);
     }
 
-    fn get_coeffs(mode: FilterMode, sample_rate: f32, cutoff: f32, q: f32) -> SvfCoeffs {
+    fn get_coeffs(mode: FilterMode, sample_rate: f32, cutoff: f32, q: f32, gain: f32) -> SvfCoeffs {
         match mode {
             FilterMode::Highpass => SvfCoeffs::highpass(sample_rate, cutoff, q),
             FilterMode::Lowpass => SvfCoeffs::lowpass(sample_rate, cutoff, q),
             FilterMode::BandPass => SvfCoeffs::bandpass(sample_rate, cutoff, q),
+            FilterMode::Notch => SvfCoeffs::notch(sample_rate, cutoff, q),
+            FilterMode::Peak => SvfCoeffs::peak(sample_rate, cutoff, q),
+            FilterMode::AllPass => SvfCoeffs::allpass(sample_rate, cutoff, q),
+            FilterMode::Bell => SvfCoeffs::bell(sample_rate, cutoff, q, gain),
+            FilterMode::ConstantQBell => SvfCoeffs::constant_q_bell(sample_rate, cutoff, q, gain),
+            FilterMode::LowShelf => SvfCoeffs::lowshelf(sample_rate, cutoff, q, gain),
+            FilterMode::HighShelf => SvfCoeffs::highshelf(sample_rate, cutoff, q, gain),
         }
     }
 
+    pub fn coeffs(&self) -> &SvfCoeffs {
+        &self.coeffs
+    }
+
+    /// Frequency response at `frequency` in Hz.
+    pub fn response(&self, frequency: f32) -> Complex32 {
+        self.coeffs.response(frequency / self.sample_rate)
+    }
+
     pub fn sample_rate(&self) -> f32 {
         self.sample_rate
     }
@@ -102,6 +161,10 @@ impl SvfFilter {
         self.q
     }
 
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
     pub fn graph(&self) -> Vec<Point> {
         let mut points = Vec::new();
         let duration = 0.01; // Duration in seconds
@@ -122,7 +185,13 @@ impl SvfFilter {
             sine_wave.push(sample);
         }
 
-        let mut cloned = Self::new(self.mode(), self.sample_rate(), self.cutoff(), self.q());
+        let mut cloned = Self::with_gain(
+            self.mode(),
+            self.sample_rate(),
+            self.cutoff(),
+            self.q(),
+            self.gain(),
+        );
 
         sine_wave
             .iter_mut()
@@ -0,0 +1,228 @@
+use nih_plug::vizia::prelude::Data;
+use serde::{Deserialize, Serialize};
+
+/// Passive bass/mid/treble network found in most guitar amps.
+/// Only the component values change between the classic circuits.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum ToneStackModel {
+    Fender,
+    Marshall,
+    Vox,
+}
+
+struct Components {
+    c1: f64,
+    c2: f64,
+    c3: f64,
+    r1: f64,
+    r2: f64,
+    r3: f64,
+    r4: f64,
+}
+
+impl ToneStackModel {
+    pub const ALL: [ToneStackModel; 3] = [
+        ToneStackModel::Fender,
+        ToneStackModel::Marshall,
+        ToneStackModel::Vox,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToneStackModel::Fender => "FENDER",
+            ToneStackModel::Marshall => "MARSHALL",
+            ToneStackModel::Vox => "VOX",
+        }
+    }
+
+    fn components(&self) -> Components {
+        match self {
+            // '59 Bassman
+            ToneStackModel::Fender => Components {
+                c1: 250e-12,
+                c2: 20e-9,
+                c3: 20e-9,
+                r1: 250e3,
+                r2: 1e6,
+                r3: 25e3,
+                r4: 56e3,
+            },
+            // JCM800
+            ToneStackModel::Marshall => Components {
+                c1: 470e-12,
+                c2: 22e-9,
+                c3: 22e-9,
+                r1: 220e3,
+                r2: 1e6,
+                r3: 22e3,
+                r4: 33e3,
+            },
+            // AC30 top boost
+            ToneStackModel::Vox => Components {
+                c1: 50e-12,
+                c2: 22e-9,
+                c3: 22e-9,
+                r1: 1e6,
+                r2: 1e6,
+                r3: 10e3,
+                r4: 100e3,
+            },
+        }
+    }
+}
+
+/// Third order model of the tone stack, discretized with the bilinear transform.
+/// From Yeh and Smith, "Discretization of the '59 Fender Bassman Tone Stack".
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ToneStack {
+    model: ToneStackModel,
+    bass: f32,
+    mid: f32,
+    treble: f32,
+    sample_rate: f32,
+
+    // The poles sit close to DC, single precision drifts audibly there
+    b: [f64; 4],
+    a: [f64; 4],
+    state: [f64; 3],
+}
+
+impl ToneStack {
+    pub fn new(model: ToneStackModel) -> Self {
+        let mut tone_stack = Self {
+            model,
+            bass: 0.5,
+            mid: 0.5,
+            treble: 0.5,
+            sample_rate: 44100.0,
+            b: [0.0; 4],
+            a: [1.0, 0.0, 0.0, 0.0],
+            state: [0.0; 3],
+        };
+        tone_stack.recalculate();
+        tone_stack
+    }
+
+    pub fn model(&self) -> ToneStackModel {
+        self.model
+    }
+
+    pub fn bass(&self) -> f32 {
+        self.bass
+    }
+
+    pub fn mid(&self) -> f32 {
+        self.mid
+    }
+
+    pub fn treble(&self) -> f32 {
+        self.treble
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn set_model(&mut self, model: ToneStackModel) {
+        self.model = model;
+        self.recalculate();
+    }
+
+    /// Knob positions between 0 and 1.
+    pub fn set_controls(&mut self, bass: f32, mid: f32, treble: f32) {
+        self.bass = bass.clamp(0.0, 1.0);
+        self.mid = mid.clamp(0.0, 1.0);
+        self.treble = treble.clamp(0.0, 1.0);
+        self.recalculate();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.recalculate();
+    }
+
+    pub fn reset(&mut self) {
+        self.state = [0.0; 3];
+    }
+
+    #[inline]
+    pub fn tick(&mut self, input: f32) -> f32 {
+        let x = input as f64;
+        let y = self.b[0] * x + self.state[0];
+        self.state[0] = self.b[1] * x - self.a[1] * y + self.state[1];
+        self.state[1] = self.b[2] * x - self.a[2] * y + self.state[2];
+        self.state[2] = self.b[3] * x - self.a[3] * y;
+        y as f32
+    }
+
+    fn recalculate(&mut self) {
+        let Components {
+            c1,
+            c2,
+            c3,
+            r1,
+            r2,
+            r3,
+            r4,
+        } = self.model.components();
+
+        // The bass pot has a log taper
+        let l = ((self.bass as f64 - 1.0) * 3.4).exp();
+        let m = self.mid as f64;
+        let t = self.treble as f64;
+
+        let b1 = t * c1 * r1 + m * c3 * r3 + l * (c1 * r2 + c2 * r2) + (c1 * r3 + c2 * r3);
+        let b2 = t * (c1 * c2 * r1 * r4 + c1 * c3 * r1 * r4)
+            - m * m * (c1 * c3 * r3 * r3 + c2 * c3 * r3 * r3)
+            + m * (c1 * c3 * r1 * r3 + c1 * c3 * r3 * r3 + c2 * c3 * r3 * r3)
+            + l * (c1 * c2 * r1 * r2 + c1 * c2 * r2 * r4 + c1 * c3 * r2 * r4)
+            + l * m * (c1 * c3 * r2 * r3 + c2 * c3 * r2 * r3)
+            + (c1 * c2 * r1 * r3 + c1 * c2 * r3 * r4 + c1 * c3 * r3 * r4);
+        let b3 = l * m * (c1 * c2 * c3 * r1 * r2 * r3 + c1 * c2 * c3 * r2 * r3 * r4)
+            - m * m * (c1 * c2 * c3 * r1 * r3 * r3 + c1 * c2 * c3 * r3 * r3 * r4)
+            + m * (c1 * c2 * c3 * r1 * r3 * r3 + c1 * c2 * c3 * r3 * r3 * r4)
+            + t * c1 * c2 * c3 * r1 * r3 * r4
+            - t * m * c1 * c2 * c3 * r1 * r3 * r4
+            + t * l * c1 * c2 * c3 * r1 * r2 * r4;
+
+        let a0 = 1.0;
+        let a1 = (c1 * r1 + c1 * r3 + c2 * r3 + c2 * r4 + c3 * r4)
+            + m * c3 * r3
+            + l * (c1 * r2 + c2 * r2);
+        let a2 = m * (c1 * c3 * r1 * r3 - c2 * c3 * r3 * r4 + c1 * c3 * r3 * r3 + c2 * c3 * r3 * r3)
+            + l * m * (c1 * c3 * r2 * r3 + c2 * c3 * r2 * r3)
+            - m * m * (c1 * c3 * r3 * r3 + c2 * c3 * r3 * r3)
+            + l * (c1 * c2 * r2 * r4 + c1 * c2 * r1 * r2 + c1 * c3 * r2 * r4 + c2 * c3 * r2 * r4)
+            + (c1 * c2 * r1 * r4
+                + c1 * c3 * r1 * r4
+                + c1 * c2 * r3 * r4
+                + c1 * c2 * r1 * r3
+                + c1 * c3 * r3 * r4
+                + c2 * c3 * r3 * r4);
+        let a3 = l * m * (c1 * c2 * c3 * r1 * r2 * r3 + c1 * c2 * c3 * r2 * r3 * r4)
+            - m * m * (c1 * c2 * c3 * r1 * r3 * r3 + c1 * c2 * c3 * r3 * r3 * r4)
+            + m * (c1 * c2 * c3 * r3 * r3 * r4 + c1 * c2 * c3 * r1 * r3 * r3
+                - c1 * c2 * c3 * r1 * r3 * r4)
+            + l * c1 * c2 * c3 * r1 * r2 * r4
+            + c1 * c2 * c3 * r1 * r3 * r4;
+
+        let c = 2.0 * self.sample_rate as f64;
+        let (cc, ccc) = (c * c, c * c * c);
+
+        let b = [
+            -b1 * c - b2 * cc - b3 * ccc,
+            -b1 * c + b2 * cc + 3.0 * b3 * ccc,
+            b1 * c + b2 * cc - 3.0 * b3 * ccc,
+            b1 * c - b2 * cc + b3 * ccc,
+        ];
+        let a = [
+            -a0 - a1 * c - a2 * cc - a3 * ccc,
+            -3.0 * a0 - a1 * c + a2 * cc + 3.0 * a3 * ccc,
+            -3.0 * a0 + a1 * c + a2 * cc - 3.0 * a3 * ccc,
+            -a0 + a1 * c - a2 * cc + a3 * ccc,
+        ];
+
+        self.b = b.map(|coeff| coeff / a[0]);
+        self.a = a.map(|coeff| coeff / a[0]);
+    }
+}
@@ -0,0 +1,645 @@
+//! Wave digital filters, for circuits that have to keep the behaviour of their components.
+//!
+//! A circuit is built as a tree of adaptors with linear elements at the leaves and a single
+//! root, which may be nonlinear. Every sample the waves travel up from the leaves to the root
+//! with [`WdfElement::reflected`] and back down with [`WdfElement::incident`].
+//! After changing a component value, call [`WdfElement::propagate_impedance`] on the top of the tree.
+
+use serde::{Deserialize, Serialize};
+
+/// Keeps `exp` and `cosh` finite while Newton-Raphson overshoots.
+const MAX_EXPONENT: f32 = 80.0;
+const NEWTON_TOLERANCE: f32 = 1e-6;
+const NEWTON_ITERATIONS: usize = 16;
+
+/// One port of a wave digital filter.
+pub trait WdfElement {
+    /// Port resistance seen from the adaptor above.
+    fn impedance(&self) -> f32;
+
+    /// Recomputes the port resistances from the leaves up.
+    fn propagate_impedance(&mut self) {}
+
+    /// Reactive elements need the rate to discretize, everything else passes it on.
+    fn set_sample_rate(&mut self, _sample_rate: f32) {}
+
+    /// Takes the wave coming down from the adaptor above.
+    fn incident(&mut self, wave: f32);
+
+    /// Gives the wave going up to the adaptor above.
+    fn reflected(&mut self) -> f32;
+
+    /// Incident and reflected waves of the last sample.
+    fn waves(&self) -> (f32, f32);
+
+    #[inline]
+    fn voltage(&self) -> f32 {
+        let (a, b) = self.waves();
+        (a + b) * 0.5
+    }
+
+    #[inline]
+    fn current(&self) -> f32 {
+        let (a, b) = self.waves();
+        (a - b) / (2.0 * self.impedance())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Resistor {
+    resistance: f32,
+    a: f32,
+    b: f32,
+}
+
+impl Resistor {
+    pub fn new(resistance: f32) -> Self {
+        Self {
+            resistance,
+            a: 0.0,
+            b: 0.0,
+        }
+    }
+
+    pub fn set_resistance(&mut self, resistance: f32) {
+        self.resistance = resistance;
+    }
+}
+
+impl WdfElement for Resistor {
+    fn impedance(&self) -> f32 {
+        self.resistance
+    }
+
+    #[inline]
+    fn incident(&mut self, wave: f32) {
+        self.a = wave;
+    }
+
+    #[inline]
+    fn reflected(&mut self) -> f32 {
+        self.b = 0.0;
+        self.b
+    }
+
+    fn waves(&self) -> (f32, f32) {
+        (self.a, self.b)
+    }
+}
+
+/// Capacitor discretized with the bilinear transform.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Capacitor {
+    capacitance: f32,
+    impedance: f32,
+    sample_rate: f32,
+    state: f32,
+    a: f32,
+    b: f32,
+}
+
+impl Capacitor {
+    pub fn new(capacitance: f32, sample_rate: f32) -> Self {
+        Self {
+            capacitance,
+            impedance: 1.0 / (2.0 * capacitance * sample_rate),
+            sample_rate,
+            state: 0.0,
+            a: 0.0,
+            b: 0.0,
+        }
+    }
+
+    pub fn set_capacitance(&mut self, capacitance: f32) {
+        self.capacitance = capacitance;
+        self.impedance = 1.0 / (2.0 * capacitance * self.sample_rate);
+    }
+
+    pub fn reset(&mut self) {
+        self.state = 0.0;
+    }
+}
+
+impl WdfElement for Capacitor {
+    fn impedance(&self) -> f32 {
+        self.impedance
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.set_capacitance(self.capacitance);
+    }
+
+    #[inline]
+    fn incident(&mut self, wave: f32) {
+        self.a = wave;
+        self.state = wave;
+    }
+
+    #[inline]
+    fn reflected(&mut self) -> f32 {
+        self.b = self.state;
+        self.b
+    }
+
+    fn waves(&self) -> (f32, f32) {
+        (self.a, self.b)
+    }
+}
+
+/// Inductor discretized with the bilinear transform.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Inductor {
+    inductance: f32,
+    impedance: f32,
+    sample_rate: f32,
+    state: f32,
+    a: f32,
+    b: f32,
+}
+
+impl Inductor {
+    pub fn new(inductance: f32, sample_rate: f32) -> Self {
+        Self {
+            inductance,
+            impedance: 2.0 * inductance * sample_rate,
+            sample_rate,
+            state: 0.0,
+            a: 0.0,
+            b: 0.0,
+        }
+    }
+
+    pub fn set_inductance(&mut self, inductance: f32) {
+        self.inductance = inductance;
+        self.impedance = 2.0 * inductance * self.sample_rate;
+    }
+
+    pub fn reset(&mut self) {
+        self.state = 0.0;
+    }
+}
+
+impl WdfElement for Inductor {
+    fn impedance(&self) -> f32 {
+        self.impedance
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.set_inductance(self.inductance);
+    }
+
+    #[inline]
+    fn incident(&mut self, wave: f32) {
+        self.a = wave;
+        self.state = wave;
+    }
+
+    #[inline]
+    fn reflected(&mut self) -> f32 {
+        self.b = -self.state;
+        self.b
+    }
+
+    fn waves(&self) -> (f32, f32) {
+        (self.a, self.b)
+    }
+}
+
+/// Voltage source with a resistor in series, so it can sit anywhere in the tree.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ResistiveVoltageSource {
+    resistance: f32,
+    voltage: f32,
+    a: f32,
+    b: f32,
+}
+
+impl ResistiveVoltageSource {
+    pub fn new(resistance: f32) -> Self {
+        Self {
+            resistance,
+            voltage: 0.0,
+            a: 0.0,
+            b: 0.0,
+        }
+    }
+
+    pub fn set_resistance(&mut self, resistance: f32) {
+        self.resistance = resistance;
+    }
+
+    #[inline]
+    pub fn set_voltage(&mut self, voltage: f32) {
+        self.voltage = voltage;
+    }
+}
+
+impl WdfElement for ResistiveVoltageSource {
+    fn impedance(&self) -> f32 {
+        self.resistance
+    }
+
+    #[inline]
+    fn incident(&mut self, wave: f32) {
+        self.a = wave;
+    }
+
+    #[inline]
+    fn reflected(&mut self) -> f32 {
+        self.b = self.voltage;
+        self.b
+    }
+
+    fn waves(&self) -> (f32, f32) {
+        (self.a, self.b)
+    }
+}
+
+/// Current source with a resistor in parallel, so it can sit anywhere in the tree.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ResistiveCurrentSource {
+    resistance: f32,
+    current: f32,
+    a: f32,
+    b: f32,
+}
+
+impl ResistiveCurrentSource {
+    pub fn new(resistance: f32) -> Self {
+        Self {
+            resistance,
+            current: 0.0,
+            a: 0.0,
+            b: 0.0,
+        }
+    }
+
+    pub fn set_resistance(&mut self, resistance: f32) {
+        self.resistance = resistance;
+    }
+
+    #[inline]
+    pub fn set_current(&mut self, current: f32) {
+        self.current = current;
+    }
+}
+
+impl WdfElement for ResistiveCurrentSource {
+    fn impedance(&self) -> f32 {
+        self.resistance
+    }
+
+    #[inline]
+    fn incident(&mut self, wave: f32) {
+        self.a = wave;
+    }
+
+    #[inline]
+    fn reflected(&mut self) -> f32 {
+        self.b = self.resistance * self.current;
+        self.b
+    }
+
+    fn waves(&self) -> (f32, f32) {
+        (self.a, self.b)
+    }
+}
+
+/// Two ports sharing the same current.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Series<L, R> {
+    pub left: L,
+    pub right: R,
+    impedance: f32,
+    left_reflection: f32,
+    a: f32,
+    b: f32,
+}
+
+impl<L: WdfElement, R: WdfElement> Series<L, R> {
+    pub fn new(left: L, right: R) -> Self {
+        let mut series = Self {
+            left,
+            right,
+            impedance: 0.0,
+            left_reflection: 0.0,
+            a: 0.0,
+            b: 0.0,
+        };
+        series.calculate_impedance();
+        series
+    }
+
+    fn calculate_impedance(&mut self) {
+        self.impedance = self.left.impedance() + self.right.impedance();
+        self.left_reflection = self.left.impedance() / self.impedance;
+    }
+}
+
+impl<L: WdfElement, R: WdfElement> WdfElement for Series<L, R> {
+    fn impedance(&self) -> f32 {
+        self.impedance
+    }
+
+    fn propagate_impedance(&mut self) {
+        self.left.propagate_impedance();
+        self.right.propagate_impedance();
+        self.calculate_impedance();
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.left.set_sample_rate(sample_rate);
+        self.right.set_sample_rate(sample_rate);
+        self.calculate_impedance();
+    }
+
+    #[inline]
+    fn incident(&mut self, wave: f32) {
+        let (_, left_b) = self.left.waves();
+        let (_, right_b) = self.right.waves();
+        let left_a = left_b - self.left_reflection * (wave + left_b + right_b);
+        self.left.incident(left_a);
+        self.right.incident(-(wave + left_a));
+        self.a = wave;
+    }
+
+    #[inline]
+    fn reflected(&mut self) -> f32 {
+        self.b = -(self.left.reflected() + self.right.reflected());
+        self.b
+    }
+
+    fn waves(&self) -> (f32, f32) {
+        (self.a, self.b)
+    }
+}
+
+/// Two ports sharing the same voltage.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Parallel<L, R> {
+    pub left: L,
+    pub right: R,
+    impedance: f32,
+    left_reflection: f32,
+    difference: f32,
+    a: f32,
+    b: f32,
+}
+
+impl<L: WdfElement, R: WdfElement> Parallel<L, R> {
+    pub fn new(left: L, right: R) -> Self {
+        let mut parallel = Self {
+            left,
+            right,
+            impedance: 0.0,
+            left_reflection: 0.0,
+            difference: 0.0,
+            a: 0.0,
+            b: 0.0,
+        };
+        parallel.calculate_impedance();
+        parallel
+    }
+
+    fn calculate_impedance(&mut self) {
+        let left_conductance = self.left.impedance().recip();
+        let conductance = left_conductance + self.right.impedance().recip();
+        self.impedance = conductance.recip();
+        self.left_reflection = left_conductance / conductance;
+    }
+}
+
+impl<L: WdfElement, R: WdfElement> WdfElement for Parallel<L, R> {
+    fn impedance(&self) -> f32 {
+        self.impedance
+    }
+
+    fn propagate_impedance(&mut self) {
+        self.left.propagate_impedance();
+        self.right.propagate_impedance();
+        self.calculate_impedance();
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.left.set_sample_rate(sample_rate);
+        self.right.set_sample_rate(sample_rate);
+        self.calculate_impedance();
+    }
+
+    #[inline]
+    fn incident(&mut self, wave: f32) {
+        let (_, right_b) = self.right.waves();
+        let right_a = wave + self.b - right_b;
+        self.left.incident(right_a + self.difference);
+        self.right.incident(right_a);
+        self.a = wave;
+    }
+
+    #[inline]
+    fn reflected(&mut self) -> f32 {
+        let left_b = self.left.reflected();
+        let right_b = self.right.reflected();
+        self.difference = right_b - left_b;
+        self.b = right_b - self.left_reflection * self.difference;
+        self.b
+    }
+
+    fn waves(&self) -> (f32, f32) {
+        (self.a, self.b)
+    }
+}
+
+/// Flips the polarity of the port below it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Inverter<P> {
+    pub port: P,
+    a: f32,
+    b: f32,
+}
+
+impl<P: WdfElement> Inverter<P> {
+    pub fn new(port: P) -> Self {
+        Self {
+            port,
+            a: 0.0,
+            b: 0.0,
+        }
+    }
+}
+
+impl<P: WdfElement> WdfElement for Inverter<P> {
+    fn impedance(&self) -> f32 {
+        self.port.impedance()
+    }
+
+    fn propagate_impedance(&mut self) {
+        self.port.propagate_impedance();
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.port.set_sample_rate(sample_rate);
+    }
+
+    #[inline]
+    fn incident(&mut self, wave: f32) {
+        self.a = wave;
+        self.port.incident(-wave);
+    }
+
+    #[inline]
+    fn reflected(&mut self) -> f32 {
+        self.b = -self.port.reflected();
+        self.b
+    }
+
+    fn waves(&self) -> (f32, f32) {
+        (self.a, self.b)
+    }
+}
+
+/// Root that holds the tree's top port at a fixed voltage. At 0 V it's a short circuit.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct IdealVoltageSource {
+    voltage: f32,
+    a: f32,
+    b: f32,
+}
+
+impl IdealVoltageSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn set_voltage(&mut self, voltage: f32) {
+        self.voltage = voltage;
+    }
+
+    /// Runs one sample through `tree`.
+    #[inline]
+    pub fn process<T: WdfElement>(&mut self, tree: &mut T) {
+        self.a = tree.reflected();
+        self.b = 2.0 * self.voltage - self.a;
+        tree.incident(self.b);
+    }
+
+    /// Current the source pushes into the tree.
+    #[inline]
+    pub fn current<T: WdfElement>(&self, tree: &T) -> f32 {
+        (self.b - self.a) / (2.0 * tree.impedance())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DiodeSolver {
+    /// Iterates on the exact diode pair equation.
+    NewtonRaphson,
+    /// Closed form from Werner et al., exact for one diode and very close for a pair.
+    WrightOmega,
+}
+
+/// Root made of two diodes in antiparallel, the usual clipping pair.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct DiodePair {
+    saturation_current: f32,
+    thermal_voltage: f32,
+    solver: DiodeSolver,
+    a: f32,
+    b: f32,
+}
+
+impl DiodePair {
+    /// `thermal_voltage` already includes the ideality factor of the diodes.
+    pub fn new(saturation_current: f32, thermal_voltage: f32, solver: DiodeSolver) -> Self {
+        Self {
+            saturation_current,
+            thermal_voltage,
+            solver,
+            a: 0.0,
+            b: 0.0,
+        }
+    }
+
+    /// 1N914 silicon signal diodes.
+    pub fn silicon(solver: DiodeSolver) -> Self {
+        Self::new(2.52e-9, 1.752 * 25.85e-3, solver)
+    }
+
+    pub fn set_solver(&mut self, solver: DiodeSolver) {
+        self.solver = solver;
+    }
+
+    /// Voltage across the diodes after the last sample.
+    #[inline]
+    pub fn voltage(&self) -> f32 {
+        (self.a + self.b) * 0.5
+    }
+
+    /// Runs one sample through `tree`.
+    #[inline]
+    pub fn process<T: WdfElement>(&mut self, tree: &mut T) {
+        self.a = tree.reflected();
+        let impedance = tree.impedance();
+        self.b = match self.solver {
+            DiodeSolver::NewtonRaphson => self.solve_newton(self.a, impedance),
+            DiodeSolver::WrightOmega => self.solve_omega(self.a, impedance),
+        };
+        tree.incident(self.b);
+    }
+
+    #[inline]
+    fn solve_omega(&self, a: f32, impedance: f32) -> f32 {
+        let vt = self.thermal_voltage;
+        let r_is = impedance * self.saturation_current;
+        let sign = a.signum();
+        a + 2.0 * sign * (r_is - vt * omega4((r_is / vt).ln() + sign * a / vt + r_is / vt))
+    }
+
+    /// Solves `(a - v) / R = 2 Is sinh(v / Vt)` for the voltage, starting from the last one.
+    #[inline]
+    fn solve_newton(&self, a: f32, impedance: f32) -> f32 {
+        let vt = self.thermal_voltage;
+        let is = self.saturation_current;
+        let mut voltage = self.voltage().clamp(-a.abs(), a.abs());
+
+        for _ in 0..NEWTON_ITERATIONS {
+            let exponent = (voltage / vt).clamp(-MAX_EXPONENT, MAX_EXPONENT);
+            let f = (a - voltage) / impedance - 2.0 * is * exponent.sinh();
+            let slope = -1.0 / impedance - 2.0 * is / vt * exponent.cosh();
+            let step = f / slope;
+            voltage -= step;
+            if step.abs() < NEWTON_TOLERANCE {
+                break;
+            }
+        }
+
+        2.0 * voltage - a
+    }
+}
+
+/// Wright omega function, the fourth order approximation from D'Angelo et al.
+#[inline]
+fn omega4(x: f32) -> f32 {
+    let y = omega3(x);
+    y - (y - (x - y).exp()) / (y + 1.0)
+}
+
+#[inline]
+fn omega3(x: f32) -> f32 {
+    const X1: f32 = -3.3414595;
+    const X2: f32 = 8.0;
+    const A: f32 = -0.0013142931;
+    const B: f32 = 0.047759313;
+    const C: f32 = 0.36319527;
+    const D: f32 = 0.63131833;
+
+    if x < X1 {
+        0.0
+    } else if x < X2 {
+        ((A * x + B) * x + C) * x + D
+    } else {
+        x - x.ln()
+    }
+}
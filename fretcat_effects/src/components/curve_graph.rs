@@ -0,0 +1,182 @@
+use nih_plug::vizia::{
+    prelude::*,
+    vg::{Paint, Path},
+};
+
+use crate::common::map_value;
+
+const NODE_RADIUS: f32 = 5.0;
+const NODE_GRAB_RADIUS: f32 = 12.0;
+
+#[derive(Debug, Clone, Copy, Data, PartialEq, Default)]
+pub struct CurveNode {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Transfer curve plot with both axes going from -1 to 1, and draggable nodes on top.
+/// The curve values are expected evenly spaced along the x axis.
+pub struct CurveGraph<C, N>
+where
+    C: Lens<Target = Vec<f32>>,
+    N: Lens<Target = Vec<CurveNode>>,
+{
+    curve: C,
+    nodes: N,
+    dragging: Option<usize>,
+    on_drag: Option<Box<dyn Fn(&mut EventContext, usize, f32, f32)>>,
+}
+
+impl<C, N> CurveGraph<C, N>
+where
+    C: Lens<Target = Vec<f32>>,
+    N: Lens<Target = Vec<CurveNode>>,
+{
+    pub fn new(cx: &mut Context, curve: C, nodes: N) -> Handle<Self> {
+        Self {
+            curve,
+            nodes,
+            dragging: None,
+            on_drag: None,
+        }
+        .build(cx, |_| {})
+    }
+
+    fn to_x(&self, value: f32, bounds: &BoundingBox) -> f32 {
+        map_value(value, -1.0, 1.0, bounds.left(), bounds.right())
+    }
+
+    fn to_y(&self, value: f32, bounds: &BoundingBox) -> f32 {
+        map_value(value, -1.0, 1.0, bounds.bottom(), bounds.top())
+    }
+
+    fn from_x(&self, x: f32, bounds: &BoundingBox) -> f32 {
+        map_value(x, bounds.left(), bounds.right(), -1.0, 1.0)
+    }
+
+    fn from_y(&self, y: f32, bounds: &BoundingBox) -> f32 {
+        map_value(y, bounds.top(), bounds.bottom(), 1.0, -1.0)
+    }
+}
+
+pub trait CurveGraphModifier {
+    fn on_drag<F: Fn(&mut EventContext, usize, f32, f32) + 'static>(self, f: F) -> Self;
+}
+
+impl<'a, C, N> CurveGraphModifier for Handle<'a, CurveGraph<C, N>>
+where
+    C: Lens<Target = Vec<f32>>,
+    N: Lens<Target = Vec<CurveNode>>,
+{
+    fn on_drag<F: Fn(&mut EventContext, usize, f32, f32) + 'static>(self, f: F) -> Self {
+        self.modify(|view: &mut CurveGraph<C, N>| view.on_drag = Some(Box::new(f)))
+    }
+}
+
+impl<C, N> View for CurveGraph<C, N>
+where
+    C: Lens<Target = Vec<f32>>,
+    N: Lens<Target = Vec<CurveNode>>,
+{
+    fn element(&self) -> Option<&'static str> {
+        Some("curve-graph")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, _| match window_event {
+            WindowEvent::MouseDown(MouseButton::Left) => {
+                if cx.is_disabled() {
+                    return;
+                }
+
+                let bounds = cx.bounds();
+                let (x, y) = (cx.mouse().cursorx, cx.mouse().cursory);
+                let grab_radius = NODE_GRAB_RADIUS * cx.scale_factor();
+
+                self.dragging = self
+                    .nodes
+                    .get(cx)
+                    .iter()
+                    .enumerate()
+                    .map(|(index, node)| {
+                        let dx = self.to_x(node.x, &bounds) - x;
+                        let dy = self.to_y(node.y, &bounds) - y;
+                        (index, (dx * dx + dy * dy).sqrt())
+                    })
+                    .filter(|(_, distance)| *distance <= grab_radius)
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(index, _)| index);
+
+                if self.dragging.is_some() {
+                    cx.capture();
+                }
+            }
+            WindowEvent::MouseUp(MouseButton::Left) => {
+                if self.dragging.take().is_some() {
+                    cx.release();
+                }
+            }
+            WindowEvent::MouseMove(x, y) => {
+                if let Some(index) = self.dragging {
+                    let bounds = cx.bounds();
+                    let value_x = self.from_x(*x, &bounds);
+                    let value_y = self.from_y(*y, &bounds);
+                    if let Some(f) = &self.on_drag {
+                        (f)(cx, index, value_x, value_y);
+                    }
+                }
+            }
+            _ => {}
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bg = cx.background_color();
+        let line_color = cx.font_color();
+        let grid_color = cx.border_color();
+        let node_color = cx.selection_color();
+
+        let bounds = cx.bounds();
+
+        let mut bg_path = Path::new();
+        bg_path.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&bg_path, &Paint::color(bg.into()));
+
+        let mut grid = Path::new();
+        let (center_x, center_y) = (self.to_x(0.0, &bounds), self.to_y(0.0, &bounds));
+        grid.move_to(bounds.left(), center_y);
+        grid.line_to(bounds.right(), center_y);
+        grid.move_to(center_x, bounds.top());
+        grid.line_to(center_x, bounds.bottom());
+        let mut grid_paint = Paint::color(grid_color.into());
+        grid_paint.set_line_width(1.0);
+        canvas.stroke_path(&grid, &grid_paint);
+
+        let curve = self.curve.get(cx);
+        let last = (curve.len().max(2) - 1) as f32;
+        let mut curve_line = Path::new();
+        curve.iter().enumerate().for_each(|(i, value)| {
+            let x = bounds.x + (i as f32 / last) * bounds.w;
+            let y = self.to_y(*value, &bounds);
+            if i == 0 {
+                curve_line.move_to(x, y);
+            } else {
+                curve_line.line_to(x, y);
+            }
+        });
+        let mut paint = Paint::color(line_color.into());
+        paint.set_line_width(1.5);
+        canvas.stroke_path(&curve_line, &paint);
+
+        let radius = NODE_RADIUS * cx.scale_factor();
+        self.nodes.get(cx).iter().for_each(|node| {
+            let mut circle = Path::new();
+            circle.circle(
+                self.to_x(node.x, &bounds),
+                self.to_y(node.y, &bounds),
+                radius,
+            );
+            canvas.fill_path(&circle, &Paint::color(node_color.into()));
+        });
+    }
+}
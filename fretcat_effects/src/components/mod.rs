@@ -5,4 +5,10 @@ mod named_knob;
 pub use named_knob::*;
 
 mod graph;
-pub use graph::*;
\ No newline at end of file
+pub use graph::*;
+
+mod response_graph;
+pub use response_graph::*;
+
+mod curve_graph;
+pub use curve_graph::*;
@@ -0,0 +1,220 @@
+use nih_plug::vizia::{
+    prelude::*,
+    vg::{Paint, Path},
+};
+
+use crate::common::map_value;
+
+pub const GRAPH_MIN_FREQ: f32 = 20.0;
+pub const GRAPH_MAX_FREQ: f32 = 20000.0;
+
+const NODE_RADIUS: f32 = 5.0;
+const NODE_GRAB_RADIUS: f32 = 12.0;
+const GRID_FREQUENCIES: [f32; 3] = [100.0, 1000.0, 10000.0];
+
+/// Log spaced frequencies between [`GRAPH_MIN_FREQ`] and [`GRAPH_MAX_FREQ`],
+/// the x axis a [`ResponseGraph`] expects its response values to follow.
+pub fn graph_frequencies(resolution: usize) -> Vec<f32> {
+    let ratio = GRAPH_MAX_FREQ / GRAPH_MIN_FREQ;
+    (0..resolution)
+        .map(|i| GRAPH_MIN_FREQ * ratio.powf(i as f32 / (resolution - 1).max(1) as f32))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Data, PartialEq, Default)]
+pub struct GraphNode {
+    pub frequency: f32,
+    pub gain_db: f32,
+    pub enabled: bool,
+    pub selected: bool,
+}
+
+/// Magnitude response plot on a log frequency axis with draggable nodes.
+pub struct ResponseGraph<R, N>
+where
+    R: Lens<Target = Vec<f32>>,
+    N: Lens<Target = Vec<GraphNode>>,
+{
+    response: R,
+    nodes: N,
+    db_range: f32,
+    dragging: Option<usize>,
+    on_drag: Option<Box<dyn Fn(&mut EventContext, usize, f32, f32)>>,
+}
+
+impl<R, N> ResponseGraph<R, N>
+where
+    R: Lens<Target = Vec<f32>>,
+    N: Lens<Target = Vec<GraphNode>>,
+{
+    pub fn new(cx: &mut Context, response: R, nodes: N, db_range: f32) -> Handle<Self> {
+        Self {
+            response,
+            nodes,
+            db_range,
+            dragging: None,
+            on_drag: None,
+        }
+        .build(cx, |_| {})
+    }
+
+    fn frequency_to_x(&self, frequency: f32, bounds: &BoundingBox) -> f32 {
+        let ratio = (frequency / GRAPH_MIN_FREQ).ln() / (GRAPH_MAX_FREQ / GRAPH_MIN_FREQ).ln();
+        bounds.x + ratio.clamp(0.0, 1.0) * bounds.w
+    }
+
+    fn x_to_frequency(&self, x: f32, bounds: &BoundingBox) -> f32 {
+        let ratio = ((x - bounds.x) / bounds.w).clamp(0.0, 1.0);
+        GRAPH_MIN_FREQ * (GRAPH_MAX_FREQ / GRAPH_MIN_FREQ).powf(ratio)
+    }
+
+    fn db_to_y(&self, db: f32, bounds: &BoundingBox) -> f32 {
+        map_value(
+            db,
+            -self.db_range,
+            self.db_range,
+            bounds.bottom(),
+            bounds.top(),
+        )
+    }
+
+    fn y_to_db(&self, y: f32, bounds: &BoundingBox) -> f32 {
+        map_value(
+            y,
+            bounds.top(),
+            bounds.bottom(),
+            self.db_range,
+            -self.db_range,
+        )
+    }
+}
+
+pub trait ResponseGraphModifier {
+    fn on_drag<F: Fn(&mut EventContext, usize, f32, f32) + 'static>(self, f: F) -> Self;
+}
+
+impl<'a, R, N> ResponseGraphModifier for Handle<'a, ResponseGraph<R, N>>
+where
+    R: Lens<Target = Vec<f32>>,
+    N: Lens<Target = Vec<GraphNode>>,
+{
+    fn on_drag<F: Fn(&mut EventContext, usize, f32, f32) + 'static>(self, f: F) -> Self {
+        self.modify(|view: &mut ResponseGraph<R, N>| view.on_drag = Some(Box::new(f)))
+    }
+}
+
+impl<R, N> View for ResponseGraph<R, N>
+where
+    R: Lens<Target = Vec<f32>>,
+    N: Lens<Target = Vec<GraphNode>>,
+{
+    fn element(&self) -> Option<&'static str> {
+        Some("response-graph")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, _| match window_event {
+            WindowEvent::MouseDown(MouseButton::Left) => {
+                if cx.is_disabled() {
+                    return;
+                }
+
+                let bounds = cx.bounds();
+                let (x, y) = (cx.mouse().cursorx, cx.mouse().cursory);
+                let grab_radius = NODE_GRAB_RADIUS * cx.scale_factor();
+
+                self.dragging = self
+                    .nodes
+                    .get(cx)
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, node)| node.enabled)
+                    .map(|(index, node)| {
+                        let dx = self.frequency_to_x(node.frequency, &bounds) - x;
+                        let dy = self.db_to_y(node.gain_db, &bounds) - y;
+                        (index, (dx * dx + dy * dy).sqrt())
+                    })
+                    .filter(|(_, distance)| *distance <= grab_radius)
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(index, _)| index);
+
+                if self.dragging.is_some() {
+                    cx.capture();
+                }
+            }
+            WindowEvent::MouseUp(MouseButton::Left) => {
+                if self.dragging.take().is_some() {
+                    cx.release();
+                }
+            }
+            WindowEvent::MouseMove(x, y) => {
+                if let Some(index) = self.dragging {
+                    let bounds = cx.bounds();
+                    let frequency = self.x_to_frequency(*x, &bounds);
+                    let gain_db = self.y_to_db(*y, &bounds);
+                    if let Some(f) = &self.on_drag {
+                        (f)(cx, index, frequency, gain_db);
+                    }
+                }
+            }
+            _ => {}
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bg = cx.background_color();
+        let line_color = cx.font_color();
+        let grid_color = cx.border_color();
+        let node_color = cx.selection_color();
+
+        let bounds = cx.bounds();
+
+        let mut bg_path = Path::new();
+        bg_path.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&bg_path, &Paint::color(bg.into()));
+
+        let mut grid = Path::new();
+        let zero_db = self.db_to_y(0.0, &bounds);
+        grid.move_to(bounds.x, zero_db);
+        grid.line_to(bounds.right(), zero_db);
+        GRID_FREQUENCIES.iter().for_each(|frequency| {
+            let x = self.frequency_to_x(*frequency, &bounds);
+            grid.move_to(x, bounds.top());
+            grid.line_to(x, bounds.bottom());
+        });
+        let mut grid_paint = Paint::color(grid_color.into());
+        grid_paint.set_line_width(1.0);
+        canvas.stroke_path(&grid, &grid_paint);
+
+        let response = self.response.get(cx);
+        let last = (response.len().max(2) - 1) as f32;
+        let mut graph_line = Path::new();
+        response.iter().enumerate().for_each(|(i, db)| {
+            let x = bounds.x + (i as f32 / last) * bounds.w;
+            let y = self.db_to_y(*db, &bounds);
+            if i == 0 {
+                graph_line.move_to(x, y);
+            } else {
+                graph_line.line_to(x, y);
+            }
+        });
+        let mut paint = Paint::color(line_color.into());
+        paint.set_line_width(1.5);
+        canvas.stroke_path(&graph_line, &paint);
+
+        let radius = NODE_RADIUS * cx.scale_factor();
+        self.nodes
+            .get(cx)
+            .iter()
+            .filter(|node| node.enabled)
+            .for_each(|node| {
+                let mut circle = Path::new();
+                circle.circle(
+                    self.frequency_to_x(node.frequency, &bounds),
+                    self.db_to_y(node.gain_db, &bounds),
+                    if node.selected { radius * 1.5 } else { radius },
+                );
+                canvas.fill_path(&circle, &Paint::color(node_color.into()));
+            });
+    }
+}
@@ -8,12 +8,18 @@ use crate::prelude::{Frame, Transport};
 #[derive(Debug)]
 pub struct EffectHandle<T: AudioEffect + ?Sized> {
     active: Arc<AtomicBool>,
+    // Set when the effect is switched back on, the audio thread clears it before the next block
+    reactivated: Arc<AtomicBool>,
     handle: Arc<T>,
 }
 
 impl<T: AudioEffect + ?Sized> Clone for EffectHandle<T> {
     fn clone(&self) -> Self {
-        EffectHandle { active: self.active.clone(), handle: self.handle.clone() }
+        EffectHandle {
+            active: self.active.clone(),
+            reactivated: self.reactivated.clone(),
+            handle: self.handle.clone(),
+        }
     }
 }
 
@@ -47,7 +53,8 @@ impl<T: AudioEffect> From<EffectHandle<dyn AudioEffect>> for EffectHandle<T> {
     fn from(value: EffectHandle<dyn AudioEffect>) -> Self {
         EffectHandle{ 
             handle: value.handle().into_any_arc().downcast::<T>().unwrap(),
-            active: value.active.clone()
+            active: value.active.clone(),
+            reactivated: value.reactivated.clone(),
         }
     }
 }
@@ -56,12 +63,16 @@ impl<T: AudioEffect + ?Sized> EffectHandle<T> {
     pub fn new(handle: Arc<T>) -> Self {
         Self {
             active: Arc::new(true.into()),
+            reactivated: Arc::new(false.into()),
             handle: handle.clone()
         }
     }
 
     pub fn process_if_active(&mut self, input_buffer: &mut Frame, transport: &Transport) {
         if self.active.load(std::sync::atomic::Ordering::Relaxed) {
+            if self.reactivated.swap(false, std::sync::atomic::Ordering::Relaxed) {
+                self.get_mut().reactivate();
+            }
             self.process(input_buffer, transport)
         }
     }
@@ -71,7 +82,10 @@ impl<T: AudioEffect + ?Sized> EffectHandle<T> {
     }
 
     pub fn set_active(&mut self, active: bool) {
-        self.active.store(active, std::sync::atomic::Ordering::Relaxed);
+        let was_active = self.active.swap(active, std::sync::atomic::Ordering::Relaxed);
+        if active && !was_active {
+            self.reactivated.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
     }
 
     pub fn active(&self) -> bool {
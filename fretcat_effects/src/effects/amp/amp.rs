@@ -0,0 +1,536 @@
+use crate::prelude::*;
+
+const MAX_STAGES: usize = 3;
+// Stages after the first run at a fixed gain, the gain knob only drives the first one
+const LATER_STAGE_GAIN: f32 = 3.0;
+const STAGE_BIAS: [f32; MAX_STAGES] = [0.2, 0.35, 0.5];
+// A triode clips harder when the grid starts conducting than when it cuts off
+const CUTOFF_KNEE: f32 = 0.6;
+
+// The passive tone stack loses a lot of level, the recovery stage makes it back
+const TONE_STACK_MAKEUP_DB: f32 = 12.0;
+const PRESENCE_HZ: f32 = 3000.0;
+const DEPTH_HZ: f32 = 100.0;
+const MAX_SHELF_DB: f32 = 10.0;
+
+const POWER_AMP_DRIVE: f32 = 2.0;
+const SAG_ATTACK_MS: f32 = 10.0;
+const SAG_RELEASE_MS: f32 = 150.0;
+// How far the supply rail drops at full sag with the power amp flat out
+const MAX_SAG: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum AmpChannel {
+    Clean,
+    Crunch,
+    Lead,
+}
+
+impl AmpChannel {
+    pub const ALL: [AmpChannel; 3] = [AmpChannel::Clean, AmpChannel::Crunch, AmpChannel::Lead];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AmpChannel::Clean => "CLEAN",
+            AmpChannel::Crunch => "CRUNCH",
+            AmpChannel::Lead => "LEAD",
+        }
+    }
+
+    fn stages(&self) -> usize {
+        match self {
+            AmpChannel::Clean => 1,
+            AmpChannel::Crunch => 2,
+            AmpChannel::Lead => 3,
+        }
+    }
+
+    /// Drive of the first stage with the gain knob at its lowest and highest.
+    fn drive_range(&self) -> (f32, f32) {
+        match self {
+            AmpChannel::Clean => (1.0, 6.0),
+            AmpChannel::Crunch => (3.0, 40.0),
+            AmpChannel::Lead => (8.0, 150.0),
+        }
+    }
+
+    /// High gain channels use smaller coupling caps so the low end doesn't turn to mush.
+    fn coupling_hz(&self) -> f32 {
+        match self {
+            AmpChannel::Clean => 30.0,
+            AmpChannel::Crunch => 80.0,
+            AmpChannel::Lead => 120.0,
+        }
+    }
+
+    fn stage_lowpass_hz(&self) -> f32 {
+        match self {
+            AmpChannel::Clean => 12000.0,
+            AmpChannel::Crunch => 10000.0,
+            AmpChannel::Lead => 8000.0,
+        }
+    }
+}
+
+/// One triode gain stage followed by the coupling cap and Miller capacitance of the next.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct TubeStage {
+    bias: f32,
+    coupling: DCBlock,
+    lowpass: ButterLowpass,
+}
+
+impl TubeStage {
+    fn new(bias: f32, channel: AmpChannel) -> Self {
+        Self {
+            bias,
+            coupling: DCBlock::new(channel.coupling_hz()),
+            lowpass: ButterLowpass::new(channel.stage_lowpass_hz()),
+        }
+    }
+
+    fn configure(&mut self, channel: AmpChannel) {
+        self.coupling.set_cutoff(channel.coupling_hz());
+        self.lowpass.set_cutoff(channel.stage_lowpass_hz());
+        self.coupling.reset();
+        self.lowpass.reset();
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.coupling.set_sample_rate(sample_rate);
+        self.lowpass.set_sample_rate(sample_rate);
+    }
+
+    #[inline]
+    fn shape(v: f32) -> f32 {
+        if v >= 0.0 {
+            v.tanh()
+        } else {
+            CUTOFF_KNEE * (v / CUTOFF_KNEE).tanh()
+        }
+    }
+
+    /// The plate output is inverted, so each stage clips the opposite side of the wave to the last.
+    #[inline]
+    fn tick(&mut self, input: f32, drive: f32) -> f32 {
+        let shaped = Self::shape(input * drive + self.bias) - Self::shape(self.bias);
+        -self.lowpass.tick(self.coupling.tick(shaped))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Amp {
+    channel: AmpChannel,
+    model: ToneStackModel,
+    gain: f32,
+    bass: f32,
+    mid: f32,
+    treble: f32,
+    presence: f32,
+    depth: f32,
+    sag: f32,
+    master_db: f32,
+
+    sample_rate: f32,
+    stages: [[TubeStage; MAX_STAGES]; NUM_CHANNELS],
+    tone_stacks: [ToneStack; NUM_CHANNELS],
+    presence_filters: [SvfFilter; NUM_CHANNELS],
+    depth_filters: [SvfFilter; NUM_CHANNELS],
+    sag_envelope: f32,
+}
+
+impl Default for Amp {
+    fn default() -> Self {
+        let channel = AmpChannel::Crunch;
+        let model = ToneStackModel::Marshall;
+        let stages = STAGE_BIAS.map(|bias| TubeStage::new(bias, channel));
+        let mut amp = Self {
+            channel,
+            model,
+            gain: 0.5,
+            bass: 0.5,
+            mid: 0.5,
+            treble: 0.5,
+            presence: 0.3,
+            depth: 0.3,
+            sag: 0.3,
+            master_db: -6.0,
+            sample_rate: 44100.0,
+            stages: [stages; NUM_CHANNELS],
+            tone_stacks: [ToneStack::new(model); NUM_CHANNELS],
+            presence_filters: [SvfFilter::new(FilterMode::HighShelf, 44100.0, PRESENCE_HZ, 0.707);
+                NUM_CHANNELS],
+            depth_filters: [SvfFilter::new(FilterMode::LowShelf, 44100.0, DEPTH_HZ, 0.707);
+                NUM_CHANNELS],
+            sag_envelope: 0.0,
+        };
+        amp.update_tone_stacks();
+        amp.update_shelves();
+        amp
+    }
+}
+
+impl PartialEq for Amp {
+    fn eq(&self, other: &Self) -> bool {
+        self.channel == other.channel
+            && self.model == other.model
+            && self.gain == other.gain
+            && self.bass == other.bass
+            && self.mid == other.mid
+            && self.treble == other.treble
+            && self.presence == other.presence
+            && self.depth == other.depth
+            && self.sag == other.sag
+            && self.master_db == other.master_db
+    }
+}
+
+impl Amp {
+    pub fn channel(&self) -> AmpChannel {
+        self.channel
+    }
+
+    pub fn model(&self) -> ToneStackModel {
+        self.model
+    }
+
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    pub fn bass(&self) -> f32 {
+        self.bass
+    }
+
+    pub fn mid(&self) -> f32 {
+        self.mid
+    }
+
+    pub fn treble(&self) -> f32 {
+        self.treble
+    }
+
+    pub fn presence(&self) -> f32 {
+        self.presence
+    }
+
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    pub fn sag(&self) -> f32 {
+        self.sag
+    }
+
+    pub fn master_db(&self) -> f32 {
+        self.master_db
+    }
+
+    pub fn set_channel(&mut self, channel: AmpChannel) {
+        self.channel = channel;
+        self.stages
+            .iter_mut()
+            .flatten()
+            .for_each(|stage| stage.configure(channel));
+    }
+
+    pub fn set_model(&mut self, model: ToneStackModel) {
+        self.model = model;
+        self.tone_stacks
+            .iter_mut()
+            .for_each(|tone_stack| tone_stack.set_model(model));
+    }
+
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain.clamp(0.0, 1.0);
+    }
+
+    pub fn set_bass(&mut self, bass: f32) {
+        self.bass = bass.clamp(0.0, 1.0);
+        self.update_tone_stacks();
+    }
+
+    pub fn set_mid(&mut self, mid: f32) {
+        self.mid = mid.clamp(0.0, 1.0);
+        self.update_tone_stacks();
+    }
+
+    pub fn set_treble(&mut self, treble: f32) {
+        self.treble = treble.clamp(0.0, 1.0);
+        self.update_tone_stacks();
+    }
+
+    /// Lifts the upper mids the way the power amp's feedback loop does on a real amp.
+    pub fn set_presence(&mut self, presence: f32) {
+        self.presence = presence.clamp(0.0, 1.0);
+        self.update_shelves();
+    }
+
+    /// Low end counterpart of presence, sometimes called resonance.
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth.clamp(0.0, 1.0);
+        self.update_shelves();
+    }
+
+    /// How much the supply droops under load, compressing hard hits.
+    pub fn set_sag(&mut self, sag: f32) {
+        self.sag = sag.clamp(0.0, 1.0);
+    }
+
+    pub fn set_master_db(&mut self, master_db: f32) {
+        self.master_db = master_db;
+    }
+
+    fn update_tone_stacks(&mut self) {
+        let (bass, mid, treble) = (self.bass, self.mid, self.treble);
+        self.tone_stacks
+            .iter_mut()
+            .for_each(|tone_stack| tone_stack.set_controls(bass, mid, treble));
+    }
+
+    fn update_shelves(&mut self) {
+        let presence = db_to_gain(self.presence * MAX_SHELF_DB);
+        let depth = db_to_gain(self.depth * MAX_SHELF_DB);
+        self.presence_filters
+            .iter_mut()
+            .for_each(|filter| filter.set_gain(presence));
+        self.depth_filters
+            .iter_mut()
+            .for_each(|filter| filter.set_gain(depth));
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.stages
+            .iter_mut()
+            .flatten()
+            .for_each(|stage| stage.set_sample_rate(sample_rate));
+        self.tone_stacks
+            .iter_mut()
+            .for_each(|tone_stack| tone_stack.set_sample_rate(sample_rate));
+        self.presence_filters
+            .iter_mut()
+            .chain(self.depth_filters.iter_mut())
+            .for_each(|filter| filter.set_sample_rate(sample_rate));
+    }
+}
+
+impl AudioEffect for Amp {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        let (min_drive, max_drive) = self.channel.drive_range();
+        let drive = min_drive * (max_drive / min_drive).powf(self.gain);
+        let active_stages = self.channel.stages();
+        let makeup = db_to_gain(TONE_STACK_MAKEUP_DB);
+        let master = db_to_gain(self.master_db);
+        let sag_depth = self.sag * MAX_SAG;
+        let sag_attack = one_pole_coeff(SAG_ATTACK_MS, self.sample_rate);
+        let sag_release = one_pole_coeff(SAG_RELEASE_MS, self.sample_rate);
+
+        let Self {
+            stages,
+            tone_stacks,
+            presence_filters,
+            depth_filters,
+            sag_envelope,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            // Both sides share one power supply, like the two halves of a push-pull output
+            let supply = 1.0 - sag_depth * sag_envelope.min(1.0);
+            let mut demand: f32 = 0.0;
+
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let mut signal = *sample;
+                for (index, stage) in stages[channel][..active_stages].iter_mut().enumerate() {
+                    let stage_drive = if index == 0 { drive } else { LATER_STAGE_GAIN };
+                    signal = stage.tick(signal, stage_drive);
+                }
+
+                signal = tone_stacks[channel].tick(signal) * makeup;
+                signal = depth_filters[channel].tick(presence_filters[channel].tick(signal));
+
+                let power_input = signal * POWER_AMP_DRIVE;
+                demand = demand.max(power_input.abs());
+                *sample = supply * (power_input / supply).tanh() * master;
+            }
+
+            let coeff = if demand > *sag_envelope {
+                sag_attack
+            } else {
+                sag_release
+            };
+            *sag_envelope = demand + coeff * (*sag_envelope - demand);
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        AmpView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        200.0
+    }
+}
+
+enum AmpEvent {
+    SetChannel(AmpChannel),
+    CycleModel,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct AmpView {
+    #[msg]
+    gain: f32,
+    #[msg]
+    bass: f32,
+    #[msg]
+    mid: f32,
+    #[msg]
+    treble: f32,
+    #[msg]
+    presence: f32,
+    #[msg]
+    depth: f32,
+    #[msg]
+    sag: f32,
+    #[msg]
+    master: f32,
+
+    channel: AmpChannel,
+    model: ToneStackModel,
+
+    #[lens(ignore)]
+    handle: EffectHandle<Amp>,
+}
+
+impl AmpView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<Amp>) -> Handle<Self> {
+        Self {
+            gain: handle.gain() * 10.0,
+            bass: handle.bass() * 10.0,
+            mid: handle.mid() * 10.0,
+            treble: handle.treble() * 10.0,
+            presence: handle.presence() * 10.0,
+            depth: handle.depth() * 10.0,
+            sag: handle.sag() * 100.0,
+            master: handle.master_db(),
+            channel: handle.channel(),
+            model: handle.model(),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            VStack::new(cx, |cx| {
+                HStack::new(cx, |cx| {
+                    VStack::new(cx, |cx| {
+                        for channel in AmpChannel::ALL {
+                            Button::new(
+                                cx,
+                                move |ex| ex.emit(AmpEvent::SetChannel(channel)),
+                                |cx| Label::new(cx, channel.name()),
+                            )
+                            .class("amp-option")
+                            .toggle_class(
+                                "option-enabled",
+                                Self::channel.map(move |selected| *selected == channel),
+                            );
+                        }
+                    })
+                    .class("amp-options");
+                    NamedKnob::new(cx, "Gain", Self::gain, false, 0.0..10.0)
+                        .on_changing(|ex, val| ex.emit(Message::Gain(val)));
+                    NamedKnob::new(cx, "Sag", Self::sag, false, 0.0..100.0)
+                        .on_changing(|ex, val| ex.emit(Message::Sag(val)));
+                    NamedKnob::new(cx, "Master", Self::master, true, -30.0..6.0)
+                        .on_changing(|ex, val| ex.emit(Message::Master(val)));
+                })
+                .class("amp-row");
+                HStack::new(cx, |cx| {
+                    VStack::new(cx, |cx| {
+                        Button::new(
+                            cx,
+                            |ex| ex.emit(AmpEvent::CycleModel),
+                            |cx| Label::new(cx, Self::model.map(|model| model.name())),
+                        )
+                        .class("amp-option");
+                    })
+                    .class("amp-options");
+                    NamedKnob::new(cx, "Bass", Self::bass, false, 0.0..10.0)
+                        .on_changing(|ex, val| ex.emit(Message::Bass(val)));
+                    NamedKnob::new(cx, "Mid", Self::mid, false, 0.0..10.0)
+                        .on_changing(|ex, val| ex.emit(Message::Mid(val)));
+                    NamedKnob::new(cx, "Treble", Self::treble, false, 0.0..10.0)
+                        .on_changing(|ex, val| ex.emit(Message::Treble(val)));
+                    NamedKnob::new(cx, "Presence", Self::presence, false, 0.0..10.0)
+                        .on_changing(|ex, val| ex.emit(Message::Presence(val)));
+                    NamedKnob::new(cx, "Depth", Self::depth, false, 0.0..10.0)
+                        .on_changing(|ex, val| ex.emit(Message::Depth(val)));
+                })
+                .class("amp-row");
+            });
+            Label::new(cx, "AMP").class("effect-title");
+        })
+    }
+}
+
+impl View for AmpView {
+    fn element(&self) -> Option<&'static str> {
+        Some("amp")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Gain(val) => {
+                self.gain = *val;
+                self.handle.set_gain(*val / 10.0);
+            }
+            Message::Bass(val) => {
+                self.bass = *val;
+                self.handle.set_bass(*val / 10.0);
+            }
+            Message::Mid(val) => {
+                self.mid = *val;
+                self.handle.set_mid(*val / 10.0);
+            }
+            Message::Treble(val) => {
+                self.treble = *val;
+                self.handle.set_treble(*val / 10.0);
+            }
+            Message::Presence(val) => {
+                self.presence = *val;
+                self.handle.set_presence(*val / 10.0);
+            }
+            Message::Depth(val) => {
+                self.depth = *val;
+                self.handle.set_depth(*val / 10.0);
+            }
+            Message::Sag(val) => {
+                self.sag = *val;
+                self.handle.set_sag(*val / 100.0);
+            }
+            Message::Master(val) => {
+                self.master = *val;
+                self.handle.set_master_db(*val);
+            }
+        });
+
+        event.map(|event, _| match event {
+            AmpEvent::SetChannel(channel) => {
+                self.channel = *channel;
+                self.handle.set_channel(*channel);
+            }
+            AmpEvent::CycleModel => {
+                let current = ToneStackModel::ALL
+                    .iter()
+                    .position(|model| *model == self.model)
+                    .unwrap_or(0);
+                self.model = ToneStackModel::ALL[(current + 1) % ToneStackModel::ALL.len()];
+                self.handle.set_model(self.model);
+            }
+        });
+    }
+}
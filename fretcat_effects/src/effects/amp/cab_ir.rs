@@ -0,0 +1,599 @@
+use std::{f32::consts::PI, path::Path, sync::Arc};
+
+use hound::{SampleFormat, WavReader};
+
+use crate::prelude::*;
+
+const IR_SLOTS: usize = 2;
+const MAX_IR_MS: f32 = 500.0;
+const BLOCK_SIZE: usize = 128;
+// Zero crossings on either side of the resampling kernel
+const SINC_ZERO_CROSSINGS: usize = 16;
+// Cutting a response off mid-ring clicks, the tail is faded over this long instead
+const TRIM_FADE_MS: f32 = 2.0;
+
+/// Mono impulse response at the rate it was recorded.
+#[derive(Debug, Clone)]
+struct ImpulseResponse {
+    samples: Vec<f32>,
+    sample_rate: f32,
+    gain: f32,
+}
+
+impl ImpulseResponse {
+    fn load(path: &str) -> Result<Self, hound::Error> {
+        let mut reader = WavReader::open(path)?;
+        let spec = reader.spec();
+        let samples: Vec<f32> = match spec.sample_format {
+            SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            SampleFormat::Int => {
+                let scale = 1.0 / (1_i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f32 * scale))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        let sample_rate = spec.sample_rate as f32;
+        let channels = spec.channels.max(1) as usize;
+        let max_len = (MAX_IR_MS * 0.001 * sample_rate) as usize;
+        // Stereo responses are folded down, the cab sits in the middle anyway
+        let samples: Vec<f32> = samples
+            .chunks(channels)
+            .take(max_len)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+
+        // Brings every file to the same loudness on broadband material
+        let energy = samples.iter().map(|sample| sample * sample).sum::<f32>();
+        let gain = if energy > 0.0 { energy.sqrt().recip() } else { 0.0 };
+
+        Ok(Self {
+            samples,
+            sample_rate,
+            gain,
+        })
+    }
+
+    fn duration(&self) -> f32 {
+        self.samples.len() as f32 / self.sample_rate
+    }
+
+    /// Reads the response `time` seconds in, scaled for playback at `sample_rate`.
+    /// Band-limited to the lower of the two rates, so a response played back at a lower rate
+    /// than it was recorded at doesn't fold its top octave back down.
+    fn sample_at(&self, time: f32, sample_rate: f32) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+
+        let position = time * self.sample_rate;
+        let cutoff = (sample_rate / self.sample_rate).min(1.0);
+        // The kernel widens as the cutoff drops, so it keeps the same number of zero crossings
+        let half_width = SINC_ZERO_CROSSINGS as f32 / cutoff;
+        let first = (position - half_width).ceil().max(0.0) as usize;
+        let last = ((position + half_width).floor().max(0.0) as usize).min(self.samples.len() - 1);
+
+        let value = (first..=last)
+            .map(|index| {
+                let distance = index as f32 - position;
+                let x = PI * distance * cutoff;
+                let sinc = if x.abs() < 1e-6 { 1.0 } else { x.sin() / x };
+                // Blackman window
+                let phase = PI * distance / half_width;
+                let window = 0.42 + 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+                self.samples[index] * cutoff * sinc * window
+            })
+            .sum::<f32>();
+
+        // A response at a higher rate has more samples to sum, which would change its level
+        let rate_ratio = self.sample_rate / sample_rate;
+        value * self.gain * rate_ratio
+    }
+
+    /// The whole response at `sample_rate`.
+    fn resample(&self, sample_rate: f32) -> Vec<f32> {
+        let length = (self.duration() * sample_rate) as usize;
+        (0..length)
+            .map(|index| self.sample_at(index as f32 / sample_rate, sample_rate))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CabIrSettings {
+    paths: [Option<String>; IR_SLOTS],
+    blend: f32,
+    start_ms: f32,
+    length_ms: f32,
+    low_cut_hz: f32,
+    high_cut_hz: f32,
+    mix: f32,
+}
+
+/// The part the audio thread runs. It's rebuilt away from it whenever the responses
+/// or the trim change and swapped in between blocks.
+#[derive(Debug)]
+struct CabEngine {
+    convolvers: [Convolver; NUM_CHANNELS],
+    has_ir: bool,
+}
+
+impl CabEngine {
+    fn empty() -> Self {
+        Self {
+            convolvers: [Convolver::new(BLOCK_SIZE, 0), Convolver::new(BLOCK_SIZE, 0)],
+            has_ir: false,
+        }
+    }
+
+    fn carry_over(&mut self, previous: &Self) {
+        for (convolver, previous) in self.convolvers.iter_mut().zip(&previous.convolvers) {
+            convolver.carry_over(previous);
+        }
+    }
+}
+
+/// Speaker cabinet from impulse responses loaded off disk.
+/// Only the file paths go into presets, the responses are read when the cab is prepared to play.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(from = "CabIrSettings", into = "CabIrSettings")]
+pub struct CabIr {
+    paths: [Option<String>; IR_SLOTS],
+    blend: f32,
+    start_ms: f32,
+    length_ms: f32,
+    low_cut_hz: f32,
+    high_cut_hz: f32,
+    mix: f32,
+
+    sample_rate: f32,
+    sources: [Option<Arc<ImpulseResponse>>; IR_SLOTS],
+    // The sources at `sample_rate`, resampling is the slow part so blend and trim start from these
+    resampled: [Option<Arc<Vec<f32>>>; IR_SLOTS],
+    engine: CabEngine,
+    incoming: Handoff<CabEngine>,
+    low_cuts: [SvfFilter; NUM_CHANNELS],
+    high_cuts: [SvfFilter; NUM_CHANNELS],
+}
+
+impl Default for CabIr {
+    fn default() -> Self {
+        CabIrSettings {
+            paths: [None, None],
+            blend: 0.5,
+            start_ms: 0.0,
+            length_ms: 200.0,
+            low_cut_hz: 20.0,
+            high_cut_hz: 20000.0,
+            mix: 1.0,
+        }
+        .into()
+    }
+}
+
+/// Copies share the loaded responses, the convolvers are built again when the copy is prepared.
+impl Clone for CabIr {
+    fn clone(&self) -> Self {
+        Self {
+            paths: self.paths.clone(),
+            blend: self.blend,
+            start_ms: self.start_ms,
+            length_ms: self.length_ms,
+            low_cut_hz: self.low_cut_hz,
+            high_cut_hz: self.high_cut_hz,
+            mix: self.mix,
+            sample_rate: self.sample_rate,
+            sources: self.sources.clone(),
+            resampled: self.resampled.clone(),
+            engine: CabEngine::empty(),
+            incoming: Handoff::new(),
+            low_cuts: self.low_cuts,
+            high_cuts: self.high_cuts,
+        }
+    }
+}
+
+impl From<CabIrSettings> for CabIr {
+    fn from(settings: CabIrSettings) -> Self {
+        let mut cab_ir = Self {
+            paths: settings.paths,
+            blend: settings.blend,
+            start_ms: settings.start_ms,
+            length_ms: settings.length_ms,
+            low_cut_hz: settings.low_cut_hz,
+            high_cut_hz: settings.high_cut_hz,
+            mix: settings.mix,
+            sample_rate: 44100.0,
+            sources: [None, None],
+            resampled: [None, None],
+            engine: CabEngine::empty(),
+            incoming: Handoff::new(),
+            low_cuts: [SvfFilter::new(FilterMode::Highpass, 44100.0, 20.0, 0.707); NUM_CHANNELS],
+            high_cuts: [SvfFilter::new(FilterMode::Lowpass, 44100.0, 20000.0, 0.707); NUM_CHANNELS],
+        };
+        cab_ir.update_filters();
+        cab_ir
+    }
+}
+
+impl From<CabIr> for CabIrSettings {
+    fn from(cab_ir: CabIr) -> Self {
+        Self {
+            paths: cab_ir.paths,
+            blend: cab_ir.blend,
+            start_ms: cab_ir.start_ms,
+            length_ms: cab_ir.length_ms,
+            low_cut_hz: cab_ir.low_cut_hz,
+            high_cut_hz: cab_ir.high_cut_hz,
+            mix: cab_ir.mix,
+        }
+    }
+}
+
+impl PartialEq for CabIr {
+    fn eq(&self, other: &Self) -> bool {
+        self.paths == other.paths
+            && self.blend == other.blend
+            && self.start_ms == other.start_ms
+            && self.length_ms == other.length_ms
+            && self.low_cut_hz == other.low_cut_hz
+            && self.high_cut_hz == other.high_cut_hz
+            && self.mix == other.mix
+    }
+}
+
+impl CabIr {
+    pub fn path(&self, slot: usize) -> Option<&str> {
+        self.paths[slot].as_deref()
+    }
+
+    /// Paths that are set but don't point to a file.
+    pub fn missing_irs(&self) -> impl Iterator<Item = &str> {
+        self.paths
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .filter(|path| !Path::new(path).is_file())
+    }
+
+    pub fn blend(&self) -> f32 {
+        self.blend
+    }
+
+    pub fn start_ms(&self) -> f32 {
+        self.start_ms
+    }
+
+    pub fn length_ms(&self) -> f32 {
+        self.length_ms
+    }
+
+    pub fn low_cut(&self) -> f32 {
+        self.low_cut_hz
+    }
+
+    pub fn high_cut(&self) -> f32 {
+        self.high_cut_hz
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    /// Reads a WAV file into `slot`. The slot is left untouched if it can't be read.
+    pub fn load_ir(&mut self, slot: usize, path: &str) -> Result<(), hound::Error> {
+        let source = ImpulseResponse::load(path)?;
+        self.sources[slot] = Some(Arc::new(source));
+        self.paths[slot] = Some(path.to_owned());
+        self.resample(slot);
+        self.rebuild();
+        Ok(())
+    }
+
+    pub fn clear_ir(&mut self, slot: usize) {
+        self.sources[slot] = None;
+        self.resampled[slot] = None;
+        self.paths[slot] = None;
+        self.rebuild();
+    }
+
+    /// Crossfades from the first response at 0 to the second at 1.
+    pub fn set_blend(&mut self, blend: f32) {
+        self.blend = blend.clamp(0.0, 1.0);
+        self.rebuild();
+    }
+
+    /// Skips the start of the responses, usually the silence before the sound reaches the mic.
+    pub fn set_start_ms(&mut self, start_ms: f32) {
+        self.start_ms = start_ms.max(0.0);
+        self.rebuild();
+    }
+
+    pub fn set_length_ms(&mut self, length_ms: f32) {
+        self.length_ms = length_ms.clamp(TRIM_FADE_MS, MAX_IR_MS);
+        self.rebuild();
+    }
+
+    pub fn set_low_cut(&mut self, low_cut_hz: f32) {
+        self.low_cut_hz = low_cut_hz;
+        self.update_filters();
+    }
+
+    pub fn set_high_cut(&mut self, high_cut_hz: f32) {
+        self.high_cut_hz = high_cut_hz;
+        self.update_filters();
+    }
+
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    fn resample(&mut self, slot: usize) {
+        self.resampled[slot] = self.sources[slot]
+            .as_ref()
+            .map(|source| Arc::new(source.resample(self.sample_rate)));
+    }
+
+    /// Hands a new engine to the audio thread. Never call it from the audio thread.
+    fn rebuild(&mut self) {
+        self.incoming.send(self.build_engine());
+    }
+
+    /// Convolvers holding the trimmed and blended responses at the current rate.
+    fn build_engine(&self) -> CabEngine {
+        let sample_rate = self.sample_rate;
+        let start = (self.start_ms * 0.001 * sample_rate) as usize;
+        let full_length = self
+            .resampled
+            .iter()
+            .flatten()
+            .map(|samples| samples.len())
+            .max()
+            .unwrap_or(0);
+        let length = full_length
+            .saturating_sub(start)
+            .min((self.length_ms * 0.001 * sample_rate) as usize);
+        let fade_len = ((TRIM_FADE_MS * 0.001 * sample_rate) as usize).clamp(1, length.max(1));
+
+        let weights = match &self.resampled {
+            [Some(_), Some(_)] => [1.0 - self.blend, self.blend],
+            [Some(_), None] => [1.0, 0.0],
+            [None, Some(_)] => [0.0, 1.0],
+            [None, None] => [0.0, 0.0],
+        };
+
+        let sample = |index: usize| {
+            let value = self
+                .resampled
+                .iter()
+                .zip(weights)
+                .filter_map(|(samples, weight)| {
+                    let samples = samples.as_ref()?;
+                    Some(weight * samples.get(start + index).copied().unwrap_or(0.0))
+                })
+                .sum::<f32>();
+            let fade = ((length - index) as f32 / fade_len as f32).min(1.0);
+            value * fade
+        };
+
+        let mut convolver = Convolver::new(BLOCK_SIZE, length);
+        convolver.set_kernel(length, sample);
+        CabEngine {
+            convolvers: [convolver.clone(), convolver],
+            has_ir: self.resampled.iter().any(Option::is_some),
+        }
+    }
+
+    fn update_filters(&mut self) {
+        let nyquist_limit = 0.45 * self.sample_rate;
+        let low_cut = self.low_cut_hz.min(nyquist_limit);
+        let high_cut = self.high_cut_hz.min(nyquist_limit);
+        self.low_cuts
+            .iter_mut()
+            .for_each(|filter| filter.set_cutoff(low_cut));
+        self.high_cuts
+            .iter_mut()
+            .for_each(|filter| filter.set_cutoff(high_cut));
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.low_cuts
+            .iter_mut()
+            .chain(self.high_cuts.iter_mut())
+            .for_each(|filter| filter.set_sample_rate(sample_rate));
+        self.update_filters();
+    }
+}
+
+impl AudioEffect for CabIr {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        // The kernels were built in prepare for this rate, only the filters can lag behind
+        if self.sample_rate != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        self.incoming
+            .receive_with(&mut self.engine, CabEngine::carry_over);
+        if !self.engine.has_ir {
+            return;
+        }
+
+        let Self {
+            mix,
+            engine,
+            low_cuts,
+            high_cuts,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let (dry, wet) = engine.convolvers[channel].tick(*sample);
+                let wet = high_cuts[channel].tick(low_cuts[channel].tick(wet));
+                *sample = dry + *mix * (wet - dry);
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        CabIrView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        200.0
+    }
+
+    fn latency(&self) -> u32 {
+        if self.engine.has_ir {
+            self.engine.convolvers[0].latency() as u32
+        } else {
+            0
+        }
+    }
+
+    fn prepare(&mut self, sample_rate: f32) {
+        self.set_sample_rate(sample_rate);
+
+        // A file that went missing keeps its path, so saving the preset again doesn't lose it
+        for (path, source) in self.paths.iter().zip(&mut self.sources) {
+            if let (Some(path), None) = (path, &source) {
+                *source = ImpulseResponse::load(path).ok().map(Arc::new);
+            }
+        }
+        (0..IR_SLOTS).for_each(|slot| self.resample(slot));
+
+        // Nothing is processing this cab yet, so the engine can go straight in
+        self.incoming.discard();
+        self.engine = self.build_engine();
+    }
+}
+
+enum CabIrEvent {
+    Load(usize, String),
+    Clear(usize),
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct CabIrView {
+    #[msg]
+    blend: f32,
+    #[msg]
+    start: f32,
+    #[msg]
+    length: f32,
+    #[msg]
+    low_cut: f32,
+    #[msg]
+    high_cut: f32,
+    #[msg]
+    mix: f32,
+
+    paths: [String; IR_SLOTS],
+
+    #[lens(ignore)]
+    handle: EffectHandle<CabIr>,
+}
+
+impl CabIrView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<CabIr>) -> Handle<Self> {
+        Self {
+            blend: handle.blend() * 100.0,
+            start: handle.start_ms(),
+            length: handle.length_ms(),
+            low_cut: handle.low_cut(),
+            high_cut: handle.high_cut(),
+            mix: handle.mix() * 100.0,
+            paths: [0, 1].map(|slot| handle.path(slot).unwrap_or_default().to_owned()),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            VStack::new(cx, |cx| {
+                for (slot, name) in ["A", "B"].into_iter().enumerate() {
+                    HStack::new(cx, |cx| {
+                        Label::new(cx, name).class("cab-ir-slot");
+                        Textbox::new(cx, Self::paths.map(move |paths| paths[slot].clone()))
+                            .class("cab-ir-path")
+                            .on_submit(move |ex, path, _| ex.emit(CabIrEvent::Load(slot, path)));
+                        Button::new(
+                            cx,
+                            move |ex| ex.emit(CabIrEvent::Clear(slot)),
+                            |cx| Label::new(cx, "CLEAR"),
+                        )
+                        .class("cab-ir-option");
+                    })
+                    .class("cab-ir-row");
+                }
+                HStack::new(cx, |cx| {
+                    NamedKnob::new(cx, "Blend", Self::blend, false, 0.0..100.0)
+                        .on_changing(|ex, val| ex.emit(Message::Blend(val)));
+                    NamedKnob::new(cx, "Start", Self::start, false, 0.0..50.0)
+                        .on_changing(|ex, val| ex.emit(Message::Start(val)));
+                    NamedKnob::new(cx, "Length", Self::length, false, 10.0..MAX_IR_MS)
+                        .on_changing(|ex, val| ex.emit(Message::Length(val)));
+                    NamedKnob::new(cx, "Low Cut", Self::low_cut, false, 20.0..500.0)
+                        .on_changing(|ex, val| ex.emit(Message::Low_cut(val)));
+                    NamedKnob::new(cx, "High Cut", Self::high_cut, false, 1000.0..20000.0)
+                        .on_changing(|ex, val| ex.emit(Message::High_cut(val)));
+                    NamedKnob::new(cx, "Mix", Self::mix, false, 0.0..100.0)
+                        .on_changing(|ex, val| ex.emit(Message::Mix(val)));
+                })
+                .class("cab-ir-knobs");
+            });
+            Label::new(cx, "CAB IR").class("effect-title");
+        })
+    }
+}
+
+impl View for CabIrView {
+    fn element(&self) -> Option<&'static str> {
+        Some("cab-ir")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Blend(val) => {
+                self.blend = *val;
+                self.handle.set_blend(*val / 100.0);
+            }
+            Message::Start(val) => {
+                self.start = *val;
+                self.handle.set_start_ms(*val);
+            }
+            Message::Length(val) => {
+                self.length = *val;
+                self.handle.set_length_ms(*val);
+            }
+            Message::Low_cut(val) => {
+                self.low_cut = *val;
+                self.handle.set_low_cut(*val);
+            }
+            Message::High_cut(val) => {
+                self.high_cut = *val;
+                self.handle.set_high_cut(*val);
+            }
+            Message::Mix(val) => {
+                self.mix = *val;
+                self.handle.set_mix(*val / 100.0);
+            }
+        });
+
+        event.map(|event, _| match event {
+            CabIrEvent::Load(slot, path) => match self.handle.load_ir(*slot, path) {
+                Ok(_) => self.paths[*slot] = path.clone(),
+                Err(err) => {
+                    cx.emit(EffectError(format!(
+                        "Couldn't load impulse response {}: {}",
+                        path, err
+                    )));
+                }
+            },
+            CabIrEvent::Clear(slot) => {
+                self.paths[*slot].clear();
+                self.handle.clear_ir(*slot);
+            }
+        });
+    }
+}
@@ -0,0 +1,326 @@
+use crate::prelude::*;
+
+const MIN_DISTANCE_CM: f32 = 1.0;
+const MAX_DISTANCE_CM: f32 = 30.0;
+const PROXIMITY_HZ: f32 = 150.0;
+const MAX_PROXIMITY_DB: f32 = 6.0;
+// A mic backed off the grille hears less of the cone's top end and less level overall
+const FAR_ROLLOFF: f32 = 0.7;
+const FAR_LEVEL_DB: f32 = -4.0;
+// Quality factors of a fourth order Butterworth split into two sections
+const ROLLOFF_Q: [f32; 2] = [0.54, 1.31];
+
+const LOW_CUT: usize = 0;
+const RESONANCE: usize = 1;
+const BOX: usize = 2;
+const BREAKUP: usize = 3;
+const PROXIMITY: usize = 4;
+const ROLLOFF: usize = 5;
+const STAGES: usize = 7;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum CabSize {
+    OneByTwelve,
+    TwoByTwelve,
+    FourByTwelve,
+}
+
+/// Low end of a closed back cab, which mostly comes down to how much air is inside.
+struct SizeVoicing {
+    low_cut_hz: f32,
+    resonance_hz: f32,
+    resonance_db: f32,
+    box_hz: f32,
+    box_db: f32,
+}
+
+impl CabSize {
+    pub const ALL: [CabSize; 3] = [
+        CabSize::OneByTwelve,
+        CabSize::TwoByTwelve,
+        CabSize::FourByTwelve,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CabSize::OneByTwelve => "1X12",
+            CabSize::TwoByTwelve => "2X12",
+            CabSize::FourByTwelve => "4X12",
+        }
+    }
+
+    fn voicing(&self) -> SizeVoicing {
+        match self {
+            CabSize::OneByTwelve => SizeVoicing {
+                low_cut_hz: 100.0,
+                resonance_hz: 110.0,
+                resonance_db: 3.0,
+                box_hz: 600.0,
+                box_db: -2.0,
+            },
+            CabSize::TwoByTwelve => SizeVoicing {
+                low_cut_hz: 80.0,
+                resonance_hz: 95.0,
+                resonance_db: 4.0,
+                box_hz: 500.0,
+                box_db: -3.0,
+            },
+            CabSize::FourByTwelve => SizeVoicing {
+                low_cut_hz: 65.0,
+                resonance_hz: 85.0,
+                resonance_db: 5.0,
+                box_hz: 400.0,
+                box_db: -4.0,
+            },
+        }
+    }
+}
+
+#[inline]
+fn lerp(from: f32, to: f32, amount: f32) -> f32 {
+    from + (to - from) * amount
+}
+
+/// Speaker cabinet built from a handful of filters, for when there's no impulse response at hand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CabSim {
+    size: CabSize,
+    mic_position: f32,
+    mic_distance_cm: f32,
+    level_db: f32,
+
+    sample_rate: f32,
+    filters: [[SvfFilter; STAGES]; NUM_CHANNELS],
+}
+
+impl Default for CabSim {
+    fn default() -> Self {
+        let stages = [
+            FilterMode::Highpass,
+            FilterMode::Bell,
+            FilterMode::Bell,
+            FilterMode::Bell,
+            FilterMode::LowShelf,
+            FilterMode::Lowpass,
+            FilterMode::Lowpass,
+        ]
+        .map(|mode| SvfFilter::new(mode, 44100.0, 1000.0, 0.707));
+
+        let mut cab_sim = Self {
+            size: CabSize::FourByTwelve,
+            mic_position: 0.3,
+            mic_distance_cm: 5.0,
+            level_db: 0.0,
+            sample_rate: 44100.0,
+            filters: [stages; NUM_CHANNELS],
+        };
+        cab_sim.update_filters();
+        cab_sim
+    }
+}
+
+impl PartialEq for CabSim {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size
+            && self.mic_position == other.mic_position
+            && self.mic_distance_cm == other.mic_distance_cm
+            && self.level_db == other.level_db
+    }
+}
+
+impl CabSim {
+    pub fn size(&self) -> CabSize {
+        self.size
+    }
+
+    pub fn mic_position(&self) -> f32 {
+        self.mic_position
+    }
+
+    pub fn mic_distance_cm(&self) -> f32 {
+        self.mic_distance_cm
+    }
+
+    pub fn level_db(&self) -> f32 {
+        self.level_db
+    }
+
+    pub fn set_size(&mut self, size: CabSize) {
+        self.size = size;
+        self.update_filters();
+    }
+
+    /// From the dust cap at 0 to the edge of the cone at 1, which is darker and smoother.
+    pub fn set_mic_position(&mut self, mic_position: f32) {
+        self.mic_position = mic_position.clamp(0.0, 1.0);
+        self.update_filters();
+    }
+
+    /// Close mics pick up extra low end from the proximity effect.
+    pub fn set_mic_distance_cm(&mut self, mic_distance_cm: f32) {
+        self.mic_distance_cm = mic_distance_cm.clamp(MIN_DISTANCE_CM, MAX_DISTANCE_CM);
+        self.update_filters();
+    }
+
+    pub fn set_level_db(&mut self, level_db: f32) {
+        self.level_db = level_db;
+    }
+
+    fn normalized_distance(&self) -> f32 {
+        (self.mic_distance_cm - MIN_DISTANCE_CM) / (MAX_DISTANCE_CM - MIN_DISTANCE_CM)
+    }
+
+    fn update_filters(&mut self) {
+        let sample_rate = self.sample_rate;
+        let voicing = self.size.voicing();
+        let position = self.mic_position;
+        let distance = self.normalized_distance();
+
+        // Cutoff, Q and gain in dB of every stage
+        let mut settings = [(0.0, 0.0, 0.0); STAGES];
+        settings[LOW_CUT] = (voicing.low_cut_hz, 0.707, 0.0);
+        settings[RESONANCE] = (voicing.resonance_hz, 1.4, voicing.resonance_db);
+        settings[BOX] = (voicing.box_hz, 0.8, voicing.box_db);
+        // The cone stops moving as one piece up here, and the center of it is where that rings most
+        settings[BREAKUP] = (lerp(3000.0, 2000.0, position), 1.5, lerp(5.0, 1.0, position));
+        settings[PROXIMITY] = (PROXIMITY_HZ, 0.707, lerp(MAX_PROXIMITY_DB, 0.0, distance));
+        let rolloff_hz = lerp(6500.0, 3500.0, position) * lerp(1.0, FAR_ROLLOFF, distance);
+        for (setting, q) in settings[ROLLOFF..].iter_mut().zip(ROLLOFF_Q) {
+            *setting = (rolloff_hz.min(0.45 * sample_rate), q, 0.0);
+        }
+
+        for filter_bank in self.filters.iter_mut() {
+            for (filter, (cutoff, q, gain_db)) in filter_bank.iter_mut().zip(settings) {
+                filter.set_gain(db_to_gain(gain_db));
+                filter.recalculate_coeffs(cutoff, q, sample_rate);
+            }
+        }
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_filters();
+    }
+}
+
+impl AudioEffect for CabSim {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        let level = db_to_gain(self.level_db + FAR_LEVEL_DB * self.normalized_distance());
+
+        let Self { filters, .. } = self;
+
+        input_buffer.process_individual(|left, right| {
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let filtered = filters[channel]
+                    .iter_mut()
+                    .fold(*sample, |signal, filter| filter.tick(signal));
+                *sample = filtered * level;
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        CabSimView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        100.0
+    }
+}
+
+enum CabSimEvent {
+    SetSize(CabSize),
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct CabSimView {
+    #[msg]
+    position: f32,
+    #[msg]
+    distance: f32,
+    #[msg]
+    level: f32,
+
+    size: CabSize,
+
+    #[lens(ignore)]
+    handle: EffectHandle<CabSim>,
+}
+
+impl CabSimView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<CabSim>) -> Handle<Self> {
+        Self {
+            position: handle.mic_position() * 100.0,
+            distance: handle.mic_distance_cm(),
+            level: handle.level_db(),
+            size: handle.size(),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                VStack::new(cx, |cx| {
+                    for size in CabSize::ALL {
+                        Button::new(
+                            cx,
+                            move |ex| ex.emit(CabSimEvent::SetSize(size)),
+                            |cx| Label::new(cx, size.name()),
+                        )
+                        .class("cab-sim-option")
+                        .toggle_class(
+                            "option-enabled",
+                            Self::size.map(move |selected| *selected == size),
+                        );
+                    }
+                })
+                .class("cab-sim-options");
+                NamedKnob::new(cx, "Position", Self::position, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Position(val)));
+                NamedKnob::new(
+                    cx,
+                    "Distance",
+                    Self::distance,
+                    false,
+                    MIN_DISTANCE_CM..MAX_DISTANCE_CM,
+                )
+                .on_changing(|ex, val| ex.emit(Message::Distance(val)));
+                NamedKnob::new(cx, "Level", Self::level, true, -12.0..12.0)
+                    .on_changing(|ex, val| ex.emit(Message::Level(val)));
+                Label::new(cx, "CABINET").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for CabSimView {
+    fn element(&self) -> Option<&'static str> {
+        Some("cab-sim")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Position(val) => {
+                self.position = *val;
+                self.handle.set_mic_position(*val / 100.0);
+            }
+            Message::Distance(val) => {
+                self.distance = *val;
+                self.handle.set_mic_distance_cm(*val);
+            }
+            Message::Level(val) => {
+                self.level = *val;
+                self.handle.set_level_db(*val);
+            }
+        });
+
+        event.map(|event, _| match event {
+            CabSimEvent::SetSize(size) => {
+                self.size = *size;
+                self.handle.set_size(*size);
+            }
+        });
+    }
+}
@@ -0,0 +1,12 @@
+mod amp;
+pub use amp::{Amp, AmpChannel};
+
+mod cab_ir;
+pub use cab_ir::CabIr;
+
+mod cab_sim;
+pub use cab_sim::{CabSim, CabSize};
+
+mod nam;
+mod nam_player;
+pub use nam_player::NamPlayer;
@@ -0,0 +1,139 @@
+use serde::Deserialize;
+
+use super::{sigmoid, NamError, WeightReader};
+
+fn default_input_size() -> usize {
+    1
+}
+
+#[derive(Deserialize)]
+pub struct LstmConfig {
+    #[serde(default = "default_input_size")]
+    input_size: usize,
+    hidden_size: usize,
+    num_layers: usize,
+}
+
+#[derive(Clone)]
+struct LstmLayer {
+    inputs: usize,
+    hidden_size: usize,
+    // Input, forget, cell and output gates stacked, each row over [input, hidden]
+    weights: Vec<f32>,
+    bias: Vec<f32>,
+    hidden: Vec<f32>,
+    cell: Vec<f32>,
+    gates: Vec<f32>,
+}
+
+impl LstmLayer {
+    fn read(inputs: usize, hidden_size: usize, reader: &mut WeightReader) -> Result<Self, NamError> {
+        let weights = reader.read(4 * hidden_size * (inputs + hidden_size))?.to_vec();
+        let bias = reader.read(4 * hidden_size)?.to_vec();
+        let hidden = reader.read(hidden_size)?.to_vec();
+        let cell = reader.read(hidden_size)?.to_vec();
+
+        Ok(Self {
+            inputs,
+            hidden_size,
+            weights,
+            bias,
+            hidden,
+            cell,
+            gates: vec![0.0; 4 * hidden_size],
+        })
+    }
+
+    #[inline]
+    fn process(&mut self, input: &[f32]) {
+        let columns = self.inputs + self.hidden_size;
+
+        for (row, gate) in self.gates.iter_mut().enumerate() {
+            let weights = &self.weights[row * columns..(row + 1) * columns];
+            let (input_weights, hidden_weights) = weights.split_at(self.inputs);
+            *gate = self.bias[row]
+                + input_weights
+                    .iter()
+                    .zip(input)
+                    .map(|(w, x)| w * x)
+                    .sum::<f32>()
+                + hidden_weights
+                    .iter()
+                    .zip(&self.hidden)
+                    .map(|(w, h)| w * h)
+                    .sum::<f32>();
+        }
+
+        let (input_gates, rest) = self.gates.split_at(self.hidden_size);
+        let (forget_gates, rest) = rest.split_at(self.hidden_size);
+        let (cell_gates, output_gates) = rest.split_at(self.hidden_size);
+        let states = self.cell.iter_mut().zip(self.hidden.iter_mut());
+        let gates = input_gates
+            .iter()
+            .zip(forget_gates)
+            .zip(cell_gates)
+            .zip(output_gates);
+
+        for ((cell, hidden), (((input, forget), candidate), output)) in states.zip(gates) {
+            *cell = sigmoid(*forget) * *cell + sigmoid(*input) * candidate.tanh();
+            *hidden = sigmoid(*output) * cell.tanh();
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Lstm {
+    layers: Vec<LstmLayer>,
+    head_weights: Vec<f32>,
+    head_bias: f32,
+}
+
+impl Lstm {
+    pub fn read(config: LstmConfig, reader: &mut WeightReader) -> Result<Self, NamError> {
+        if config.input_size != 1 || config.num_layers == 0 {
+            return Err(NamError::Unsupported("LSTM layer sizes".to_owned()));
+        }
+
+        let layers = (0..config.num_layers)
+            .map(|index| {
+                // Every layer after the first one listens to the hidden state below it
+                let inputs = if index == 0 {
+                    config.input_size
+                } else {
+                    config.hidden_size
+                };
+                LstmLayer::read(inputs, config.hidden_size, reader)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let head_weights = reader.read(config.hidden_size)?.to_vec();
+        let head_bias = reader.read(1)?[0];
+
+        Ok(Self {
+            layers,
+            head_weights,
+            head_bias,
+        })
+    }
+
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        let input = [input];
+
+        for index in 0..self.layers.len() {
+            let (previous, rest) = self.layers.split_at_mut(index);
+            match previous.last() {
+                Some(previous) => rest[0].process(&previous.hidden),
+                None => rest[0].process(&input),
+            }
+        }
+
+        let last = &self.layers[self.layers.len() - 1];
+        self.head_bias
+            + self
+                .head_weights
+                .iter()
+                .zip(&last.hidden)
+                .map(|(w, h)| w * h)
+                .sum::<f32>()
+    }
+}
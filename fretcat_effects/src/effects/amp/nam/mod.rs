@@ -0,0 +1,297 @@
+mod lstm;
+mod wavenet;
+
+use std::{fmt, fs, io};
+
+use serde::Deserialize;
+
+use self::{lstm::Lstm, wavenet::WaveNet};
+
+/// Rate the models are trained at when the file doesn't say.
+const DEFAULT_SAMPLE_RATE: f32 = 48000.0;
+// Recurrent models need a while of silence to settle into their resting state
+const LSTM_PREWARM_SECONDS: f32 = 0.5;
+
+#[derive(Debug)]
+pub enum NamError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Unsupported(String),
+    WeightCount { expected: usize, found: usize },
+}
+
+impl fmt::Display for NamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NamError::Io(err) => write!(f, "{}", err),
+            NamError::Json(err) => write!(f, "invalid model file ({})", err),
+            NamError::Unsupported(what) => write!(f, "unsupported model: {}", what),
+            NamError::WeightCount { expected, found } => write!(
+                f,
+                "model has {} weights but its config needs {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for NamError {
+    fn from(err: io::Error) -> Self {
+        NamError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for NamError {
+    fn from(err: serde_json::Error) -> Self {
+        NamError::Json(err)
+    }
+}
+
+/// Optional information the trainer writes about the captured gear.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NamMetadata {
+    pub name: Option<String>,
+    pub modeled_by: Option<String>,
+    pub gear_make: Option<String>,
+    pub gear_model: Option<String>,
+    pub gear_type: Option<String>,
+    pub tone_type: Option<String>,
+    /// Loudness of the model's output in dB, used to level different captures.
+    pub loudness: Option<f32>,
+    /// Level in dBu that hit 0 dBFS on the interface used for the capture.
+    pub input_level_dbu: Option<f32>,
+    pub output_level_dbu: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct NamFile {
+    architecture: String,
+    config: serde_json::Value,
+    #[serde(default)]
+    metadata: Option<NamMetadata>,
+    weights: Vec<f32>,
+    #[serde(default)]
+    sample_rate: Option<f32>,
+}
+
+/// Hands out the flat weight list in the order the layers were written.
+struct WeightReader<'a> {
+    weights: &'a [f32],
+    position: usize,
+}
+
+impl<'a> WeightReader<'a> {
+    fn new(weights: &'a [f32]) -> Self {
+        Self {
+            weights,
+            position: 0,
+        }
+    }
+
+    fn read(&mut self, count: usize) -> Result<&'a [f32], NamError> {
+        let end = self.position + count;
+        let weights = self
+            .weights
+            .get(self.position..end)
+            .ok_or(NamError::WeightCount {
+                expected: end,
+                found: self.weights.len(),
+            })?;
+        self.position = end;
+        Ok(weights)
+    }
+
+    fn finish(self) -> Result<(), NamError> {
+        if self.position == self.weights.len() {
+            Ok(())
+        } else {
+            Err(NamError::WeightCount {
+                expected: self.position,
+                found: self.weights.len(),
+            })
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Activation {
+    Tanh,
+    FastTanh,
+    HardTanh,
+    Relu,
+    LeakyRelu,
+    Sigmoid,
+}
+
+impl Activation {
+    fn from_name(name: &str) -> Result<Self, NamError> {
+        match name {
+            "Tanh" => Ok(Activation::Tanh),
+            "Fasttanh" => Ok(Activation::FastTanh),
+            "Hardtanh" => Ok(Activation::HardTanh),
+            "ReLU" => Ok(Activation::Relu),
+            "LeakyReLU" => Ok(Activation::LeakyRelu),
+            "Sigmoid" => Ok(Activation::Sigmoid),
+            _ => Err(NamError::Unsupported(format!("{} activation", name))),
+        }
+    }
+
+    #[inline]
+    fn apply(&self, x: f32) -> f32 {
+        match self {
+            Activation::Tanh => x.tanh(),
+            Activation::FastTanh => fast_tanh(x),
+            Activation::HardTanh => x.clamp(-1.0, 1.0),
+            Activation::Relu => x.max(0.0),
+            Activation::LeakyRelu => {
+                if x > 0.0 {
+                    x
+                } else {
+                    0.01 * x
+                }
+            }
+            Activation::Sigmoid => sigmoid(x),
+        }
+    }
+}
+
+#[inline]
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// Same rational approximation the reference implementation uses for `Fasttanh`.
+#[inline]
+fn fast_tanh(x: f32) -> f32 {
+    let ax = x.abs();
+    let x2 = x * x;
+    (x * (2.4555075 + 2.4555075 * ax + (0.89322984 + 0.82122666 * ax) * x2))
+        / (2.4450665 + (2.4450665 + x2) * (x + 0.8146427 * x * ax).abs())
+}
+
+/// Fully connected layer applied to one frame, weights stored row by row.
+#[derive(Clone)]
+struct Dense {
+    inputs: usize,
+    outputs: usize,
+    weights: Vec<f32>,
+    bias: Option<Vec<f32>>,
+}
+
+impl Dense {
+    fn read(
+        reader: &mut WeightReader,
+        inputs: usize,
+        outputs: usize,
+        bias: bool,
+    ) -> Result<Self, NamError> {
+        let weights = reader.read(inputs * outputs)?.to_vec();
+        let bias = if bias {
+            Some(reader.read(outputs)?.to_vec())
+        } else {
+            None
+        };
+        Ok(Self {
+            inputs,
+            outputs,
+            weights,
+            bias,
+        })
+    }
+
+    #[inline]
+    fn apply(&self, input: &[f32], output: &mut [f32]) {
+        for (row, out) in output[..self.outputs].iter_mut().enumerate() {
+            let weights = &self.weights[row * self.inputs..(row + 1) * self.inputs];
+            let sum = weights.iter().zip(input).map(|(w, x)| w * x).sum::<f32>();
+            *out = sum + self.bias.as_ref().map_or(0.0, |bias| bias[row]);
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Network {
+    WaveNet(WaveNet),
+    Lstm(Lstm),
+}
+
+/// Neural Amp Modeler capture, ready to run one sample at a time.
+#[derive(Clone)]
+pub struct NamModel {
+    network: Network,
+    architecture: String,
+    metadata: NamMetadata,
+    sample_rate: f32,
+}
+
+impl fmt::Debug for NamModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamModel")
+            .field("architecture", &self.architecture)
+            .field("metadata", &self.metadata)
+            .field("sample_rate", &self.sample_rate)
+            .finish()
+    }
+}
+
+impl NamModel {
+    /// Reads a `.nam` file. Everything the network needs while running is allocated here.
+    pub fn load(path: &str) -> Result<Self, NamError> {
+        let file: NamFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let mut reader = WeightReader::new(&file.weights);
+        let sample_rate = file.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE);
+
+        let (network, prewarm_samples) = match file.architecture.as_str() {
+            "WaveNet" => {
+                let wavenet = WaveNet::read(serde_json::from_value(file.config)?, &mut reader)?;
+                let prewarm = wavenet.receptive_field();
+                (Network::WaveNet(wavenet), prewarm)
+            }
+            "LSTM" => {
+                let lstm = Lstm::read(serde_json::from_value(file.config)?, &mut reader)?;
+                (
+                    Network::Lstm(lstm),
+                    (LSTM_PREWARM_SECONDS * sample_rate) as usize,
+                )
+            }
+            other => return Err(NamError::Unsupported(format!("{} architecture", other))),
+        };
+        reader.finish()?;
+
+        let mut model = Self {
+            network,
+            architecture: file.architecture,
+            metadata: file.metadata.unwrap_or_default(),
+            sample_rate,
+        };
+        model.prewarm(prewarm_samples);
+        Ok(model)
+    }
+
+    pub fn architecture(&self) -> &str {
+        &self.architecture
+    }
+
+    pub fn metadata(&self) -> &NamMetadata {
+        &self.metadata
+    }
+
+    /// Rate the capture was trained at.
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        match &mut self.network {
+            Network::WaveNet(wavenet) => wavenet.process(input),
+            Network::Lstm(lstm) => lstm.process(input),
+        }
+    }
+
+    fn prewarm(&mut self, samples: usize) {
+        for _ in 0..samples {
+            self.process(0.0);
+        }
+    }
+}
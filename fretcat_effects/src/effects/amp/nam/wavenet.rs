@@ -0,0 +1,278 @@
+use serde::Deserialize;
+
+use super::{sigmoid, Activation, Dense, NamError, WeightReader};
+
+#[derive(Deserialize)]
+pub struct LayerArrayConfig {
+    input_size: usize,
+    condition_size: usize,
+    head_size: usize,
+    channels: usize,
+    kernel_size: usize,
+    dilations: Vec<usize>,
+    activation: String,
+    gated: bool,
+    head_bias: bool,
+}
+
+#[derive(Deserialize)]
+pub struct WaveNetConfig {
+    layers: Vec<LayerArrayConfig>,
+    #[serde(default)]
+    head: Option<serde_json::Value>,
+}
+
+/// Dilated convolution, gated activation and residual mix of one WaveNet layer.
+#[derive(Clone)]
+struct Layer {
+    channels: usize,
+    kernel_size: usize,
+    dilation: usize,
+    gated: bool,
+    activation: Activation,
+
+    // Taps stored as [tap][output][input], the oldest tap first
+    conv_weights: Vec<f32>,
+    conv_bias: Vec<f32>,
+    mixin: Dense,
+    mix: Dense,
+
+    // Past inputs, one frame of `channels` per sample
+    history: Vec<f32>,
+    history_mask: usize,
+    history_position: usize,
+    z: Vec<f32>,
+    mixed: Vec<f32>,
+}
+
+impl Layer {
+    fn read(
+        config: &LayerArrayConfig,
+        dilation: usize,
+        activation: Activation,
+        reader: &mut WeightReader,
+    ) -> Result<Self, NamError> {
+        let channels = config.channels;
+        let kernel_size = config.kernel_size;
+        let conv_outputs = if config.gated { 2 * channels } else { channels };
+
+        // The file lists the taps innermost
+        let weights = reader.read(conv_outputs * channels * kernel_size)?;
+        let mut conv_weights = vec![0.0; weights.len()];
+        for output in 0..conv_outputs {
+            for input in 0..channels {
+                for tap in 0..kernel_size {
+                    let from = (output * channels + input) * kernel_size + tap;
+                    let to = (tap * conv_outputs + output) * channels + input;
+                    conv_weights[to] = weights[from];
+                }
+            }
+        }
+        let conv_bias = reader.read(conv_outputs)?.to_vec();
+        let mixin = Dense::read(reader, config.condition_size, conv_outputs, false)?;
+        let mix = Dense::read(reader, channels, channels, true)?;
+
+        let frames = (dilation * (kernel_size - 1) + 1).next_power_of_two();
+        Ok(Self {
+            channels,
+            kernel_size,
+            dilation,
+            gated: config.gated,
+            activation,
+            conv_weights,
+            conv_bias,
+            mixin,
+            mix,
+            history: vec![0.0; frames * channels],
+            history_mask: frames - 1,
+            history_position: 0,
+            z: vec![0.0; conv_outputs],
+            mixed: vec![0.0; channels],
+        })
+    }
+
+    /// Adds this layer's activations to `head` and writes the residual output to `output`.
+    #[inline]
+    fn process(&mut self, input: &[f32], condition: &[f32], head: &mut [f32], output: &mut [f32]) {
+        let channels = self.channels;
+        let conv_outputs = self.z.len();
+
+        let frame = self.history_position * channels;
+        self.history[frame..frame + channels].copy_from_slice(&input[..channels]);
+
+        self.mixin.apply(condition, &mut self.z);
+        for (z, bias) in self.z.iter_mut().zip(&self.conv_bias) {
+            *z += bias;
+        }
+        for tap in 0..self.kernel_size {
+            let delay = self.dilation * (self.kernel_size - 1 - tap);
+            let frame = (self.history_position.wrapping_sub(delay) & self.history_mask) * channels;
+            let past = &self.history[frame..frame + channels];
+            let weights = &self.conv_weights[tap * conv_outputs * channels..];
+            for (output, z) in self.z.iter_mut().enumerate() {
+                let row = &weights[output * channels..(output + 1) * channels];
+                *z += row.iter().zip(past).map(|(w, x)| w * x).sum::<f32>();
+            }
+        }
+        self.history_position = (self.history_position + 1) & self.history_mask;
+
+        let (top, bottom) = self.z.split_at_mut(channels);
+        for (index, z) in top.iter_mut().enumerate() {
+            *z = self.activation.apply(*z);
+            if self.gated {
+                *z *= sigmoid(bottom[index]);
+            }
+        }
+
+        for (head, z) in head.iter_mut().zip(top.iter()) {
+            *head += z;
+        }
+        self.mix.apply(top, &mut self.mixed);
+        for ((output, input), mixed) in output.iter_mut().zip(input).zip(&self.mixed) {
+            *output = input + mixed;
+        }
+    }
+}
+
+/// Stack of layers sharing a channel count, with its own head output.
+#[derive(Clone)]
+struct LayerArray {
+    rechannel: Dense,
+    layers: Vec<Layer>,
+    head_rechannel: Dense,
+
+    residual: Vec<f32>,
+    next_residual: Vec<f32>,
+    head: Vec<f32>,
+    head_output: Vec<f32>,
+}
+
+impl LayerArray {
+    fn read(config: &LayerArrayConfig, reader: &mut WeightReader) -> Result<Self, NamError> {
+        if config.condition_size != 1 {
+            return Err(NamError::Unsupported(format!(
+                "condition size of {}",
+                config.condition_size
+            )));
+        }
+
+        let activation = Activation::from_name(&config.activation)?;
+        let rechannel = Dense::read(reader, config.input_size, config.channels, false)?;
+        let layers = config
+            .dilations
+            .iter()
+            .map(|dilation| Layer::read(config, *dilation, activation, reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        let head_rechannel =
+            Dense::read(reader, config.channels, config.head_size, config.head_bias)?;
+
+        Ok(Self {
+            rechannel,
+            layers,
+            head_rechannel,
+            residual: vec![0.0; config.channels],
+            next_residual: vec![0.0; config.channels],
+            head: vec![0.0; config.channels],
+            head_output: vec![0.0; config.head_size],
+        })
+    }
+
+    /// `head_input` is what the previous array's head produced, or nothing for the first one.
+    #[inline]
+    fn process(&mut self, input: &[f32], condition: &[f32], head_input: Option<&[f32]>) {
+        self.rechannel.apply(input, &mut self.residual);
+        match head_input {
+            Some(head_input) => self.head.copy_from_slice(head_input),
+            None => self.head.fill(0.0),
+        }
+
+        for layer in self.layers.iter_mut() {
+            layer.process(
+                &self.residual,
+                condition,
+                &mut self.head,
+                &mut self.next_residual,
+            );
+            std::mem::swap(&mut self.residual, &mut self.next_residual);
+        }
+
+        self.head_rechannel.apply(&self.head, &mut self.head_output);
+    }
+}
+
+#[derive(Clone)]
+pub struct WaveNet {
+    arrays: Vec<LayerArray>,
+    head_scale: f32,
+    receptive_field: usize,
+}
+
+impl WaveNet {
+    pub fn read(config: WaveNetConfig, reader: &mut WeightReader) -> Result<Self, NamError> {
+        if config.head.is_some_and(|head| !head.is_null()) {
+            return Err(NamError::Unsupported("WaveNet post head".to_owned()));
+        }
+
+        // Each array feeds its residual and head into the next one, so their sizes have to line up
+        let shapes_match = config.layers.windows(2).all(|pair| {
+            pair[1].input_size == pair[0].channels && pair[1].channels == pair[0].head_size
+        });
+        let ends_mono = config.layers.last().is_some_and(|last| last.head_size == 1);
+        let starts_mono = config.layers.first().is_some_and(|first| first.input_size == 1);
+        if !shapes_match || !ends_mono || !starts_mono {
+            return Err(NamError::Unsupported("WaveNet layer sizes".to_owned()));
+        }
+
+        let arrays = config
+            .layers
+            .iter()
+            .map(|layer_config| LayerArray::read(layer_config, reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        let head_scale = reader.read(1)?[0];
+
+        let receptive_field = config
+            .layers
+            .iter()
+            .flat_map(|layer| {
+                layer
+                    .dilations
+                    .iter()
+                    .map(|dilation| dilation * (layer.kernel_size - 1))
+            })
+            .sum::<usize>()
+            + 1;
+
+        Ok(Self {
+            arrays,
+            head_scale,
+            receptive_field,
+        })
+    }
+
+    /// Samples it takes for an input to stop affecting the output.
+    pub fn receptive_field(&self) -> usize {
+        self.receptive_field
+    }
+
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        let condition = [input];
+
+        for index in 0..self.arrays.len() {
+            let (previous, rest) = self.arrays.split_at_mut(index);
+            let array = &mut rest[0];
+            match previous.last() {
+                Some(previous) => array.process(
+                    &previous.residual,
+                    &condition,
+                    Some(&previous.head_output),
+                ),
+                None => array.process(&condition, &condition, None),
+            }
+        }
+
+        self.arrays
+            .last()
+            .map_or(0.0, |array| array.head_output[0] * self.head_scale)
+    }
+}
@@ -0,0 +1,446 @@
+use std::{path::Path, sync::Arc};
+
+use crate::prelude::*;
+
+use super::nam::{NamError, NamMetadata, NamModel};
+
+// Level the trainer aims captures at, so normalized models sit close to each other
+const TARGET_LOUDNESS_DB: f32 = -18.0;
+
+#[derive(Serialize, Deserialize)]
+struct NamPlayerSettings {
+    path: Option<String>,
+    input_gain_db: f32,
+    output_gain_db: f32,
+    calibrate_input: bool,
+    interface_dbu: f32,
+    normalize_output: bool,
+}
+
+/// Plays back a Neural Amp Modeler capture.
+/// Only the file path and calibration go into presets, the model is read when the player is prepared.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(from = "NamPlayerSettings", into = "NamPlayerSettings")]
+pub struct NamPlayer {
+    path: Option<String>,
+    input_gain_db: f32,
+    output_gain_db: f32,
+    calibrate_input: bool,
+    interface_dbu: f32,
+    normalize_output: bool,
+
+    sample_rate: f32,
+    // As read from disk, the audio thread runs its own copy
+    loaded: Option<Arc<NamModel>>,
+    model: Option<NamModel>,
+    incoming: Handoff<Option<NamModel>>,
+}
+
+impl Default for NamPlayer {
+    fn default() -> Self {
+        NamPlayerSettings {
+            path: None,
+            input_gain_db: 0.0,
+            output_gain_db: 0.0,
+            calibrate_input: false,
+            interface_dbu: 12.0,
+            normalize_output: true,
+        }
+        .into()
+    }
+}
+
+/// Copies share the model read from disk, the one that plays is made when the copy is prepared.
+impl Clone for NamPlayer {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            input_gain_db: self.input_gain_db,
+            output_gain_db: self.output_gain_db,
+            calibrate_input: self.calibrate_input,
+            interface_dbu: self.interface_dbu,
+            normalize_output: self.normalize_output,
+            sample_rate: self.sample_rate,
+            loaded: self.loaded.clone(),
+            model: None,
+            incoming: Handoff::new(),
+        }
+    }
+}
+
+impl From<NamPlayerSettings> for NamPlayer {
+    fn from(settings: NamPlayerSettings) -> Self {
+        Self {
+            path: settings.path,
+            input_gain_db: settings.input_gain_db,
+            output_gain_db: settings.output_gain_db,
+            calibrate_input: settings.calibrate_input,
+            interface_dbu: settings.interface_dbu,
+            normalize_output: settings.normalize_output,
+            sample_rate: 44100.0,
+            loaded: None,
+            model: None,
+            incoming: Handoff::new(),
+        }
+    }
+}
+
+impl From<NamPlayer> for NamPlayerSettings {
+    fn from(player: NamPlayer) -> Self {
+        Self {
+            path: player.path,
+            input_gain_db: player.input_gain_db,
+            output_gain_db: player.output_gain_db,
+            calibrate_input: player.calibrate_input,
+            interface_dbu: player.interface_dbu,
+            normalize_output: player.normalize_output,
+        }
+    }
+}
+
+impl PartialEq for NamPlayer {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+            && self.input_gain_db == other.input_gain_db
+            && self.output_gain_db == other.output_gain_db
+            && self.calibrate_input == other.calibrate_input
+            && self.interface_dbu == other.interface_dbu
+            && self.normalize_output == other.normalize_output
+    }
+}
+
+impl NamPlayer {
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Path that is set but doesn't point to a file.
+    pub fn missing_model(&self) -> Option<&str> {
+        self.path
+            .as_deref()
+            .filter(|path| !Path::new(path).is_file())
+    }
+
+    pub fn metadata(&self) -> Option<&NamMetadata> {
+        self.loaded.as_deref().map(NamModel::metadata)
+    }
+
+    pub fn architecture(&self) -> Option<&str> {
+        self.loaded.as_deref().map(NamModel::architecture)
+    }
+
+    pub fn model_sample_rate(&self) -> Option<f32> {
+        self.loaded.as_deref().map(NamModel::sample_rate)
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn input_gain_db(&self) -> f32 {
+        self.input_gain_db
+    }
+
+    pub fn output_gain_db(&self) -> f32 {
+        self.output_gain_db
+    }
+
+    pub fn calibrate_input(&self) -> bool {
+        self.calibrate_input
+    }
+
+    pub fn interface_dbu(&self) -> f32 {
+        self.interface_dbu
+    }
+
+    pub fn normalize_output(&self) -> bool {
+        self.normalize_output
+    }
+
+    /// Reads a `.nam` file and hands it to the audio thread.
+    /// The current model keeps playing if it can't be read. Never call it from the audio thread.
+    pub fn load_model(&mut self, path: &str) -> Result<(), NamError> {
+        let model = NamModel::load(path)?;
+        self.incoming.send(Some(model.clone()));
+        self.loaded = Some(Arc::new(model));
+        self.path = Some(path.to_owned());
+        Ok(())
+    }
+
+    pub fn clear_model(&mut self) {
+        self.incoming.send(None);
+        self.loaded = None;
+        self.path = None;
+    }
+
+    pub fn set_input_gain_db(&mut self, input_gain_db: f32) {
+        self.input_gain_db = input_gain_db;
+    }
+
+    pub fn set_output_gain_db(&mut self, output_gain_db: f32) {
+        self.output_gain_db = output_gain_db;
+    }
+
+    /// Matches the input level to the one the capture was made at, when the model lists it.
+    pub fn set_calibrate_input(&mut self, calibrate_input: bool) {
+        self.calibrate_input = calibrate_input;
+    }
+
+    /// Level in dBu that reaches 0 dBFS on the interface the guitar is plugged into.
+    pub fn set_interface_dbu(&mut self, interface_dbu: f32) {
+        self.interface_dbu = interface_dbu;
+    }
+
+    pub fn set_normalize_output(&mut self, normalize_output: bool) {
+        self.normalize_output = normalize_output;
+    }
+
+    fn calibration_db(metadata: &NamMetadata, calibrate_input: bool, interface_dbu: f32) -> f32 {
+        match metadata.input_level_dbu {
+            Some(input_level_dbu) if calibrate_input => interface_dbu - input_level_dbu,
+            _ => 0.0,
+        }
+    }
+
+    fn normalization_db(metadata: &NamMetadata, normalize_output: bool) -> f32 {
+        match metadata.loudness {
+            Some(loudness) if normalize_output => TARGET_LOUDNESS_DB - loudness,
+            _ => 0.0,
+        }
+    }
+}
+
+impl AudioEffect for NamPlayer {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        self.sample_rate = transport.sample_rate;
+        self.incoming.receive(&mut self.model);
+
+        let Self {
+            input_gain_db,
+            output_gain_db,
+            calibrate_input,
+            interface_dbu,
+            normalize_output,
+            model,
+            ..
+        } = self;
+
+        let Some(model) = model else {
+            return;
+        };
+
+        let metadata = model.metadata();
+        let input_gain = db_to_gain(
+            *input_gain_db + Self::calibration_db(metadata, *calibrate_input, *interface_dbu),
+        );
+        let output_gain =
+            db_to_gain(*output_gain_db + Self::normalization_db(metadata, *normalize_output));
+
+        // Captures are mono, both sides get the same signal back
+        input_buffer.process_individual(|left, right| {
+            let output = model.process((*left + *right) * 0.5 * input_gain) * output_gain;
+            *left = output;
+            *right = output;
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        NamPlayerView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        200.0
+    }
+
+    fn prepare(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+
+        // A file that went missing keeps its path, so saving the preset again doesn't lose it
+        if let (Some(path), None) = (&self.path, &self.loaded) {
+            self.loaded = NamModel::load(path).ok().map(Arc::new);
+        }
+
+        // Nothing is processing this player yet, so its model can go straight in
+        self.incoming.discard();
+        self.model = self.loaded.as_deref().cloned();
+    }
+}
+
+enum NamPlayerEvent {
+    Load(String),
+    Clear,
+    ToggleCalibrate,
+    ToggleNormalize,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct NamPlayerView {
+    #[msg]
+    input: f32,
+    #[msg]
+    output: f32,
+    #[msg]
+    reference: f32,
+
+    calibrate: bool,
+    normalize: bool,
+    path: String,
+    name: String,
+    gear: String,
+    details: String,
+
+    #[lens(ignore)]
+    handle: EffectHandle<NamPlayer>,
+}
+
+impl NamPlayerView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<NamPlayer>) -> Handle<Self> {
+        let mut view = Self {
+            input: handle.input_gain_db(),
+            output: handle.output_gain_db(),
+            reference: handle.interface_dbu(),
+            calibrate: handle.calibrate_input(),
+            normalize: handle.normalize_output(),
+            path: handle.path().unwrap_or_default().to_owned(),
+            name: String::new(),
+            gear: String::new(),
+            details: String::new(),
+            handle: handle.clone(),
+        };
+        view.update_info();
+
+        view.build(cx, |cx| {
+            VStack::new(cx, |cx| {
+                HStack::new(cx, |cx| {
+                    Textbox::new(cx, Self::path)
+                        .class("nam-player-path")
+                        .on_submit(|ex, path, _| ex.emit(NamPlayerEvent::Load(path)));
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(NamPlayerEvent::Clear),
+                        |cx| Label::new(cx, "CLEAR"),
+                    )
+                    .class("nam-player-option");
+                })
+                .class("nam-player-row");
+                VStack::new(cx, |cx| {
+                    Label::new(cx, Self::name).class("nam-player-name");
+                    Label::new(cx, Self::gear).class("nam-player-detail");
+                    Label::new(cx, Self::details).class("nam-player-detail");
+                })
+                .class("nam-player-info");
+                HStack::new(cx, |cx| {
+                    NamedKnob::new(cx, "Input", Self::input, true, -24.0..24.0)
+                        .on_changing(|ex, val| ex.emit(Message::Input(val)));
+                    NamedKnob::new(cx, "Output", Self::output, true, -24.0..24.0)
+                        .on_changing(|ex, val| ex.emit(Message::Output(val)));
+                    NamedKnob::new(cx, "Ref dBu", Self::reference, false, 0.0..24.0)
+                        .on_changing(|ex, val| ex.emit(Message::Reference(val)));
+                    VStack::new(cx, |cx| {
+                        Button::new(
+                            cx,
+                            |ex| ex.emit(NamPlayerEvent::ToggleCalibrate),
+                            |cx| Label::new(cx, "CALIBRATE"),
+                        )
+                        .class("nam-player-option")
+                        .toggle_class("option-enabled", Self::calibrate);
+                        Button::new(
+                            cx,
+                            |ex| ex.emit(NamPlayerEvent::ToggleNormalize),
+                            |cx| Label::new(cx, "NORMALIZE"),
+                        )
+                        .class("nam-player-option")
+                        .toggle_class("option-enabled", Self::normalize);
+                    })
+                    .class("nam-player-options");
+                })
+                .class("nam-player-knobs");
+            });
+            Label::new(cx, "NAM").class("effect-title");
+        })
+    }
+
+    fn update_info(&mut self) {
+        let Some(metadata) = self.handle.metadata() else {
+            self.name = "No model loaded".to_owned();
+            self.gear.clear();
+            self.details.clear();
+            return;
+        };
+
+        let gear = [&metadata.gear_make, &metadata.gear_model]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.name = metadata.name.clone().unwrap_or_else(|| "Untitled".to_owned());
+        self.gear = match &metadata.modeled_by {
+            Some(modeled_by) if gear.is_empty() => format!("by {}", modeled_by),
+            Some(modeled_by) => format!("{} by {}", gear, modeled_by),
+            None => gear,
+        };
+
+        let architecture = self.handle.architecture().unwrap_or_default().to_owned();
+        let model_rate = self.handle.model_sample_rate().unwrap_or_default();
+        self.details = format!("{} at {} kHz", architecture, model_rate / 1000.0);
+        // Models run at whatever rate the host gives them, which bends their response off the capture
+        if model_rate != self.handle.sample_rate() {
+            self.details += &format!(
+                ", running at {} kHz",
+                self.handle.sample_rate() / 1000.0
+            );
+        }
+    }
+}
+
+impl View for NamPlayerView {
+    fn element(&self) -> Option<&'static str> {
+        Some("nam-player")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Input(val) => {
+                self.input = *val;
+                self.handle.set_input_gain_db(*val);
+            }
+            Message::Output(val) => {
+                self.output = *val;
+                self.handle.set_output_gain_db(*val);
+            }
+            Message::Reference(val) => {
+                self.reference = *val;
+                self.handle.set_interface_dbu(*val);
+            }
+        });
+
+        event.map(|event, _| match event {
+            NamPlayerEvent::Load(path) => match self.handle.load_model(path) {
+                Ok(_) => {
+                    self.path = path.clone();
+                    self.update_info();
+                }
+                Err(err) => {
+                    cx.emit(EffectError(format!(
+                        "Couldn't load model {}: {}",
+                        path, err
+                    )));
+                }
+            },
+            NamPlayerEvent::Clear => {
+                self.path.clear();
+                self.handle.clear_model();
+                self.update_info();
+            }
+            NamPlayerEvent::ToggleCalibrate => {
+                self.calibrate = !self.calibrate;
+                self.handle.set_calibrate_input(self.calibrate);
+            }
+            NamPlayerEvent::ToggleNormalize => {
+                self.normalize = !self.normalize;
+                self.handle.set_normalize_output(self.normalize);
+            }
+        });
+    }
+}
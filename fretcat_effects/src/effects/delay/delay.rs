@@ -13,6 +13,7 @@ impl PartialEq for MonoDelay {
             && self.delays[1].feedback() == other.delays[1].feedback()
             && self.delays[0].delay_time_secs() == other.delays[0].delay_time_secs()
             && self.delays[1].delay_time_secs() == other.delays[1].delay_time_secs()
+            && self.delays[0].time_mode() == other.delays[0].time_mode()
     }
 }
 
@@ -29,6 +30,12 @@ impl MonoDelay {
         });
     }
 
+    pub fn set_time_mode(&mut self, time_mode: DelayTimeMode) {
+        self.delays.iter_mut().for_each(|delay| {
+            delay.set_time_mode(time_mode);
+        });
+    }
+
     pub fn set_wet(&mut self, wet: f32) {
         self.wet = wet.clamp(0.0, 1.0);
     }
@@ -38,7 +45,10 @@ impl Default for MonoDelay {
     fn default() -> Self {
         Self {
             wet: 0.5,
-            delays: [Delay::default(), Delay::default()],
+            delays: [
+                Delay::new(MAX_DELAY_SECS),
+                Delay::new(MAX_DELAY_SECS),
+            ],
         }
     }
 }
@@ -46,10 +56,8 @@ impl Default for MonoDelay {
 impl AudioEffect for MonoDelay {
     fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
         if transport.sample_rate != self.delays[0].sample_rate() {
-            nih_plug::util::permit_alloc(|| {
-                self.delays[0].set_sample_rate(transport.sample_rate);
-                self.delays[1].set_sample_rate(transport.sample_rate);
-            });
+            self.delays[0].set_sample_rate(transport.sample_rate);
+            self.delays[1].set_sample_rate(transport.sample_rate);
         }
 
         input_buffer.process_individual(|left, right| {
@@ -67,6 +75,10 @@ impl AudioEffect for MonoDelay {
     }
 }
 
+enum DelayEvent {
+    CycleTimeMode,
+}
+
 #[derive(Debug, Clone, Lens, Message)]
 struct DelayView {
     #[msg]
@@ -76,6 +88,8 @@ struct DelayView {
     #[msg]
     wet: f32,
 
+    time_mode: DelayTimeMode,
+
     #[lens(ignore)]
     handle: EffectHandle<MonoDelay>,
 }
@@ -86,16 +100,23 @@ impl DelayView {
             wet: handle.wet * 100.0,
             time: handle.delays[0].delay_time_secs() * 1000.0,
             feedback: handle.delays[0].feedback() * 100.0,
+            time_mode: handle.delays[0].time_mode(),
             handle: handle.clone(),
         }
         .build(cx, |cx| {
             HStack::new(cx, |cx| {
-                NamedKnob::new(cx, "Time", Self::time, false, 20.0..1000.0)
+                NamedKnob::new(cx, "Time", Self::time, false, 20.0..MAX_DELAY_SECS * 1000.0)
                     .on_changing(|ex, val| ex.emit(Message::Time(val)));
                 NamedKnob::new(cx, "Feedback", Self::feedback, false, 0.0..100.0)
                     .on_changing(|ex, val| ex.emit(Message::Feedback(val)));
                 NamedKnob::new(cx, "Wet", Self::wet, false, 0.0..100.0)
                     .on_changing(|ex, val| ex.emit(Message::Wet(val)));
+                Button::new(
+                    cx,
+                    |ex| ex.emit(DelayEvent::CycleTimeMode),
+                    |cx| Label::new(cx, Self::time_mode.map(|mode| mode.name())),
+                )
+                .class("delay-mode");
                 Label::new(cx, "DELAY").class("effect-title");
             });
         })
@@ -122,5 +143,14 @@ impl View for DelayView {
                 self.handle.set_wet(*val / 100.0);
             }
         });
+
+        event.map(|event, _| match event {
+            DelayEvent::CycleTimeMode => {
+                let modes = DelayTimeMode::ALL;
+                let index = modes.iter().position(|mode| *mode == self.time_mode).unwrap_or(0);
+                self.time_mode = modes[(index + 1) % modes.len()];
+                self.handle.set_time_mode(self.time_mode);
+            }
+        });
     }
 }
@@ -0,0 +1,718 @@
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+use crate::prelude::*;
+
+// Loop lengths the buffer can be sized for, in seconds
+const MAX_LENGTHS: [f32; 4] = [15.0, 30.0, 60.0, 120.0];
+// Control changes a footswitch can send, from the undefined range so they don't clash
+// with anything else on the pedalboard
+const MIDI_MAP: [(u8, LooperCommand); 7] = [
+    (102, LooperCommand::Record),
+    (103, LooperCommand::Play),
+    (104, LooperCommand::Stop),
+    (105, LooperCommand::Undo),
+    (106, LooperCommand::Clear),
+    (107, LooperCommand::Reverse),
+    (108, LooperCommand::HalfSpeed),
+];
+const NOT_WRITTEN: usize = usize::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Data)]
+pub enum LooperState {
+    Empty,
+    Recording,
+    Playing,
+    Overdubbing,
+    Stopped,
+}
+
+impl LooperState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LooperState::Empty => "EMPTY",
+            LooperState::Recording => "RECORDING",
+            LooperState::Playing => "PLAYING",
+            LooperState::Overdubbing => "OVERDUB",
+            LooperState::Stopped => "STOPPED",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LooperCommand {
+    /// Starts a loop, closes it, or switches between playing and overdubbing.
+    Record,
+    Play,
+    Stop,
+    /// Takes back the last overdub.
+    Undo,
+    Clear,
+    Reverse,
+    HalfSpeed,
+}
+
+/// Memory for the longest loop at the prepared rate. Built away from the audio thread and
+/// handed to it whole, since it runs to hundreds of megabytes at the longest lengths.
+#[derive(Debug, Default)]
+struct LoopBuffers {
+    samples: [Vec<f32>; NUM_CHANNELS],
+    // What the last overdub wrote over, only valid where `touched` has its bit set
+    undo: [Vec<f32>; NUM_CHANNELS],
+    touched: Vec<u64>,
+}
+
+impl LoopBuffers {
+    fn new(max_length: f32, sample_rate: f32) -> Self {
+        let capacity = (max_length * sample_rate) as usize;
+        Self {
+            samples: [vec![0.0; capacity], vec![0.0; capacity]],
+            undo: [vec![0.0; capacity], vec![0.0; capacity]],
+            touched: vec![0; capacity.div_ceil(64)],
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.samples[0].len()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LooperSettings {
+    max_length: f32,
+    level: f32,
+    quantize: bool,
+    reverse: bool,
+    half_speed: bool,
+}
+
+/// Looper with overdubs and a single undo. The loop itself never goes into presets,
+/// it can be exported to a WAV file instead.
+///
+/// Commands from the editor and footswitches are picked up at the start of the next block,
+/// where the host tempo is known to close a loop on a whole number of bars.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(from = "LooperSettings", into = "LooperSettings")]
+pub struct Looper {
+    max_length: f32,
+    level: f32,
+    quantize: bool,
+    reverse: bool,
+    half_speed: bool,
+
+    state: LooperState,
+    pending: Option<LooperCommand>,
+    sample_rate: f32,
+    prepared_rate: f32,
+    bar_length: Option<f32>,
+    memory: LoopBuffers,
+    incoming: Handoff<LoopBuffers>,
+    can_undo: bool,
+    length: usize,
+    position: f32,
+    last_written: usize,
+}
+
+impl Default for Looper {
+    fn default() -> Self {
+        LooperSettings {
+            max_length: 30.0,
+            level: 1.0,
+            quantize: false,
+            reverse: false,
+            half_speed: false,
+        }
+        .into()
+    }
+}
+
+impl From<LooperSettings> for Looper {
+    fn from(settings: LooperSettings) -> Self {
+        // Nothing is allocated until the chain prepares it for the host sample rate
+        Self {
+            max_length: settings.max_length,
+            level: settings.level,
+            quantize: settings.quantize,
+            reverse: settings.reverse,
+            half_speed: settings.half_speed,
+            state: LooperState::Empty,
+            pending: None,
+            sample_rate: 44100.0,
+            prepared_rate: 0.0,
+            bar_length: None,
+            memory: LoopBuffers::default(),
+            incoming: Handoff::new(),
+            can_undo: false,
+            length: 0,
+            position: 0.0,
+            last_written: NOT_WRITTEN,
+        }
+    }
+}
+
+/// Copies only take the settings, a loop can be hundreds of megabytes.
+/// Their memory is allocated when they're prepared.
+impl Clone for Looper {
+    fn clone(&self) -> Self {
+        LooperSettings {
+            max_length: self.max_length,
+            level: self.level,
+            quantize: self.quantize,
+            reverse: self.reverse,
+            half_speed: self.half_speed,
+        }
+        .into()
+    }
+}
+
+impl From<Looper> for LooperSettings {
+    fn from(looper: Looper) -> Self {
+        Self {
+            max_length: looper.max_length,
+            level: looper.level,
+            quantize: looper.quantize,
+            reverse: looper.reverse,
+            half_speed: looper.half_speed,
+        }
+    }
+}
+
+impl PartialEq for Looper {
+    fn eq(&self, other: &Self) -> bool {
+        self.max_length == other.max_length
+            && self.level == other.level
+            && self.quantize == other.quantize
+            && self.reverse == other.reverse
+            && self.half_speed == other.half_speed
+    }
+}
+
+impl Looper {
+    /// Longest loop in seconds at the prepared sample rate.
+    pub fn max_length(&self) -> f32 {
+        self.max_length
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Whether a new loop is rounded to whole bars of the host tempo.
+    pub fn quantize(&self) -> bool {
+        self.quantize
+    }
+
+    pub fn reverse(&self) -> bool {
+        self.reverse
+    }
+
+    pub fn half_speed(&self) -> bool {
+        self.half_speed
+    }
+
+    pub fn state(&self) -> LooperState {
+        self.state
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.can_undo
+    }
+
+    /// Length of the loop in seconds, or how much has been recorded so far.
+    pub fn length_seconds(&self) -> f32 {
+        self.length as f32 / self.sample_rate
+    }
+
+    /// Where playback is in the loop, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        match self.state {
+            LooperState::Recording => self.length as f32 / self.memory.capacity().max(1) as f32,
+            _ if self.length > 0 => self.position / self.length as f32,
+            _ => 0.0,
+        }
+    }
+
+    /// Resizes the loop buffer, which throws the current loop away once the audio thread
+    /// picks the new one up. Allocates, so keep it off the audio thread.
+    pub fn set_max_length(&mut self, max_length: f32) {
+        self.max_length = max_length.clamp(MAX_LENGTHS[0], MAX_LENGTHS[MAX_LENGTHS.len() - 1]);
+        if self.prepared_rate > 0.0 {
+            self.incoming
+                .send(LoopBuffers::new(self.max_length, self.prepared_rate));
+        }
+    }
+
+    /// Frees loop memory the audio thread has swapped out. Never call it from the audio thread.
+    pub fn release_old_buffers(&self) {
+        self.incoming.collect();
+    }
+
+    /// Steps through the lengths the buffer can be sized for.
+    pub fn cycle_max_length(&mut self) {
+        let next = MAX_LENGTHS
+            .iter()
+            .position(|length| *length > self.max_length)
+            .unwrap_or(0);
+        self.set_max_length(MAX_LENGTHS[next]);
+    }
+
+    pub fn set_level(&mut self, level: f32) {
+        self.level = level.clamp(0.0, 1.0);
+    }
+
+    pub fn set_quantize(&mut self, quantize: bool) {
+        self.quantize = quantize;
+    }
+
+    /// Queues a command for the start of the next block.
+    pub fn trigger(&mut self, command: LooperCommand) {
+        self.pending = Some(command);
+    }
+
+    /// Writes the loop as a 32 bit float stereo WAV file.
+    /// Returns false when there's no loop to write.
+    pub fn export(&self, path: &str) -> Result<bool, hound::Error> {
+        if self.length == 0 || self.state == LooperState::Recording {
+            return Ok(false);
+        }
+
+        let spec = WavSpec {
+            channels: NUM_CHANNELS as u16,
+            sample_rate: self.sample_rate as u32,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let mut writer = WavWriter::create(path, spec)?;
+        for (left, right) in self.memory.samples[0][..self.length]
+            .iter()
+            .zip(&self.memory.samples[1][..self.length])
+        {
+            writer.write_sample(*left)?;
+            writer.write_sample(*right)?;
+        }
+        writer.finalize()?;
+
+        Ok(true)
+    }
+
+    fn clear(&mut self) {
+        self.state = LooperState::Empty;
+        self.length = 0;
+        self.position = 0.0;
+        self.can_undo = false;
+        self.last_written = NOT_WRITTEN;
+    }
+
+    fn apply(&mut self, command: LooperCommand) {
+        use LooperState::*;
+
+        match (command, self.state) {
+            (LooperCommand::Record, Empty) => {
+                self.length = 0;
+                self.state = Recording;
+            }
+            (LooperCommand::Record, Recording) | (LooperCommand::Play, Recording) => {
+                self.close_loop();
+                self.state = if self.length > 0 { Playing } else { Empty };
+            }
+            (LooperCommand::Record, Playing) => self.start_layer(),
+            (LooperCommand::Record, Stopped) => {
+                self.position = self.start();
+                self.start_layer();
+            }
+            (LooperCommand::Record, Overdubbing) | (LooperCommand::Play, Overdubbing) => {
+                self.state = Playing;
+            }
+            (LooperCommand::Play, Stopped) => {
+                self.position = self.start();
+                self.state = Playing;
+            }
+            (LooperCommand::Stop, Recording) => {
+                self.close_loop();
+                self.state = if self.length > 0 { Stopped } else { Empty };
+            }
+            (LooperCommand::Stop, Playing) | (LooperCommand::Stop, Overdubbing) => {
+                self.state = Stopped;
+            }
+            (LooperCommand::Undo, _) => {
+                if self.state == Overdubbing {
+                    self.state = Playing;
+                }
+                self.undo_layer();
+            }
+            (LooperCommand::Clear, _) => self.clear(),
+            (LooperCommand::Reverse, _) => self.reverse = !self.reverse,
+            (LooperCommand::HalfSpeed, _) => self.half_speed = !self.half_speed,
+            _ => {}
+        }
+    }
+
+    /// Where playback starts, the end of the loop when it runs backwards.
+    fn start(&self) -> f32 {
+        if self.reverse {
+            (self.length as f32 - 1.0).max(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    fn close_loop(&mut self) {
+        if let (true, Some(bar)) = (self.quantize, self.bar_length) {
+            let capacity = self.memory.capacity();
+            let bars = (self.length as f32 / bar)
+                .round()
+                .max(1.0)
+                .min((capacity as f32 / bar).floor());
+            if bars >= 1.0 {
+                let length = (bars * bar).round() as usize;
+                // Rounded up, the rest of the last bar is silence
+                if length > self.length {
+                    for buffer in self.memory.samples.iter_mut() {
+                        buffer[self.length..length].fill(0.0);
+                    }
+                }
+                self.length = length;
+            }
+        }
+
+        self.position = self.start();
+        self.can_undo = false;
+        self.last_written = NOT_WRITTEN;
+    }
+
+    fn start_layer(&mut self) {
+        self.memory.touched.fill(0);
+        self.can_undo = false;
+        self.last_written = NOT_WRITTEN;
+        self.state = LooperState::Overdubbing;
+    }
+
+    fn undo_layer(&mut self) {
+        if !self.can_undo {
+            return;
+        }
+
+        let LoopBuffers {
+            samples,
+            undo,
+            touched,
+        } = &mut self.memory;
+        for (word_index, word) in touched.iter_mut().enumerate() {
+            while *word != 0 {
+                let index = word_index * 64 + word.trailing_zeros() as usize;
+                for (buffer, undo) in samples.iter_mut().zip(undo.iter()) {
+                    buffer[index] = undo[index];
+                }
+                *word &= *word - 1;
+            }
+        }
+        self.can_undo = false;
+    }
+
+    #[inline]
+    fn tick(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let capacity = self.memory.capacity();
+        match self.state {
+            LooperState::Recording => {
+                self.memory.samples[0][self.length] = left;
+                self.memory.samples[1][self.length] = right;
+                self.length += 1;
+                if self.length == capacity {
+                    self.close_loop();
+                    self.state = LooperState::Playing;
+                }
+                (left, right)
+            }
+            LooperState::Playing | LooperState::Overdubbing => {
+                let length = self.length;
+                let index = self.position as usize;
+                let fraction = self.position - index as f32;
+                let next = (index + 1) % length;
+                let read = |buffer: &[f32]| buffer[index] + (buffer[next] - buffer[index]) * fraction;
+                let output = (
+                    left + read(self.memory.samples[0].as_slice()) * self.level,
+                    right + read(self.memory.samples[1].as_slice()) * self.level,
+                );
+
+                // At half speed every sample is passed twice, but only written once
+                if self.state == LooperState::Overdubbing && index != self.last_written {
+                    let (word, bit) = (index / 64, 1 << (index % 64));
+                    let LoopBuffers {
+                        samples,
+                        undo,
+                        touched,
+                    } = &mut self.memory;
+                    if touched[word] & bit == 0 {
+                        touched[word] |= bit;
+                        undo[0][index] = samples[0][index];
+                        undo[1][index] = samples[1][index];
+                    }
+                    samples[0][index] += left;
+                    samples[1][index] += right;
+                    self.last_written = index;
+                    self.can_undo = true;
+                }
+
+                let speed = if self.half_speed { 0.5 } else { 1.0 };
+                let step = if self.reverse { -speed } else { speed };
+                self.position = (self.position + step).rem_euclid(length as f32);
+                output
+            }
+            LooperState::Empty | LooperState::Stopped => (left, right),
+        }
+    }
+}
+
+impl AudioEffect for Looper {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        self.sample_rate = transport.sample_rate;
+        self.bar_length = match (
+            transport.tempo,
+            transport.time_sig_numerator,
+            transport.time_sig_denominator,
+        ) {
+            (Some(tempo), Some(numerator), Some(denominator)) if tempo > 0.0 => {
+                let quarter_notes = numerator as f64 * 4.0 / denominator as f64;
+                Some((quarter_notes * 60.0 / tempo) as f32 * self.sample_rate)
+            }
+            _ => None,
+        };
+
+        // A resized buffer starts out empty
+        if self.incoming.receive(&mut self.memory) {
+            self.clear();
+        }
+
+        if let Some(command) = self.pending.take() {
+            self.apply(command);
+        }
+
+        // Not prepared yet, there's nowhere to record to
+        if self.memory.capacity() == 0 {
+            return;
+        }
+
+        input_buffer.process_individual(|left, right| {
+            (*left, *right) = self.tick(*left, *right);
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        LooperView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        200.0
+    }
+
+    fn prepare(&mut self, sample_rate: f32) {
+        if sample_rate != self.prepared_rate {
+            // Nothing is processing this looper yet, so the memory can go straight in
+            self.incoming.discard();
+            self.memory = LoopBuffers::new(self.max_length, sample_rate);
+            self.prepared_rate = sample_rate;
+            self.clear();
+        }
+    }
+
+    /// Meant for momentary footswitches, every press sends a value over half.
+    fn midi_cc(&mut self, cc: u8, value: f32) {
+        if value < 0.5 {
+            return;
+        }
+
+        if let Some((_, command)) = MIDI_MAP.iter().find(|(mapped, _)| *mapped == cc) {
+            self.trigger(*command);
+        }
+    }
+}
+
+enum LooperEvent {
+    Command(LooperCommand),
+    CycleMaxLength,
+    ToggleQuantize,
+    Export(String),
+    Refresh,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct LooperView {
+    #[msg]
+    level: f32,
+
+    state: LooperState,
+    progress: f32,
+    length: f32,
+    can_undo: bool,
+    reverse: bool,
+    half_speed: bool,
+    quantize: bool,
+    max_length: f32,
+    path: String,
+
+    #[lens(ignore)]
+    handle: EffectHandle<Looper>,
+}
+
+impl LooperView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<Looper>) -> Handle<Self> {
+        Self {
+            level: handle.level() * 100.0,
+            state: handle.state(),
+            progress: handle.progress(),
+            length: handle.length_seconds(),
+            can_undo: handle.can_undo(),
+            reverse: handle.reverse(),
+            half_speed: handle.half_speed(),
+            quantize: handle.quantize(),
+            max_length: handle.max_length(),
+            path: String::new(),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            // The state moves on in the audio thread, so it's polled
+            let timer = cx.add_timer(std::time::Duration::from_millis(50), None, |ex, action| {
+                if let TimerAction::Tick(_) = action {
+                    ex.emit(LooperEvent::Refresh);
+                }
+            });
+            cx.start_timer(timer);
+
+            HStack::new(cx, |cx| {
+                VStack::new(cx, |cx| {
+                    HStack::new(cx, |cx| {
+                        for (name, command) in [
+                            ("REC", LooperCommand::Record),
+                            ("PLAY", LooperCommand::Play),
+                            ("STOP", LooperCommand::Stop),
+                            ("UNDO", LooperCommand::Undo),
+                            ("CLEAR", LooperCommand::Clear),
+                        ] {
+                            Button::new(
+                                cx,
+                                move |ex| ex.emit(LooperEvent::Command(command)),
+                                move |cx| Label::new(cx, name),
+                            )
+                            .class("looper-option")
+                            .toggle_class(
+                                "looper-unavailable",
+                                Self::can_undo
+                                    .map(move |can_undo| command == LooperCommand::Undo && !*can_undo),
+                            );
+                        }
+                    })
+                    .class("looper-row");
+                    HStack::new(cx, |cx| {
+                        Button::new(
+                            cx,
+                            |ex| ex.emit(LooperEvent::Command(LooperCommand::Reverse)),
+                            |cx| Label::new(cx, "REVERSE"),
+                        )
+                        .class("looper-option")
+                        .toggle_class("option-enabled", Self::reverse);
+                        Button::new(
+                            cx,
+                            |ex| ex.emit(LooperEvent::Command(LooperCommand::HalfSpeed)),
+                            |cx| Label::new(cx, "HALF"),
+                        )
+                        .class("looper-option")
+                        .toggle_class("option-enabled", Self::half_speed);
+                        Button::new(
+                            cx,
+                            |ex| ex.emit(LooperEvent::ToggleQuantize),
+                            |cx| Label::new(cx, "SYNC"),
+                        )
+                        .class("looper-option")
+                        .toggle_class("option-enabled", Self::quantize);
+                        Button::new(
+                            cx,
+                            |ex| ex.emit(LooperEvent::CycleMaxLength),
+                            |cx| {
+                                Label::new(
+                                    cx,
+                                    Self::max_length.map(|length| format!("MAX {:.0}s", length)),
+                                )
+                            },
+                        )
+                        .class("looper-option");
+                    })
+                    .class("looper-row");
+                    HStack::new(cx, |cx| {
+                        Label::new(cx, Self::state.map(|state| state.name()))
+                            .class("looper-state")
+                            .toggle_class(
+                                "looper-recording",
+                                Self::state.map(|state| {
+                                    matches!(state, LooperState::Recording | LooperState::Overdubbing)
+                                }),
+                            );
+                        ZStack::new(cx, |cx| {
+                            Element::new(cx)
+                                .class("looper-progress")
+                                .width(Self::progress.map(|progress| Percentage(progress * 100.0)));
+                        })
+                        .class("looper-track");
+                        Label::new(cx, Self::length.map(|length| format!("{:.1}s", length)))
+                            .class("looper-length");
+                    })
+                    .class("looper-row");
+                    HStack::new(cx, |cx| {
+                        Textbox::new(cx, Self::path)
+                            .class("looper-path")
+                            .on_submit(|ex, path, _| ex.emit(LooperEvent::Export(path)));
+                        Label::new(cx, "EXPORT WAV").class("looper-hint");
+                    })
+                    .class("looper-row");
+                })
+                .class("looper-controls");
+                NamedKnob::new(cx, "Level", Self::level, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Level(val)));
+                Label::new(cx, "LOOPER").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for LooperView {
+    fn element(&self) -> Option<&'static str> {
+        Some("looper")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Level(val) => {
+                self.level = *val;
+                self.handle.set_level(*val / 100.0);
+            }
+        });
+
+        event.map(|event, _| match event {
+            LooperEvent::Command(command) => {
+                self.handle.trigger(*command);
+            }
+            LooperEvent::CycleMaxLength => {
+                self.handle.cycle_max_length();
+                self.max_length = self.handle.max_length();
+            }
+            LooperEvent::ToggleQuantize => {
+                self.quantize = !self.quantize;
+                self.handle.set_quantize(self.quantize);
+            }
+            LooperEvent::Export(path) => match self.handle.export(path) {
+                Ok(true) => self.path = path.clone(),
+                Ok(false) => cx.emit(EffectError(String::from("There's no loop to export"))),
+                Err(err) => {
+                    cx.emit(EffectError(format!("Couldn't export loop to {}: {}", path, err)));
+                }
+            },
+            LooperEvent::Refresh => {
+                self.handle.release_old_buffers();
+                self.state = self.handle.state();
+                self.progress = self.handle.progress();
+                self.length = self.handle.length_seconds();
+                self.can_undo = self.handle.can_undo();
+                self.reverse = self.handle.reverse();
+                self.half_speed = self.handle.half_speed();
+            }
+        });
+    }
+}
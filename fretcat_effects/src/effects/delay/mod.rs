@@ -2,4 +2,13 @@ mod delay;
 pub use delay::MonoDelay;
 
 mod twin_delay;
-pub use twin_delay::TwinDelay;
\ No newline at end of file
+pub use twin_delay::TwinDelay;
+
+mod looper;
+pub use looper::{Looper, LooperCommand, LooperState};
+
+mod stereo_delay;
+pub use stereo_delay::StereoDelay;
+
+mod tape_echo;
+pub use tape_echo::TapeEcho;
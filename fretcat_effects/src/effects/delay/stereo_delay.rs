@@ -0,0 +1,596 @@
+use crate::prelude::*;
+
+const MAX_DELAY_MS: f32 = 2000.0;
+const MIN_DELAY_MS: f32 = 10.0;
+const MAX_FEEDBACK: f32 = 0.95;
+const DEFAULT_TEMPO: f64 = 120.0;
+// Time changes glide over about this long instead of jumping, like turning the knob on a pedal
+const TIME_SMOOTHING_MS: f32 = 80.0;
+const DUCK_ATTACK_MS: f32 = 10.0;
+const DUCK_RELEASE_MS: f32 = 250.0;
+// Input envelope that ducks the repeats all the way at full amount
+const DUCK_FULL_LEVEL: f32 = 0.25;
+const MAX_DRIVE: f32 = 5.0;
+
+/// Note lengths the delay times can sync to, in quarter notes, with their display names.
+pub const DELAY_DIVISIONS: [(f32, &str); 8] = [
+    (0.25, "1/16"),
+    (1.0 / 3.0, "1/8T"),
+    (0.5, "1/8"),
+    (0.75, "1/8."),
+    (2.0 / 3.0, "1/4T"),
+    (1.0, "1/4"),
+    (1.5, "1/4."),
+    (2.0, "1/2"),
+];
+
+#[derive(Serialize, Deserialize)]
+struct StereoDelaySettings {
+    time_l: f32,
+    time_r: f32,
+    synced: bool,
+    divisions: [usize; NUM_CHANNELS],
+    ping_pong: bool,
+    feedback: f32,
+    cross: f32,
+    width: f32,
+    low_cut: f32,
+    high_cut: f32,
+    drive: f32,
+    duck: f32,
+    mix: f32,
+}
+
+/// Stereo delay where each side's repeats can feed the other. Ping pong sends the input
+/// to the left line only and crosses all of the feedback, so repeats bounce between sides.
+/// The filters and saturation sit inside the feedback loop, every repeat goes through them again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StereoDelaySettings", into = "StereoDelaySettings")]
+pub struct StereoDelay {
+    time_l: f32,
+    time_r: f32,
+    synced: bool,
+    divisions: [usize; NUM_CHANNELS],
+    ping_pong: bool,
+    feedback: f32,
+    cross: f32,
+    width: f32,
+    low_cut: f32,
+    high_cut: f32,
+    drive: f32,
+    duck: f32,
+    mix: f32,
+
+    sample_rate: f32,
+    lines: [ModulatedDelay; NUM_CHANNELS],
+    current_delay: [f32; NUM_CHANNELS],
+    low_cuts: [SvfFilter; NUM_CHANNELS],
+    high_cuts: [SvfFilter; NUM_CHANNELS],
+    envelope: f32,
+}
+
+impl Default for StereoDelay {
+    fn default() -> Self {
+        StereoDelaySettings {
+            time_l: 375.0,
+            time_r: 500.0,
+            synced: false,
+            divisions: [3, 5],
+            ping_pong: false,
+            feedback: 0.4,
+            cross: 0.3,
+            width: 1.0,
+            low_cut: 100.0,
+            high_cut: 6000.0,
+            drive: 0.0,
+            duck: 0.0,
+            mix: 0.35,
+        }
+        .into()
+    }
+}
+
+impl From<StereoDelaySettings> for StereoDelay {
+    fn from(settings: StereoDelaySettings) -> Self {
+        let mut delay = Self {
+            time_l: settings.time_l,
+            time_r: settings.time_r,
+            synced: settings.synced,
+            divisions: settings.divisions,
+            ping_pong: settings.ping_pong,
+            feedback: settings.feedback,
+            cross: settings.cross,
+            width: settings.width,
+            low_cut: settings.low_cut,
+            high_cut: settings.high_cut,
+            drive: settings.drive,
+            duck: settings.duck,
+            mix: settings.mix,
+            sample_rate: 44100.0,
+            lines: [
+                ModulatedDelay::new(MAX_DELAY_MS),
+                ModulatedDelay::new(MAX_DELAY_MS),
+            ],
+            current_delay: [0.0; NUM_CHANNELS],
+            low_cuts: [SvfFilter::new(FilterMode::Highpass, 44100.0, settings.low_cut, 0.707);
+                NUM_CHANNELS],
+            high_cuts: [SvfFilter::new(FilterMode::Lowpass, 44100.0, settings.high_cut, 0.707);
+                NUM_CHANNELS],
+            envelope: 0.0,
+        };
+
+        // Presets can be edited by hand, so everything goes through the same limits as the knobs
+        delay.set_time_l(settings.time_l);
+        delay.set_time_r(settings.time_r);
+        for (channel, division) in settings.divisions.into_iter().enumerate() {
+            delay.set_division(channel, division);
+        }
+        delay.set_feedback(settings.feedback);
+        delay.set_cross(settings.cross);
+        delay.set_width(settings.width);
+        delay.set_low_cut(settings.low_cut);
+        delay.set_high_cut(settings.high_cut);
+        delay.set_drive(settings.drive);
+        delay.set_duck(settings.duck);
+        delay.set_mix(settings.mix);
+
+        delay.current_delay = delay.target_delay(DEFAULT_TEMPO);
+        delay
+    }
+}
+
+impl From<StereoDelay> for StereoDelaySettings {
+    fn from(delay: StereoDelay) -> Self {
+        Self {
+            time_l: delay.time_l,
+            time_r: delay.time_r,
+            synced: delay.synced,
+            divisions: delay.divisions,
+            ping_pong: delay.ping_pong,
+            feedback: delay.feedback,
+            cross: delay.cross,
+            width: delay.width,
+            low_cut: delay.low_cut,
+            high_cut: delay.high_cut,
+            drive: delay.drive,
+            duck: delay.duck,
+            mix: delay.mix,
+        }
+    }
+}
+
+impl PartialEq for StereoDelay {
+    fn eq(&self, other: &Self) -> bool {
+        self.time_l == other.time_l
+            && self.time_r == other.time_r
+            && self.synced == other.synced
+            && self.divisions == other.divisions
+            && self.ping_pong == other.ping_pong
+            && self.feedback == other.feedback
+            && self.cross == other.cross
+            && self.width == other.width
+            && self.low_cut == other.low_cut
+            && self.high_cut == other.high_cut
+            && self.drive == other.drive
+            && self.duck == other.duck
+            && self.mix == other.mix
+    }
+}
+
+impl StereoDelay {
+    #[inline]
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Left delay time in ms, used while not synced.
+    pub fn time_l(&self) -> f32 {
+        self.time_l
+    }
+
+    /// Right delay time in ms, used while not synced.
+    pub fn time_r(&self) -> f32 {
+        self.time_r
+    }
+
+    pub fn synced(&self) -> bool {
+        self.synced
+    }
+
+    /// Index into [`DELAY_DIVISIONS`] for each side while synced.
+    pub fn division(&self, channel: usize) -> usize {
+        self.divisions[channel]
+    }
+
+    pub fn ping_pong(&self) -> bool {
+        self.ping_pong
+    }
+
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    /// How much of each side's feedback goes to the other side, ignored in ping pong.
+    pub fn cross(&self) -> f32 {
+        self.cross
+    }
+
+    /// Stereo width of the repeats, 0 folds them to mono.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn low_cut(&self) -> f32 {
+        self.low_cut
+    }
+
+    pub fn high_cut(&self) -> f32 {
+        self.high_cut
+    }
+
+    /// Saturation inside the feedback loop, from 0 to 1.
+    pub fn drive(&self) -> f32 {
+        self.drive
+    }
+
+    /// How much the repeats drop while playing, from 0 to 1.
+    pub fn duck(&self) -> f32 {
+        self.duck
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    #[inline]
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for filter in self.low_cuts.iter_mut().chain(self.high_cuts.iter_mut()) {
+            filter.set_sample_rate(sample_rate);
+        }
+    }
+
+    pub fn set_time_l(&mut self, time_l: f32) {
+        self.time_l = time_l.clamp(MIN_DELAY_MS, MAX_DELAY_MS);
+    }
+
+    pub fn set_time_r(&mut self, time_r: f32) {
+        self.time_r = time_r.clamp(MIN_DELAY_MS, MAX_DELAY_MS);
+    }
+
+    pub fn set_synced(&mut self, synced: bool) {
+        self.synced = synced;
+    }
+
+    pub fn set_division(&mut self, channel: usize, division: usize) {
+        self.divisions[channel] = division.min(DELAY_DIVISIONS.len() - 1);
+    }
+
+    pub fn set_ping_pong(&mut self, ping_pong: bool) {
+        self.ping_pong = ping_pong;
+    }
+
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(0.0, MAX_FEEDBACK);
+    }
+
+    pub fn set_cross(&mut self, cross: f32) {
+        self.cross = cross.clamp(0.0, 1.0);
+    }
+
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width.clamp(0.0, 1.0);
+    }
+
+    pub fn set_low_cut(&mut self, low_cut: f32) {
+        self.low_cut = low_cut.clamp(20.0, 1000.0);
+        for filter in self.low_cuts.iter_mut() {
+            filter.set_cutoff(self.low_cut);
+        }
+    }
+
+    pub fn set_high_cut(&mut self, high_cut: f32) {
+        self.high_cut = high_cut.clamp(1000.0, 20000.0);
+        for filter in self.high_cuts.iter_mut() {
+            filter.set_cutoff(self.high_cut);
+        }
+    }
+
+    pub fn set_drive(&mut self, drive: f32) {
+        self.drive = drive.clamp(0.0, 1.0);
+    }
+
+    pub fn set_duck(&mut self, duck: f32) {
+        self.duck = duck.clamp(0.0, 1.0);
+    }
+
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    /// Delay of each side in samples for the current settings.
+    fn target_delay(&self, tempo: f64) -> [f32; NUM_CHANNELS] {
+        let times = if self.synced {
+            let quarter_ms = (60000.0 / tempo) as f32;
+            self.divisions
+                .map(|division| DELAY_DIVISIONS[division].0 * quarter_ms)
+        } else {
+            [self.time_l, self.time_r]
+        };
+        times.map(|time| time.clamp(MIN_DELAY_MS, MAX_DELAY_MS) * 0.001 * self.sample_rate)
+    }
+}
+
+impl AudioEffect for StereoDelay {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate() != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+            self.current_delay = self.target_delay(DEFAULT_TEMPO);
+        }
+
+        let target = self.target_delay(transport.tempo.unwrap_or(DEFAULT_TEMPO));
+        let smoothing = one_pole_coeff(TIME_SMOOTHING_MS, self.sample_rate);
+        let attack = one_pole_coeff(DUCK_ATTACK_MS, self.sample_rate);
+        let release = one_pole_coeff(DUCK_RELEASE_MS, self.sample_rate);
+        let cross = if self.ping_pong { 1.0 } else { self.cross };
+        let drive = 1.0 + self.drive * (MAX_DRIVE - 1.0);
+
+        let Self {
+            ping_pong,
+            feedback,
+            width,
+            duck,
+            mix,
+            lines,
+            current_delay,
+            low_cuts,
+            high_cuts,
+            envelope,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            let mut repeats = [0.0; NUM_CHANNELS];
+            for (channel, repeat) in repeats.iter_mut().enumerate() {
+                current_delay[channel] = target[channel]
+                    + smoothing * (current_delay[channel] - target[channel]);
+                *repeat = lines[channel].read(current_delay[channel]);
+            }
+
+            // Each line hears its own repeats and some of the other side's
+            let mut returns = [
+                (1.0 - cross) * repeats[0] + cross * repeats[1],
+                (1.0 - cross) * repeats[1] + cross * repeats[0],
+            ];
+            for (channel, sample) in returns.iter_mut().enumerate() {
+                let filtered = high_cuts[channel].tick(low_cuts[channel].tick(*sample));
+                // Saturates the loud repeats and leaves quiet ones alone
+                *sample = (filtered * drive).tanh() / drive * *feedback;
+            }
+
+            let inputs = if *ping_pong {
+                [(*left + *right) * 0.5, 0.0]
+            } else {
+                [*left, *right]
+            };
+            for ((line, input), repeat) in lines.iter_mut().zip(inputs).zip(returns) {
+                line.write(input + repeat);
+            }
+
+            let level = (left.abs() + right.abs()) * 0.5;
+            let coeff = if level > *envelope { attack } else { release };
+            *envelope = level + coeff * (*envelope - level);
+            let ducking = 1.0 - *duck * (*envelope / DUCK_FULL_LEVEL).min(1.0);
+
+            let mid = (repeats[0] + repeats[1]) * 0.5;
+            let side = (repeats[0] - repeats[1]) * 0.5 * *width;
+            let wet = [mid + side, mid - side];
+
+            *left += *mix * (wet[0] * ducking - *left);
+            *right += *mix * (wet[1] * ducking - *right);
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        StereoDelayView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        200.0
+    }
+}
+
+enum StereoDelayEvent {
+    TogglePingPong,
+    ToggleSync,
+    CycleDivision(usize),
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct StereoDelayView {
+    #[msg]
+    time_l: f32,
+    #[msg]
+    time_r: f32,
+    #[msg]
+    feedback: f32,
+    #[msg]
+    cross: f32,
+    #[msg]
+    width: f32,
+    #[msg]
+    low_cut: f32,
+    #[msg]
+    high_cut: f32,
+    #[msg]
+    drive: f32,
+    #[msg]
+    duck: f32,
+    #[msg]
+    mix: f32,
+
+    ping_pong: bool,
+    synced: bool,
+    divisions: [usize; NUM_CHANNELS],
+
+    #[lens(ignore)]
+    handle: EffectHandle<StereoDelay>,
+}
+
+impl StereoDelayView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<StereoDelay>) -> Handle<Self> {
+        Self {
+            time_l: handle.time_l(),
+            time_r: handle.time_r(),
+            feedback: handle.feedback() * 100.0,
+            cross: handle.cross() * 100.0,
+            width: handle.width() * 100.0,
+            low_cut: handle.low_cut(),
+            high_cut: handle.high_cut(),
+            drive: handle.drive() * 100.0,
+            duck: handle.duck() * 100.0,
+            mix: handle.mix() * 100.0,
+            ping_pong: handle.ping_pong(),
+            synced: handle.synced(),
+            divisions: [handle.division(0), handle.division(1)],
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                VStack::new(cx, |cx| {
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(StereoDelayEvent::TogglePingPong),
+                        |cx| Label::new(cx, "PING PONG"),
+                    )
+                    .class("stereo-delay-option")
+                    .toggle_class("option-enabled", Self::ping_pong);
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(StereoDelayEvent::ToggleSync),
+                        |cx| Label::new(cx, "SYNC"),
+                    )
+                    .class("stereo-delay-option")
+                    .toggle_class("option-enabled", Self::synced);
+                    for (channel, side) in ["L", "R"].into_iter().enumerate() {
+                        Button::new(
+                            cx,
+                            move |ex| ex.emit(StereoDelayEvent::CycleDivision(channel)),
+                            move |cx| {
+                                Label::new(
+                                    cx,
+                                    Self::divisions.map(move |divisions| {
+                                        format!("{} {}", side, DELAY_DIVISIONS[divisions[channel]].1)
+                                    }),
+                                )
+                            },
+                        )
+                        .class("stereo-delay-option")
+                        .disabled(Self::synced.map(|synced| !*synced));
+                    }
+                })
+                .class("stereo-delay-options");
+                VStack::new(cx, |cx| {
+                    HStack::new(cx, |cx| {
+                        NamedKnob::new(cx, "Time L", Self::time_l, false, MIN_DELAY_MS..MAX_DELAY_MS)
+                            .on_changing(|ex, val| ex.emit(Message::Time_l(val)))
+                            .disabled(Self::synced);
+                        NamedKnob::new(cx, "Time R", Self::time_r, false, MIN_DELAY_MS..MAX_DELAY_MS)
+                            .on_changing(|ex, val| ex.emit(Message::Time_r(val)))
+                            .disabled(Self::synced);
+                        NamedKnob::new(cx, "Feedback", Self::feedback, false, 0.0..MAX_FEEDBACK * 100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Feedback(val)));
+                        NamedKnob::new(cx, "Cross", Self::cross, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Cross(val)))
+                            .disabled(Self::ping_pong);
+                        NamedKnob::new(cx, "Width", Self::width, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Width(val)));
+                    })
+                    .class("stereo-delay-row");
+                    HStack::new(cx, |cx| {
+                        NamedKnob::new(cx, "Low Cut", Self::low_cut, false, 20.0..1000.0)
+                            .on_changing(|ex, val| ex.emit(Message::Low_cut(val)));
+                        NamedKnob::new(cx, "High Cut", Self::high_cut, false, 1000.0..20000.0)
+                            .on_changing(|ex, val| ex.emit(Message::High_cut(val)));
+                        NamedKnob::new(cx, "Drive", Self::drive, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Drive(val)));
+                        NamedKnob::new(cx, "Duck", Self::duck, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Duck(val)));
+                        NamedKnob::new(cx, "Mix", Self::mix, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Mix(val)));
+                    })
+                    .class("stereo-delay-row");
+                })
+                .class("stereo-delay-knobs");
+                Label::new(cx, "STEREO DELAY").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for StereoDelayView {
+    fn element(&self) -> Option<&'static str> {
+        Some("stereo-delay")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Time_l(val) => {
+                self.time_l = *val;
+                self.handle.set_time_l(*val);
+            }
+            Message::Time_r(val) => {
+                self.time_r = *val;
+                self.handle.set_time_r(*val);
+            }
+            Message::Feedback(val) => {
+                self.feedback = *val;
+                self.handle.set_feedback(*val / 100.0);
+            }
+            Message::Cross(val) => {
+                self.cross = *val;
+                self.handle.set_cross(*val / 100.0);
+            }
+            Message::Width(val) => {
+                self.width = *val;
+                self.handle.set_width(*val / 100.0);
+            }
+            Message::Low_cut(val) => {
+                self.low_cut = *val;
+                self.handle.set_low_cut(*val);
+            }
+            Message::High_cut(val) => {
+                self.high_cut = *val;
+                self.handle.set_high_cut(*val);
+            }
+            Message::Drive(val) => {
+                self.drive = *val;
+                self.handle.set_drive(*val / 100.0);
+            }
+            Message::Duck(val) => {
+                self.duck = *val;
+                self.handle.set_duck(*val / 100.0);
+            }
+            Message::Mix(val) => {
+                self.mix = *val;
+                self.handle.set_mix(*val / 100.0);
+            }
+        });
+
+        event.map(|event, _| match event {
+            StereoDelayEvent::TogglePingPong => {
+                self.ping_pong = !self.ping_pong;
+                self.handle.set_ping_pong(self.ping_pong);
+            }
+            StereoDelayEvent::ToggleSync => {
+                self.synced = !self.synced;
+                self.handle.set_synced(self.synced);
+            }
+            StereoDelayEvent::CycleDivision(channel) => {
+                let division = (self.divisions[*channel] + 1) % DELAY_DIVISIONS.len();
+                self.divisions[*channel] = division;
+                self.handle.set_division(*channel, division);
+            }
+        });
+    }
+}
@@ -0,0 +1,496 @@
+use crate::prelude::*;
+
+// Playback heads sit at 1, 2 and 3 times the distance of the first one from the record head
+const HEAD_RATIOS: [f32; 3] = [1.0, 2.0, 3.0];
+const MIN_RATE_MS: f32 = 50.0;
+const MAX_RATE_MS: f32 = 600.0;
+// Changing the motor speed takes a moment, the repeats bend in pitch on the way
+const SPEED_GLIDE_MS: f32 = 300.0;
+// A little above unity so the echo can run away like the real machine, the tape saturation keeps it bounded
+const MAX_INTENSITY: f32 = 1.1;
+const MAX_DRIVE: f32 = 6.0;
+// Speed deviation at full wow or flutter, as a fraction of the tape speed
+const WOW_DEPTH: f32 = 0.008;
+const FLUTTER_DEPTH: f32 = 0.0015;
+const WOW_HZ: f32 = 0.6;
+const FLUTTER_HZ: f32 = 9.0;
+// Worn tape loses its lows as well as its highs on every pass
+const BASS_LOSS_HZ: f32 = 120.0;
+
+const SPRING_STAGES: usize = 8;
+// Stiff springs delay the highs more than the lows, a chain of all-passes gives that chirp
+const SPRING_DISPERSION: f32 = 0.62;
+const SPRING_DELAY_MS: f32 = 37.0;
+const SPRING_FEEDBACK: f32 = 0.72;
+const SPRING_DAMPING_HZ: f32 = 4000.0;
+
+/// Active playback heads for each mode, as a bit per head, with their display names.
+pub const HEAD_MODES: [(u8, &str); 7] = [
+    (0b001, "1"),
+    (0b010, "2"),
+    (0b100, "3"),
+    (0b011, "1+2"),
+    (0b110, "2+3"),
+    (0b101, "1+3"),
+    (0b111, "1+2+3"),
+];
+
+/// Small spring tank, a dispersive all-pass chain inside a damped feedback loop.
+#[derive(Debug, Clone)]
+struct SpringReverb {
+    stages: [(f32, f32); SPRING_STAGES],
+    tank: ModulatedDelay,
+    damping: SvfFilter,
+    sample_rate: f32,
+}
+
+impl SpringReverb {
+    fn new() -> Self {
+        Self {
+            stages: [(0.0, 0.0); SPRING_STAGES],
+            tank: ModulatedDelay::new(SPRING_DELAY_MS * 2.0),
+            damping: SvfFilter::new(FilterMode::Lowpass, 44100.0, SPRING_DAMPING_HZ, 0.707),
+            sample_rate: 44100.0,
+        }
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.damping.set_sample_rate(sample_rate);
+    }
+
+    #[inline]
+    fn tick(&mut self, input: f32) -> f32 {
+        let delayed = self.tank.read(SPRING_DELAY_MS * 0.001 * self.sample_rate);
+        let mut sample = input + self.damping.tick(delayed) * SPRING_FEEDBACK;
+        for (x1, y1) in self.stages.iter_mut() {
+            let output = SPRING_DISPERSION * (sample - *y1) + *x1;
+            *x1 = sample;
+            *y1 = output;
+            sample = output;
+        }
+        self.tank.write(sample);
+        delayed
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TapeEchoSettings {
+    rate: f32,
+    mode: usize,
+    intensity: f32,
+    tone: f32,
+    saturation: f32,
+    wow: f32,
+    flutter: f32,
+    echo: f32,
+    reverb: f32,
+}
+
+/// Tape echo in the spirit of the Space Echo and Echoplex. A loop of tape passes a record head
+/// and up to three playback heads, the repeat rate sets the tape speed. Every repeat goes back
+/// through the tape, so it picks up more saturation and loses more top and bottom each time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "TapeEchoSettings", into = "TapeEchoSettings")]
+pub struct TapeEcho {
+    rate: f32,
+    mode: usize,
+    intensity: f32,
+    tone: f32,
+    saturation: f32,
+    wow: f32,
+    flutter: f32,
+    echo: f32,
+    reverb: f32,
+
+    sample_rate: f32,
+    tape: ModulatedDelay,
+    current_rate: f32,
+    wow_lfo: Lfo,
+    flutter_lfo: Lfo,
+    tone_filter: SvfFilter,
+    bass_loss: SvfFilter,
+    spring: SpringReverb,
+}
+
+impl Default for TapeEcho {
+    fn default() -> Self {
+        TapeEchoSettings {
+            rate: 250.0,
+            mode: 3,
+            intensity: 0.45,
+            tone: 3500.0,
+            saturation: 0.3,
+            wow: 0.3,
+            flutter: 0.3,
+            echo: 0.5,
+            reverb: 0.0,
+        }
+        .into()
+    }
+}
+
+impl From<TapeEchoSettings> for TapeEcho {
+    fn from(settings: TapeEchoSettings) -> Self {
+        let mut echo = Self {
+            rate: settings.rate,
+            mode: 0,
+            intensity: settings.intensity,
+            tone: settings.tone,
+            saturation: settings.saturation,
+            wow: settings.wow,
+            flutter: settings.flutter,
+            echo: settings.echo,
+            reverb: settings.reverb,
+            sample_rate: 44100.0,
+            tape: ModulatedDelay::new(MAX_RATE_MS * HEAD_RATIOS[2] * (1.0 + WOW_DEPTH + FLUTTER_DEPTH)),
+            current_rate: settings.rate,
+            wow_lfo: Lfo::new(LfoShape::Sine, LfoRate::Free(WOW_HZ)),
+            flutter_lfo: Lfo::new(LfoShape::SmoothRandom, LfoRate::Free(FLUTTER_HZ)),
+            tone_filter: SvfFilter::new(FilterMode::Lowpass, 44100.0, 3500.0, 0.707),
+            bass_loss: SvfFilter::new(FilterMode::Highpass, 44100.0, BASS_LOSS_HZ, 0.707),
+            spring: SpringReverb::new(),
+        };
+
+        // Presets can be edited by hand, so everything goes through the same limits as the knobs
+        echo.set_rate(settings.rate);
+        echo.set_mode(settings.mode);
+        echo.set_intensity(settings.intensity);
+        echo.set_tone(settings.tone);
+        echo.set_saturation(settings.saturation);
+        echo.set_wow(settings.wow);
+        echo.set_flutter(settings.flutter);
+        echo.set_echo(settings.echo);
+        echo.set_reverb(settings.reverb);
+        echo.current_rate = echo.rate;
+        echo
+    }
+}
+
+impl From<TapeEcho> for TapeEchoSettings {
+    fn from(echo: TapeEcho) -> Self {
+        Self {
+            rate: echo.rate,
+            mode: echo.mode,
+            intensity: echo.intensity,
+            tone: echo.tone,
+            saturation: echo.saturation,
+            wow: echo.wow,
+            flutter: echo.flutter,
+            echo: echo.echo,
+            reverb: echo.reverb,
+        }
+    }
+}
+
+impl PartialEq for TapeEcho {
+    fn eq(&self, other: &Self) -> bool {
+        self.rate == other.rate
+            && self.mode == other.mode
+            && self.intensity == other.intensity
+            && self.tone == other.tone
+            && self.saturation == other.saturation
+            && self.wow == other.wow
+            && self.flutter == other.flutter
+            && self.echo == other.echo
+            && self.reverb == other.reverb
+    }
+}
+
+impl TapeEcho {
+    #[inline]
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Delay of the first playback head in ms.
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Index into [`HEAD_MODES`].
+    pub fn mode(&self) -> usize {
+        self.mode
+    }
+
+    /// Amount of the playback fed back to the record head.
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    pub fn tone(&self) -> f32 {
+        self.tone
+    }
+
+    pub fn saturation(&self) -> f32 {
+        self.saturation
+    }
+
+    pub fn wow(&self) -> f32 {
+        self.wow
+    }
+
+    pub fn flutter(&self) -> f32 {
+        self.flutter
+    }
+
+    /// Level of the repeats, the dry signal always passes through.
+    pub fn echo(&self) -> f32 {
+        self.echo
+    }
+
+    /// Level of the spring reverb.
+    pub fn reverb(&self) -> f32 {
+        self.reverb
+    }
+
+    #[inline]
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.wow_lfo.set_sample_rate(sample_rate);
+        self.flutter_lfo.set_sample_rate(sample_rate);
+        self.tone_filter.set_sample_rate(sample_rate);
+        self.bass_loss.set_sample_rate(sample_rate);
+        self.spring.set_sample_rate(sample_rate);
+    }
+
+    /// The tape speeds up or slows down to the new rate instead of jumping to it.
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.clamp(MIN_RATE_MS, MAX_RATE_MS);
+    }
+
+    pub fn set_mode(&mut self, mode: usize) {
+        self.mode = mode.min(HEAD_MODES.len() - 1);
+    }
+
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity.clamp(0.0, MAX_INTENSITY);
+    }
+
+    pub fn set_tone(&mut self, tone: f32) {
+        self.tone = tone.clamp(1000.0, 10000.0);
+        self.tone_filter.set_cutoff(self.tone);
+    }
+
+    pub fn set_saturation(&mut self, saturation: f32) {
+        self.saturation = saturation.clamp(0.0, 1.0);
+    }
+
+    pub fn set_wow(&mut self, wow: f32) {
+        self.wow = wow.clamp(0.0, 1.0);
+    }
+
+    pub fn set_flutter(&mut self, flutter: f32) {
+        self.flutter = flutter.clamp(0.0, 1.0);
+    }
+
+    pub fn set_echo(&mut self, echo: f32) {
+        self.echo = echo.clamp(0.0, 1.0);
+    }
+
+    pub fn set_reverb(&mut self, reverb: f32) {
+        self.reverb = reverb.clamp(0.0, 1.0);
+    }
+}
+
+impl AudioEffect for TapeEcho {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate() != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        let glide = one_pole_coeff(SPEED_GLIDE_MS, self.sample_rate);
+        let samples_per_ms = self.sample_rate * 0.001;
+        let heads = HEAD_MODES[self.mode].0;
+        let active_heads = heads.count_ones() as f32;
+        let drive = 1.0 + self.saturation * (MAX_DRIVE - 1.0);
+
+        let Self {
+            rate,
+            intensity,
+            wow,
+            flutter,
+            echo,
+            reverb,
+            tape,
+            current_rate,
+            wow_lfo,
+            flutter_lfo,
+            tone_filter,
+            bass_loss,
+            spring,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            *current_rate = *rate + glide * (*current_rate - *rate);
+            let speed = 1.0
+                + wow_lfo.tick()[0] * *wow * WOW_DEPTH
+                + flutter_lfo.tick()[0] * *flutter * FLUTTER_DEPTH;
+            let head_delay = *current_rate * samples_per_ms * speed;
+
+            let mut playback = 0.0;
+            for (head, ratio) in HEAD_RATIOS.iter().enumerate() {
+                if heads & (1 << head) != 0 {
+                    playback += tape.read(head_delay * ratio);
+                }
+            }
+
+            let input = (*left + *right) * 0.5;
+            // More heads mean more feedback paths, share the intensity between them
+            let feedback = tone_filter.tick(bass_loss.tick(playback)) * *intensity / active_heads;
+            tape.write((drive * (input + feedback)).tanh() / drive);
+
+            let wet = playback * *echo + spring.tick(input + playback) * *reverb;
+            *left += wet;
+            *right += wet;
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        TapeEchoView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        200.0
+    }
+}
+
+enum TapeEchoEvent {
+    Mode(usize),
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct TapeEchoView {
+    #[msg]
+    rate: f32,
+    #[msg]
+    intensity: f32,
+    #[msg]
+    tone: f32,
+    #[msg]
+    saturation: f32,
+    #[msg]
+    wow: f32,
+    #[msg]
+    flutter: f32,
+    #[msg]
+    echo: f32,
+    #[msg]
+    reverb: f32,
+
+    mode: usize,
+
+    #[lens(ignore)]
+    handle: EffectHandle<TapeEcho>,
+}
+
+impl TapeEchoView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<TapeEcho>) -> Handle<Self> {
+        Self {
+            rate: handle.rate(),
+            intensity: handle.intensity() * 100.0,
+            tone: handle.tone(),
+            saturation: handle.saturation() * 100.0,
+            wow: handle.wow() * 100.0,
+            flutter: handle.flutter() * 100.0,
+            echo: handle.echo() * 100.0,
+            reverb: handle.reverb() * 100.0,
+            mode: handle.mode(),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                VStack::new(cx, |cx| {
+                    Label::new(cx, "HEADS").class("tape-echo-heading");
+                    for (mode, &(_, name)) in HEAD_MODES.iter().enumerate() {
+                        Button::new(
+                            cx,
+                            move |ex| ex.emit(TapeEchoEvent::Mode(mode)),
+                            move |cx| Label::new(cx, name),
+                        )
+                        .class("tape-echo-head")
+                        .toggle_class("option-enabled", Self::mode.map(move |current| *current == mode));
+                    }
+                })
+                .class("tape-echo-heads");
+                VStack::new(cx, |cx| {
+                    HStack::new(cx, |cx| {
+                        NamedKnob::new(cx, "Rate", Self::rate, false, MIN_RATE_MS..MAX_RATE_MS)
+                            .on_changing(|ex, val| ex.emit(Message::Rate(val)));
+                        NamedKnob::new(cx, "Intensity", Self::intensity, false, 0.0..MAX_INTENSITY * 100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Intensity(val)));
+                        NamedKnob::new(cx, "Tone", Self::tone, false, 1000.0..10000.0)
+                            .on_changing(|ex, val| ex.emit(Message::Tone(val)));
+                        NamedKnob::new(cx, "Saturation", Self::saturation, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Saturation(val)));
+                    })
+                    .class("tape-echo-row");
+                    HStack::new(cx, |cx| {
+                        NamedKnob::new(cx, "Wow", Self::wow, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Wow(val)));
+                        NamedKnob::new(cx, "Flutter", Self::flutter, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Flutter(val)));
+                        NamedKnob::new(cx, "Echo", Self::echo, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Echo(val)));
+                        NamedKnob::new(cx, "Reverb", Self::reverb, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Reverb(val)));
+                    })
+                    .class("tape-echo-row");
+                })
+                .class("tape-echo-knobs");
+                Label::new(cx, "TAPE ECHO").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for TapeEchoView {
+    fn element(&self) -> Option<&'static str> {
+        Some("tape-echo")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Rate(val) => {
+                self.rate = *val;
+                self.handle.set_rate(*val);
+            }
+            Message::Intensity(val) => {
+                self.intensity = *val;
+                self.handle.set_intensity(*val / 100.0);
+            }
+            Message::Tone(val) => {
+                self.tone = *val;
+                self.handle.set_tone(*val);
+            }
+            Message::Saturation(val) => {
+                self.saturation = *val;
+                self.handle.set_saturation(*val / 100.0);
+            }
+            Message::Wow(val) => {
+                self.wow = *val;
+                self.handle.set_wow(*val / 100.0);
+            }
+            Message::Flutter(val) => {
+                self.flutter = *val;
+                self.handle.set_flutter(*val / 100.0);
+            }
+            Message::Echo(val) => {
+                self.echo = *val;
+                self.handle.set_echo(*val / 100.0);
+            }
+            Message::Reverb(val) => {
+                self.reverb = *val;
+                self.handle.set_reverb(*val / 100.0);
+            }
+        });
+
+        event.map(|event, _| match event {
+            TapeEchoEvent::Mode(mode) => {
+                self.mode = *mode;
+                self.handle.set_mode(*mode);
+            }
+        });
+    }
+}
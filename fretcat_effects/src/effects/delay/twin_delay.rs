@@ -12,7 +12,10 @@ impl Default for TwinDelay {
         Self {
             wet_l: 0.5,
             wet_r: 0.5,
-            delays: [Delay::default(), Delay::default()],
+            delays: [
+                Delay::new(MAX_DELAY_SECS),
+                Delay::new(MAX_DELAY_SECS),
+            ],
         }
     }
 }
@@ -24,17 +27,16 @@ impl PartialEq for TwinDelay {
         self.delays[0].feedback() == other.delays[0].feedback() &&
         self.delays[1].feedback() == other.delays[1].feedback() &&
         self.delays[0].delay_time_secs() == other.delays[0].delay_time_secs() &&
-        self.delays[1].delay_time_secs() == other.delays[1].delay_time_secs()
+        self.delays[1].delay_time_secs() == other.delays[1].delay_time_secs() &&
+        self.delays[0].time_mode() == other.delays[0].time_mode()
     }
 }
 
 impl AudioEffect for TwinDelay {
     fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
         if transport.sample_rate != self.delays[0].sample_rate() {
-            nih_plug::util::permit_alloc(|| {
-                self.delays[0].set_sample_rate(transport.sample_rate);
-                self.delays[1].set_sample_rate(transport.sample_rate);
-            });
+            self.delays[0].set_sample_rate(transport.sample_rate);
+            self.delays[1].set_sample_rate(transport.sample_rate);
         }
 
         input_buffer.process_individual(|left, right| {
@@ -52,6 +54,10 @@ impl AudioEffect for TwinDelay {
     }
 }
 
+enum TwinDelayEvent {
+    CycleTimeMode,
+}
+
 #[derive(Debug, Clone, Lens, Message)]
 struct TwinDelayView {
     #[msg]
@@ -68,6 +74,8 @@ struct TwinDelayView {
     #[msg]
     wet_r: f32,
 
+    time_mode: DelayTimeMode,
+
     #[lens(ignore)]
     handle: EffectHandle<TwinDelay>,
 }
@@ -83,13 +91,15 @@ impl TwinDelayView {
             time_r: handle.delays[1].delay_time_secs() * 1000.0,
             feedback_r: handle.delays[1].feedback() * 100.0,
 
+            time_mode: handle.delays[0].time_mode(),
+
             handle: handle.clone(),
         }
         .build(cx, |cx| {
             HStack::new(cx, |cx| {
                 VStack::new(cx, |cx| {
                     HStack::new(cx, |cx| {
-                        NamedKnob::new(cx, "Time", Self::time_l, false, 20.0..1000.0)
+                        NamedKnob::new(cx, "Time", Self::time_l, false, 20.0..MAX_DELAY_SECS * 1000.0)
                             .on_changing(|ex, val| ex.emit(Message::Time_l(val)));
                         Label::new(cx, "Left").class("side-indicator");
                         NamedKnob::new(cx, "Feedback", Self::feedback_l, false, 0.0..100.0)
@@ -102,7 +112,7 @@ impl TwinDelayView {
                 .class("knob-group");
                 VStack::new(cx, |cx| {
                     HStack::new(cx, |cx| {
-                        NamedKnob::new(cx, "Time", Self::time_r, false, 20.0..1000.0)
+                        NamedKnob::new(cx, "Time", Self::time_r, false, 20.0..MAX_DELAY_SECS * 1000.0)
                             .on_changing(|ex, val| ex.emit(Message::Time_r(val)));
                         Label::new(cx, "Right").class("side-indicator");
                         NamedKnob::new(cx, "Feedback", Self::feedback_r, false, 0.0..100.0)
@@ -113,6 +123,12 @@ impl TwinDelayView {
                         .on_changing(|ex, val| ex.emit(Message::Wet_r(val)));
                 })
                 .class("knob-group");
+                Button::new(
+                    cx,
+                    |ex| ex.emit(TwinDelayEvent::CycleTimeMode),
+                    |cx| Label::new(cx, Self::time_mode.map(|mode| mode.name())),
+                )
+                .class("delay-mode");
                 Label::new(cx, "TWIN DELAY").class("effect-title");
             });
         })
@@ -156,5 +172,16 @@ impl View for TwinDelayView {
                 self.handle.wet_r = *val / 100.0;
             }
         });
+
+        event.map(|event, _| match event {
+            TwinDelayEvent::CycleTimeMode => {
+                let modes = DelayTimeMode::ALL;
+                let index = modes.iter().position(|mode| *mode == self.time_mode).unwrap_or(0);
+                self.time_mode = modes[(index + 1) % modes.len()];
+                self.handle.delays.iter_mut().for_each(|delay| {
+                    delay.set_time_mode(self.time_mode);
+                });
+            }
+        });
     }
 }
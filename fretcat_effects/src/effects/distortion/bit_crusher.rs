@@ -1,44 +1,175 @@
 use crate::prelude::*;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+const MIN_BITS: f32 = 1.0;
+const MAX_BITS: f32 = 24.0;
+const MIN_RATE_HZ: f32 = 200.0;
+const MAX_RATE_HZ: f32 = 48000.0;
+// Filters sit a little under the held rate's Nyquist
+const FILTER_RATIO: f32 = 0.45;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum Dither {
+    Off,
+    /// Flat noise one step wide, removes the signal dependent distortion.
+    Rectangular,
+    /// Two rectangular sources summed, also keeps the noise floor from pumping.
+    Triangular,
+}
+
+impl Dither {
+    pub const ALL: [Dither; 3] = [Dither::Off, Dither::Rectangular, Dither::Triangular];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dither::Off => "NO DITHER",
+            Dither::Rectangular => "RPDF",
+            Dither::Triangular => "TPDF",
+        }
+    }
+}
+
+fn max_bits() -> f32 {
+    MAX_BITS
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BitCrusher {
-    bit_rate: f32,
+    // Older presets had no quantization, so they load at full depth
+    #[serde(default = "max_bits")]
+    bits: f32,
+    // Presets from before bit depth reduction called the hold rate `bit_rate`
+    #[serde(alias = "bit_rate")]
+    rate: f32,
+    dither: Dither,
+    pre_filter: bool,
+    post_filter: bool,
+    mix: f32,
+
     sample_rate: f32,
+    phase: f32,
+    held: [f32; NUM_CHANNELS],
+    seed: u32,
+    pre_filters: [ButterLowpass; NUM_CHANNELS],
+    post_filters: [ButterLowpass; NUM_CHANNELS],
 }
 
 impl Default for BitCrusher {
     fn default() -> Self {
         Self {
+            bits: 8.0,
+            rate: 11025.0,
+            dither: Dither::Off,
+            pre_filter: false,
+            post_filter: false,
+            mix: 1.0,
             sample_rate: 44100.0,
-            bit_rate: 44100.0,
+            phase: 0.0,
+            held: [0.0; NUM_CHANNELS],
+            seed: 0x9e37_79b9,
+            pre_filters: [ButterLowpass::new(11025.0 * FILTER_RATIO); NUM_CHANNELS],
+            post_filters: [ButterLowpass::new(11025.0 * FILTER_RATIO); NUM_CHANNELS],
         }
     }
 }
 
+impl PartialEq for BitCrusher {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits == other.bits
+            && self.rate == other.rate
+            && self.dither == other.dither
+            && self.pre_filter == other.pre_filter
+            && self.post_filter == other.post_filter
+            && self.mix == other.mix
+    }
+}
+
 impl BitCrusher {
     #[inline]
     pub fn sample_rate(&self) -> f32 {
         self.sample_rate
     }
 
-    #[inline]
-    pub fn bit_rate(&self) -> f32 {
-        self.bit_rate
+    pub fn bits(&self) -> f32 {
+        self.bits
+    }
+
+    /// Rate the signal is held at in Hz.
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    pub fn dither(&self) -> Dither {
+        self.dither
+    }
+
+    pub fn pre_filter(&self) -> bool {
+        self.pre_filter
+    }
+
+    pub fn post_filter(&self) -> bool {
+        self.post_filter
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
     }
 
     #[inline]
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         self.sample_rate = sample_rate;
+        for filter in self.pre_filters.iter_mut().chain(self.post_filters.iter_mut()) {
+            filter.set_sample_rate(sample_rate);
+        }
+        self.update_filters();
     }
 
-    #[inline]
-    pub fn set_bit_rate(&mut self, bit_rate: f32) {
-        self.bit_rate = bit_rate;
+    /// Fractional depths are allowed, the step size follows the knob smoothly.
+    pub fn set_bits(&mut self, bits: f32) {
+        self.bits = bits.clamp(MIN_BITS, MAX_BITS);
+    }
+
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.clamp(MIN_RATE_HZ, MAX_RATE_HZ);
+        self.update_filters();
     }
 
+    pub fn set_dither(&mut self, dither: Dither) {
+        self.dither = dither;
+    }
+
+    /// Low pass before the hold, so the held signal doesn't alias.
+    pub fn set_pre_filter(&mut self, pre_filter: bool) {
+        self.pre_filter = pre_filter;
+        self.pre_filters.iter_mut().for_each(|filter| filter.reset());
+        self.update_filters();
+    }
+
+    /// Low pass after the hold, which smooths the steps back out.
+    pub fn set_post_filter(&mut self, post_filter: bool) {
+        self.post_filter = post_filter;
+        self.post_filters.iter_mut().for_each(|filter| filter.reset());
+        self.update_filters();
+    }
+
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    fn update_filters(&mut self) {
+        let cutoff = (self.rate * FILTER_RATIO).min(self.sample_rate * FILTER_RATIO);
+        for filter in self.pre_filters.iter_mut().chain(self.post_filters.iter_mut()) {
+            filter.set_cutoff(cutoff);
+        }
+    }
+
+    // xorshift, enough for dither and never touches the allocator
     #[inline]
-    fn step_size(&self) -> usize {
-        (self.sample_rate() / self.bit_rate()) as usize
+    fn next_random(seed: &mut u32) -> f32 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 17;
+        *seed ^= *seed << 5;
+        *seed as f32 / u32::MAX as f32
     }
 }
 
@@ -48,23 +179,57 @@ impl AudioEffect for BitCrusher {
             self.set_sample_rate(transport.sample_rate);
         }
 
-        let step_size = self.step_size();
+        // Phase advance per sample, a rate at or above the host's holds every sample
+        let step = (self.rate.max(MIN_RATE_HZ) / self.sample_rate).min(1.0);
+        let scale = 2.0_f32.powf(self.bits - 1.0);
 
-        let mut sample_index = 0;
-        let buffer_size = input_buffer.len();
+        let Self {
+            dither,
+            pre_filter,
+            post_filter,
+            mix,
+            phase,
+            held,
+            seed,
+            pre_filters,
+            post_filters,
+            ..
+        } = self;
 
-        while sample_index < buffer_size {
-            let first_index = sample_index;
-            let limit_index = (sample_index + step_size).min(buffer_size);
+        input_buffer.process_individual(|left, right| {
+            *phase += step;
+            let hold = *phase >= 1.0;
+            if hold {
+                *phase -= 1.0;
+            }
 
-            while sample_index < limit_index {
-                input_buffer.process_channel(|channel| {
-                    let value = channel[first_index];
-                    channel[sample_index] = value;
-                });
-                sample_index += 1;
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let dry = *sample;
+                let filtered = if *pre_filter {
+                    pre_filters[channel].tick(dry)
+                } else {
+                    dry
+                };
+
+                if hold {
+                    let noise = match dither {
+                        Dither::Off => 0.0,
+                        Dither::Rectangular => Self::next_random(seed) - 0.5,
+                        Dither::Triangular => {
+                            Self::next_random(seed) + Self::next_random(seed) - 1.0
+                        }
+                    };
+                    held[channel] = ((filtered * scale + noise).round() / scale).clamp(-1.0, 1.0);
+                }
+
+                let wet = if *post_filter {
+                    post_filters[channel].tick(held[channel])
+                } else {
+                    held[channel]
+                };
+                *sample = dry + *mix * (wet - dry);
             }
-        }
+        });
     }
 
     fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
@@ -76,26 +241,77 @@ impl AudioEffect for BitCrusher {
     }
 }
 
-#[derive(Debug, Clone, Lens, Message, Data)]
+enum BitCrusherEvent {
+    CycleDither,
+    TogglePreFilter,
+    TogglePostFilter,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
 struct BitCrusherView {
     #[msg]
-    bit_rate: f32,
+    bits: f32,
+    #[msg]
+    rate: f32,
+    #[msg]
+    mix: f32,
+
+    dither: Dither,
+    pre_filter: bool,
+    post_filter: bool,
 
     #[lens(ignore)]
-    #[data(ignore)]
     handle: EffectHandle<BitCrusher>,
 }
 
 impl BitCrusherView {
     pub fn new(cx: &mut Context, handle: EffectHandle<BitCrusher>) -> Handle<Self> {
         Self {
-            bit_rate: (handle.bit_rate / handle.sample_rate) * 100.0,
+            bits: handle.bits(),
+            rate: handle.rate(),
+            mix: handle.mix() * 100.0,
+            dither: handle.dither(),
+            pre_filter: handle.pre_filter(),
+            post_filter: handle.post_filter(),
             handle: handle.clone(),
         }
         .build(cx, |cx| {
-            NamedKnob::new(cx, "Amount", Self::bit_rate, false, 0.0..100.0)
-                .on_changing(|ex, val| ex.emit(Message::Bit_rate(val)));
-            Label::new(cx, "BIT CRUSHER").class("effect-title");
+            HStack::new(cx, |cx| {
+                VStack::new(cx, |cx| {
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(BitCrusherEvent::CycleDither),
+                        |cx| Label::new(cx, Self::dither.map(|dither| dither.name())),
+                    )
+                    .class("bit-crusher-option")
+                    .toggle_class(
+                        "option-enabled",
+                        Self::dither.map(|dither| *dither != Dither::Off),
+                    );
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(BitCrusherEvent::TogglePreFilter),
+                        |cx| Label::new(cx, "PRE FILTER"),
+                    )
+                    .class("bit-crusher-option")
+                    .toggle_class("option-enabled", Self::pre_filter);
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(BitCrusherEvent::TogglePostFilter),
+                        |cx| Label::new(cx, "POST FILTER"),
+                    )
+                    .class("bit-crusher-option")
+                    .toggle_class("option-enabled", Self::post_filter);
+                })
+                .class("bit-crusher-options");
+                NamedKnob::new(cx, "Bits", Self::bits, false, MIN_BITS..MAX_BITS)
+                    .on_changing(|ex, val| ex.emit(Message::Bits(val)));
+                NamedKnob::new(cx, "Rate", Self::rate, false, MIN_RATE_HZ..MAX_RATE_HZ)
+                    .on_changing(|ex, val| ex.emit(Message::Rate(val)));
+                NamedKnob::new(cx, "Mix", Self::mix, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Mix(val)));
+                Label::new(cx, "BIT CRUSHER").class("effect-title");
+            });
         })
     }
 }
@@ -107,9 +323,36 @@ impl View for BitCrusherView {
 
     fn event(&mut self, _: &mut EventContext, event: &mut Event) {
         event.map(|event, _| match event {
-            Message::Bit_rate(val) => {
-                self.bit_rate = *val;
-                self.handle.bit_rate = (*val / 100.0) * self.handle.sample_rate;
+            Message::Bits(val) => {
+                self.bits = *val;
+                self.handle.set_bits(*val);
+            }
+            Message::Rate(val) => {
+                self.rate = *val;
+                self.handle.set_rate(*val);
+            }
+            Message::Mix(val) => {
+                self.mix = *val;
+                self.handle.set_mix(*val / 100.0);
+            }
+        });
+
+        event.map(|event, _| match event {
+            BitCrusherEvent::CycleDither => {
+                let current = Dither::ALL
+                    .iter()
+                    .position(|dither| *dither == self.dither)
+                    .unwrap_or(0);
+                self.dither = Dither::ALL[(current + 1) % Dither::ALL.len()];
+                self.handle.set_dither(self.dither);
+            }
+            BitCrusherEvent::TogglePreFilter => {
+                self.pre_filter = !self.pre_filter;
+                self.handle.set_pre_filter(self.pre_filter);
+            }
+            BitCrusherEvent::TogglePostFilter => {
+                self.post_filter = !self.post_filter;
+                self.handle.set_post_filter(self.post_filter);
             }
         });
     }
@@ -8,4 +8,13 @@ mod fuzz;
 pub use fuzz::Fuzz;
 
 mod bit_crusher;
-pub use bit_crusher::BitCrusher;
\ No newline at end of file
+pub use bit_crusher::BitCrusher;
+
+mod tube_screamer;
+pub use tube_screamer::TubeScreamer;
+
+mod waveshaper;
+pub use waveshaper::{ClipType, CurvePoint, Waveshaper};
+
+mod multiband;
+pub use multiband::{DistortionBand, MultibandDistortion};
@@ -0,0 +1,411 @@
+use rustfft::num_complex::Complex32;
+
+use crate::prelude::*;
+
+const MIN_CROSSOVER_HZ: f32 = 40.0;
+const MAX_CROSSOVER_HZ: f32 = 12000.0;
+// Closest two crossover points can get, as a frequency ratio
+const MIN_SPLIT_RATIO: f32 = 1.5;
+const DC_CUTOFF_HZ: f32 = 10.0;
+const BAND_CLIP_TYPES: [ClipType; 6] = [
+    ClipType::Hard,
+    ClipType::Soft,
+    ClipType::Tanh,
+    ClipType::Diode,
+    ClipType::Foldback,
+    ClipType::Rectifier,
+];
+
+const MAX_DRIVE_DB: f32 = 36.0;
+const LEVEL_RANGE_DB: f32 = 24.0;
+const DB_RANGE: f32 = 24.0;
+const RESPONSE_RESOLUTION: usize = 256;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct DistortionBand {
+    drive_db: f32,
+    clip_type: ClipType,
+    level_db: f32,
+}
+
+impl Default for DistortionBand {
+    fn default() -> Self {
+        Self {
+            drive_db: 12.0,
+            clip_type: ClipType::Tanh,
+            level_db: 0.0,
+        }
+    }
+}
+
+impl DistortionBand {
+    pub fn drive_db(&self) -> f32 {
+        self.drive_db
+    }
+
+    pub fn clip_type(&self) -> ClipType {
+        self.clip_type
+    }
+
+    pub fn level_db(&self) -> f32 {
+        self.level_db
+    }
+
+    pub fn set_drive_db(&mut self, drive_db: f32) {
+        self.drive_db = drive_db.clamp(0.0, MAX_DRIVE_DB);
+    }
+
+    /// The drawn curve belongs to the waveshaper, so `Custom` falls back to a hard clip.
+    pub fn set_clip_type(&mut self, clip_type: ClipType) {
+        self.clip_type = clip_type;
+    }
+
+    pub fn set_level_db(&mut self, level_db: f32) {
+        self.level_db = level_db.clamp(-LEVEL_RANGE_DB, LEVEL_RANGE_DB);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultibandDistortion {
+    bands: [DistortionBand; MAX_BANDS],
+    crossovers: [Crossover; NUM_CHANNELS],
+
+    sample_rate: f32,
+    dc_blocks: [[DCBlock; MAX_BANDS]; NUM_CHANNELS],
+}
+
+impl Default for MultibandDistortion {
+    fn default() -> Self {
+        Self {
+            bands: [DistortionBand::default(); MAX_BANDS],
+            crossovers: [Crossover::new(3, [250.0, 2000.0], 44100.0); NUM_CHANNELS],
+            sample_rate: 44100.0,
+            dc_blocks: [[DCBlock::new(DC_CUTOFF_HZ); MAX_BANDS]; NUM_CHANNELS],
+        }
+    }
+}
+
+impl PartialEq for MultibandDistortion {
+    fn eq(&self, other: &Self) -> bool {
+        self.bands == other.bands
+            && self.num_bands() == other.num_bands()
+            && self.frequency(0) == other.frequency(0)
+            && self.frequency(1) == other.frequency(1)
+    }
+}
+
+impl MultibandDistortion {
+    #[inline]
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn band(&self, band: usize) -> &DistortionBand {
+        &self.bands[band]
+    }
+
+    pub fn band_mut(&mut self, band: usize) -> &mut DistortionBand {
+        &mut self.bands[band]
+    }
+
+    pub fn num_bands(&self) -> usize {
+        self.crossovers[0].bands()
+    }
+
+    /// Crossover point `index` in Hz, counted from the low end.
+    pub fn frequency(&self, index: usize) -> f32 {
+        self.crossovers[0].frequency(index)
+    }
+
+    #[inline]
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for crossover in self.crossovers.iter_mut() {
+            crossover.set_sample_rate(sample_rate);
+        }
+        for dc_block in self.dc_blocks.iter_mut().flatten() {
+            dc_block.set_sample_rate(sample_rate);
+        }
+    }
+
+    pub fn set_num_bands(&mut self, bands: usize) {
+        for crossover in self.crossovers.iter_mut() {
+            crossover.set_bands(bands);
+        }
+        self.dc_blocks.iter_mut().flatten().for_each(|dc_block| dc_block.reset());
+
+        // The upper point stays wherever it was while only the lower one was in use
+        self.set_frequency(1, self.frequency(1));
+        self.set_frequency(0, self.frequency(0));
+    }
+
+    /// Points are kept in order and at least [`MIN_SPLIT_RATIO`] apart.
+    pub fn set_frequency(&mut self, index: usize, frequency: f32) {
+        let (min, max) = match index {
+            0 if self.num_bands() > 2 => (MIN_CROSSOVER_HZ, self.frequency(1) / MIN_SPLIT_RATIO),
+            0 => (MIN_CROSSOVER_HZ, MAX_CROSSOVER_HZ),
+            _ => (self.frequency(0) * MIN_SPLIT_RATIO, MAX_CROSSOVER_HZ),
+        };
+        let frequency = frequency.max(min).min(max);
+        for crossover in self.crossovers.iter_mut() {
+            crossover.set_frequency(index, frequency);
+        }
+    }
+
+    /// Level weighted sum of the band filters in dB, what the crossover does with the
+    /// drive left out.
+    pub fn response(&self, frequencies: &[f32]) -> Vec<f32> {
+        let levels = self.bands.map(|band| db_to_gain(band.level_db));
+        frequencies
+            .iter()
+            .map(|frequency| {
+                let sum: Complex32 = self.crossovers[0]
+                    .response(*frequency)
+                    .into_iter()
+                    .zip(levels)
+                    .map(|(response, level)| response * level)
+                    .sum();
+                gain_to_db(sum.norm())
+            })
+            .collect()
+    }
+}
+
+impl AudioEffect for MultibandDistortion {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate() != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        let num_bands = self.num_bands();
+        let drives = self.bands.map(|band| db_to_gain(band.drive_db));
+        let levels = self.bands.map(|band| db_to_gain(band.level_db));
+
+        let Self {
+            bands,
+            crossovers,
+            dc_blocks,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let split = crossovers[channel].split(*sample);
+                *sample = split
+                    .into_iter()
+                    .zip(bands.iter())
+                    .zip(dc_blocks[channel].iter_mut())
+                    .zip(drives.into_iter().zip(levels))
+                    .take(num_bands)
+                    .map(|(((band_sample, band), dc_block), (drive, level))| {
+                        let shaped = band.clip_type.shape(band_sample * drive);
+                        dc_block.tick(shaped) * level
+                    })
+                    .sum();
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        MultibandDistortionView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        200.0
+    }
+}
+
+fn band_name(band: usize, num_bands: usize) -> &'static str {
+    match (band, num_bands) {
+        (0, _) => "LOW",
+        (1, 2) => "HIGH",
+        (1, _) => "MID",
+        (_, 2) => "-",
+        _ => "HIGH",
+    }
+}
+
+enum MultibandDistortionEvent {
+    Select(usize),
+    Drag(usize, f32),
+    CycleClipType,
+    ToggleBands,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct MultibandDistortionView {
+    #[msg]
+    drive: f32,
+    #[msg]
+    level: f32,
+
+    selected: usize,
+    clip_type: ClipType,
+    num_bands: usize,
+    revision: u64,
+
+    response: Vec<f32>,
+    nodes: Vec<GraphNode>,
+
+    #[lens(ignore)]
+    handle: EffectHandle<MultibandDistortion>,
+}
+
+impl MultibandDistortionView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<MultibandDistortion>) -> Handle<Self> {
+        let band = *handle.band(0);
+        let mut view = Self {
+            drive: band.drive_db(),
+            level: band.level_db(),
+            selected: 0,
+            clip_type: band.clip_type(),
+            num_bands: handle.num_bands(),
+            revision: 0,
+            response: vec![],
+            nodes: vec![],
+            handle: handle.clone(),
+        };
+        view.update_graph();
+
+        view.build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                ResponseGraph::new(cx, Self::response, Self::nodes, DB_RANGE)
+                    .on_drag(|ex, index, frequency, _| {
+                        ex.emit(MultibandDistortionEvent::Drag(index, frequency))
+                    })
+                    .class("multiband-graph");
+                VStack::new(cx, |cx| {
+                    HStack::new(cx, |cx| {
+                        for index in 0..MAX_BANDS {
+                            Button::new(
+                                cx,
+                                move |ex| ex.emit(MultibandDistortionEvent::Select(index)),
+                                move |cx| {
+                                    Label::new(
+                                        cx,
+                                        Self::num_bands
+                                            .map(move |num_bands| band_name(index, *num_bands)),
+                                    )
+                                },
+                            )
+                            .class("band-btn")
+                            .toggle_class(
+                                "band-selected",
+                                Self::selected.map(move |selected| *selected == index),
+                            )
+                            .disabled(Self::num_bands.map(move |num_bands| index >= *num_bands));
+                        }
+                        Button::new(
+                            cx,
+                            |ex| ex.emit(MultibandDistortionEvent::ToggleBands),
+                            |cx| {
+                                Label::new(
+                                    cx,
+                                    Self::num_bands.map(|num_bands| format!("{num_bands} BANDS")),
+                                )
+                            },
+                        )
+                        .class("band-btn");
+                    })
+                    .class("band-selector");
+                    Binding::new(cx, Self::revision, |cx, _| {
+                        HStack::new(cx, |cx| {
+                            Button::new(
+                                cx,
+                                |ex| ex.emit(MultibandDistortionEvent::CycleClipType),
+                                |cx| Label::new(cx, Self::clip_type.map(|clip| clip.name())),
+                            )
+                            .class("band-option");
+                            NamedKnob::new(cx, "Drive", Self::drive, false, 0.0..MAX_DRIVE_DB)
+                                .on_changing(|ex, val| ex.emit(Message::Drive(val)));
+                            NamedKnob::new(
+                                cx,
+                                "Level",
+                                Self::level,
+                                true,
+                                -LEVEL_RANGE_DB..LEVEL_RANGE_DB,
+                            )
+                            .on_changing(|ex, val| ex.emit(Message::Level(val)));
+                        })
+                        .class("band-controls");
+                    });
+                })
+                .class("multiband-controls");
+                Label::new(cx, "MULTIBAND").class("effect-title");
+            });
+        })
+    }
+
+    fn sync_band(&mut self) {
+        let band = *self.handle.band(self.selected);
+        self.drive = band.drive_db();
+        self.level = band.level_db();
+        self.clip_type = band.clip_type();
+        self.num_bands = self.handle.num_bands();
+    }
+
+    fn update_graph(&mut self) {
+        self.response = self
+            .handle
+            .response(&graph_frequencies(RESPONSE_RESOLUTION));
+        let num_bands = self.handle.num_bands();
+        self.nodes = (0..MAX_BANDS - 1)
+            .map(|index| GraphNode {
+                frequency: self.handle.frequency(index),
+                gain_db: 0.0,
+                enabled: index < num_bands - 1,
+                selected: false,
+            })
+            .collect();
+    }
+}
+
+impl View for MultibandDistortionView {
+    fn element(&self) -> Option<&'static str> {
+        Some("multiband-distortion")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Drive(val) => {
+                self.drive = *val;
+                self.handle.band_mut(self.selected).set_drive_db(*val);
+            }
+            Message::Level(val) => {
+                self.level = *val;
+                self.handle.band_mut(self.selected).set_level_db(*val);
+                self.update_graph();
+            }
+        });
+
+        event.map(|event, _| match event {
+            MultibandDistortionEvent::Select(band) => {
+                self.selected = *band;
+                self.sync_band();
+                self.revision += 1;
+            }
+            MultibandDistortionEvent::Drag(index, frequency) => {
+                self.handle.set_frequency(*index, *frequency);
+                self.update_graph();
+            }
+            MultibandDistortionEvent::CycleClipType => {
+                let current = BAND_CLIP_TYPES
+                    .iter()
+                    .position(|clip_type| *clip_type == self.clip_type)
+                    .unwrap_or(0);
+                let clip_type = BAND_CLIP_TYPES[(current + 1) % BAND_CLIP_TYPES.len()];
+                self.handle.band_mut(self.selected).set_clip_type(clip_type);
+                self.sync_band();
+                self.revision += 1;
+            }
+            MultibandDistortionEvent::ToggleBands => {
+                let num_bands = if self.num_bands > 2 { 2 } else { MAX_BANDS };
+                self.handle.set_num_bands(num_bands);
+                self.selected = self.selected.min(num_bands - 1);
+                self.sync_band();
+                self.update_graph();
+                self.revision += 1;
+            }
+        });
+    }
+}
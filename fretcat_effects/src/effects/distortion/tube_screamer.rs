@@ -0,0 +1,266 @@
+use crate::prelude::*;
+
+// Clipping stage, the op amp's ground leg sets where the mid hump starts
+const R4: f32 = 4.7e3;
+const C3: f32 = 47e-9;
+const R6: f32 = 51e3;
+const C4: f32 = 51e-12;
+const DRIVE_POT: f32 = 500e3;
+
+// Passive low pass into the active tone control
+const R7: f64 = 1e3;
+const C5: f64 = 0.22e-6;
+const R8: f64 = 220.0;
+const C6: f64 = 0.22e-6;
+const R9: f64 = 1e3;
+const TONE_POT: f64 = 20e3;
+// Keeps the pot from reaching zero ohms at either end
+const MIN_POT: f64 = 1.0;
+
+/// Audio taper pot, 10% of its resistance at half rotation.
+#[inline]
+fn log_taper(position: f32) -> f32 {
+    (81.0_f32.powf(position.clamp(0.0, 1.0)) - 1.0) / 80.0
+}
+
+/// Clipping stage and tone circuit of one channel.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct ScreamerCircuit {
+    // The op amp holds its inverting input at the input voltage, so the current through the
+    // ground leg is all that flows through the feedback loop and its diodes
+    ground_leg: Series<ResistiveVoltageSource, Capacitor>,
+    ground: IdealVoltageSource,
+    feedback: Parallel<ResistiveCurrentSource, Capacitor>,
+    diodes: DiodePair,
+    tone: Biquad,
+}
+
+impl ScreamerCircuit {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            ground_leg: Series::new(
+                ResistiveVoltageSource::new(R4),
+                Capacitor::new(C3, sample_rate),
+            ),
+            ground: IdealVoltageSource::new(),
+            feedback: Parallel::new(
+                ResistiveCurrentSource::new(R6),
+                Capacitor::new(C4, sample_rate),
+            ),
+            diodes: DiodePair::silicon(DiodeSolver::WrightOmega),
+            tone: Biquad::new(),
+        }
+    }
+
+    fn set_drive(&mut self, drive: f32) {
+        self.feedback
+            .left
+            .set_resistance(R6 + DRIVE_POT * log_taper(drive));
+        self.feedback.propagate_impedance();
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.ground_leg.set_sample_rate(sample_rate);
+        self.feedback.set_sample_rate(sample_rate);
+    }
+
+    #[inline]
+    fn tick(&mut self, input: f32) -> f32 {
+        self.ground_leg.left.set_voltage(input);
+        self.ground.process(&mut self.ground_leg);
+
+        self.feedback
+            .left
+            .set_current(-self.ground_leg.right.current());
+        self.diodes.process(&mut self.feedback);
+
+        self.tone.tick(input + self.diodes.voltage())
+    }
+}
+
+/// Tone control of the original circuit, discretized with the bilinear transform.
+/// `tone` moves the wiper from the dark end at 0 to the bright end at 1.
+fn tone_coeffs(tone: f32, sample_rate: f32) -> BiquadCoefs {
+    let ra = (tone as f64 * TONE_POT).clamp(MIN_POT, TONE_POT - MIN_POT);
+    let rb = TONE_POT - ra;
+    let g7 = 1.0 / R7;
+
+    // Node analysis with an ideal op amp, multiplied through by Ra * Rb
+    let n0 = g7 * TONE_POT;
+    let n1 = g7 * (TONE_POT * C6 * R8 + ra * rb * C6 + R9 * ra * C6);
+    let d0 = g7 * TONE_POT;
+    let d1 = g7 * (TONE_POT * C6 * R8 + ra * rb * C6) + C5 * TONE_POT + rb * C6;
+    let d2 = C5 * (TONE_POT * C6 * R8 + ra * rb * C6);
+
+    let c = 2.0 * sample_rate as f64;
+    let a0 = d0 + d1 * c + d2 * c * c;
+    BiquadCoefs::arbitrary(
+        ((2.0 * d0 - 2.0 * d2 * c * c) / a0) as f32,
+        ((d0 - d1 * c + d2 * c * c) / a0) as f32,
+        ((n0 + n1 * c) / a0) as f32,
+        (2.0 * n0 / a0) as f32,
+        ((n0 - n1 * c) / a0) as f32,
+    )
+}
+
+/// Overdrive modeled on the Tube Screamer, with its diodes solved as part of the circuit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TubeScreamer {
+    drive: f32,
+    tone: f32,
+    level: f32,
+
+    sample_rate: f32,
+    circuits: [ScreamerCircuit; NUM_CHANNELS],
+}
+
+impl Default for TubeScreamer {
+    fn default() -> Self {
+        let mut tube_screamer = Self {
+            drive: 0.5,
+            tone: 0.5,
+            level: 0.5,
+            sample_rate: 44100.0,
+            circuits: [ScreamerCircuit::new(44100.0); NUM_CHANNELS],
+        };
+        tube_screamer.set_drive(tube_screamer.drive);
+        tube_screamer.set_tone(tube_screamer.tone);
+        tube_screamer
+    }
+}
+
+impl PartialEq for TubeScreamer {
+    fn eq(&self, other: &Self) -> bool {
+        self.drive == other.drive && self.tone == other.tone && self.level == other.level
+    }
+}
+
+impl TubeScreamer {
+    pub fn drive(&self) -> f32 {
+        self.drive
+    }
+
+    pub fn tone(&self) -> f32 {
+        self.tone
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Knob position between 0 and 1.
+    pub fn set_drive(&mut self, drive: f32) {
+        self.drive = drive.clamp(0.0, 1.0);
+        for circuit in self.circuits.iter_mut() {
+            circuit.set_drive(self.drive);
+        }
+    }
+
+    /// Knob position between 0 and 1.
+    pub fn set_tone(&mut self, tone: f32) {
+        self.tone = tone.clamp(0.0, 1.0);
+        let coeffs = tone_coeffs(self.tone, self.sample_rate);
+        for circuit in self.circuits.iter_mut() {
+            circuit.tone.set_coefs(coeffs);
+        }
+    }
+
+    /// Knob position between 0 and 1.
+    pub fn set_level(&mut self, level: f32) {
+        self.level = level.clamp(0.0, 1.0);
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for circuit in self.circuits.iter_mut() {
+            circuit.set_sample_rate(sample_rate);
+        }
+        self.set_drive(self.drive);
+        self.set_tone(self.tone);
+    }
+}
+
+impl AudioEffect for TubeScreamer {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        // The level pot is a plain divider after the tone stage
+        let level = log_taper(self.level);
+
+        let Self { circuits, .. } = self;
+
+        input_buffer.process_individual(|left, right| {
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                *sample = circuits[channel].tick(*sample) * level;
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        TubeScreamerView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        100.0
+    }
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct TubeScreamerView {
+    #[msg]
+    drive: f32,
+    #[msg]
+    tone: f32,
+    #[msg]
+    level: f32,
+
+    #[lens(ignore)]
+    handle: EffectHandle<TubeScreamer>,
+}
+
+impl TubeScreamerView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<TubeScreamer>) -> Handle<Self> {
+        Self {
+            drive: handle.drive() * 10.0,
+            tone: handle.tone() * 10.0,
+            level: handle.level() * 10.0,
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                NamedKnob::new(cx, "Drive", Self::drive, false, 0.0..10.0)
+                    .on_changing(|ex, val| ex.emit(Message::Drive(val)));
+                NamedKnob::new(cx, "Tone", Self::tone, false, 0.0..10.0)
+                    .on_changing(|ex, val| ex.emit(Message::Tone(val)));
+                NamedKnob::new(cx, "Level", Self::level, false, 0.0..10.0)
+                    .on_changing(|ex, val| ex.emit(Message::Level(val)));
+                Label::new(cx, "SCREAMER").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for TubeScreamerView {
+    fn element(&self) -> Option<&'static str> {
+        Some("tube-screamer")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Drive(val) => {
+                self.drive = *val;
+                self.handle.set_drive(*val / 10.0);
+            }
+            Message::Tone(val) => {
+                self.tone = *val;
+                self.handle.set_tone(*val / 10.0);
+            }
+            Message::Level(val) => {
+                self.level = *val;
+                self.handle.set_level(*val / 10.0);
+            }
+        });
+    }
+}
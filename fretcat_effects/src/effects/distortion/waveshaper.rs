@@ -0,0 +1,502 @@
+use crate::prelude::*;
+
+const CURVE_POINTS: usize = 7;
+const TABLE_SIZE: usize = 1024;
+const PREVIEW_RESOLUTION: usize = 128;
+// Keeps dragged points from landing on top of their neighbours
+const MIN_POINT_GAP: f32 = 0.02;
+// The asymmetric shapes leave an offset behind
+const DC_CUTOFF_HZ: f32 = 10.0;
+// Level the negative half of the diode shape tops out at
+const DIODE_ASYMMETRY: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum ClipType {
+    Hard,
+    Soft,
+    Tanh,
+    Diode,
+    Foldback,
+    Rectifier,
+    Custom,
+}
+
+impl ClipType {
+    pub const ALL: [ClipType; 7] = [
+        ClipType::Hard,
+        ClipType::Soft,
+        ClipType::Tanh,
+        ClipType::Diode,
+        ClipType::Foldback,
+        ClipType::Rectifier,
+        ClipType::Custom,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClipType::Hard => "HARD",
+            ClipType::Soft => "SOFT",
+            ClipType::Tanh => "TANH",
+            ClipType::Diode => "DIODE",
+            ClipType::Foldback => "FOLD",
+            ClipType::Rectifier => "RECT",
+            ClipType::Custom => "CUSTOM",
+        }
+    }
+
+    /// Fixed curves only, `Custom` needs the drawn table and falls back to a hard clip here.
+    pub fn shape(&self, x: f32) -> f32 {
+        match self {
+            ClipType::Hard | ClipType::Custom => x.clamp(-1.0, 1.0),
+            ClipType::Soft => {
+                let x = x.clamp(-1.5, 1.5);
+                x - x * x * x * (4.0 / 27.0)
+            }
+            ClipType::Tanh => x.tanh(),
+            ClipType::Diode => {
+                if x >= 0.0 {
+                    1.0 - (-x).exp()
+                } else {
+                    -DIODE_ASYMMETRY * (1.0 - (x / DIODE_ASYMMETRY).exp())
+                }
+            }
+            ClipType::Foldback => {
+                // Triangle wave with a period of 4, which folds everything back into -1..1
+                let phase = (x + 1.0).rem_euclid(4.0);
+                if phase < 2.0 {
+                    phase - 1.0
+                } else {
+                    3.0 - phase
+                }
+            }
+            ClipType::Rectifier => x.abs().min(1.0),
+        }
+    }
+}
+
+/// Control point of the user curve, both coordinates between -1 and 1.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct CurvePoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+fn default_points() -> Vec<CurvePoint> {
+    (0..CURVE_POINTS)
+        .map(|index| {
+            let x = -1.0 + 2.0 * index as f32 / (CURVE_POINTS - 1) as f32;
+            CurvePoint { x, y: x }
+        })
+        .collect()
+}
+
+/// Monotone cubic through `points`, so the curve never swings past the points the user placed.
+fn fill_table(points: &[CurvePoint], table: &mut [f32]) {
+    let count = points.len();
+    let mut secants = [0.0; CURVE_POINTS];
+    let mut tangents = [0.0; CURVE_POINTS];
+
+    for (secant, pair) in secants.iter_mut().zip(points.windows(2)) {
+        *secant = (pair[1].y - pair[0].y) / (pair[1].x - pair[0].x).max(f32::EPSILON);
+    }
+    tangents[0] = secants[0];
+    tangents[count - 1] = secants[count - 2];
+    for (tangent, pair) in tangents[1..count - 1].iter_mut().zip(secants.windows(2)) {
+        // Flat at peaks and valleys, harmonic mean of the slopes elsewhere
+        *tangent = if pair[0] * pair[1] <= 0.0 {
+            0.0
+        } else {
+            2.0 * pair[0] * pair[1] / (pair[0] + pair[1])
+        };
+    }
+
+    let last = (table.len() - 1) as f32;
+    let mut segment = 0;
+    for (index, value) in table.iter_mut().enumerate() {
+        let x = -1.0 + 2.0 * index as f32 / last;
+        while segment < count - 2 && x > points[segment + 1].x {
+            segment += 1;
+        }
+
+        let (start, end) = (points[segment], points[segment + 1]);
+        let width = (end.x - start.x).max(f32::EPSILON);
+        let t = ((x - start.x) / width).clamp(0.0, 1.0);
+        let (t2, t3) = (t * t, t * t * t);
+        *value = (2.0 * t3 - 3.0 * t2 + 1.0) * start.y
+            + (t3 - 2.0 * t2 + t) * width * tangents[segment]
+            + (-2.0 * t3 + 3.0 * t2) * end.y
+            + (t3 - t2) * width * tangents[segment + 1];
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct WaveshaperSettings {
+    clip_type: ClipType,
+    drive_db: f32,
+    output_db: f32,
+    mix: f32,
+    points: Vec<CurvePoint>,
+}
+
+/// Distortion built around a static transfer curve, either a classic one or drawn by the user.
+/// Presets store the control points, the lookup table is rebuilt from them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "WaveshaperSettings", into = "WaveshaperSettings")]
+pub struct Waveshaper {
+    clip_type: ClipType,
+    drive_db: f32,
+    output_db: f32,
+    mix: f32,
+    points: Vec<CurvePoint>,
+
+    sample_rate: f32,
+    // The editor fills a fresh table for every change and hands it over, the audio thread
+    // never sees one being written
+    curve_table: Vec<f32>,
+    table: Vec<f32>,
+    incoming: Handoff<Vec<f32>>,
+    dc_blocks: [DCBlock; NUM_CHANNELS],
+}
+
+impl Default for Waveshaper {
+    fn default() -> Self {
+        WaveshaperSettings {
+            clip_type: ClipType::Tanh,
+            drive_db: 12.0,
+            output_db: -6.0,
+            mix: 1.0,
+            points: default_points(),
+        }
+        .into()
+    }
+}
+
+impl From<WaveshaperSettings> for Waveshaper {
+    fn from(settings: WaveshaperSettings) -> Self {
+        let mut points = settings.points;
+        if points.len() != CURVE_POINTS {
+            points = default_points();
+        }
+        points.sort_by(|a, b| a.x.total_cmp(&b.x));
+        points[0].x = -1.0;
+        points[CURVE_POINTS - 1].x = 1.0;
+        for point in points.iter_mut() {
+            point.x = point.x.clamp(-1.0, 1.0);
+            point.y = point.y.clamp(-1.0, 1.0);
+        }
+
+        let mut table = vec![0.0; TABLE_SIZE];
+        fill_table(&points, &mut table);
+
+        Self {
+            clip_type: settings.clip_type,
+            drive_db: settings.drive_db,
+            output_db: settings.output_db,
+            mix: settings.mix,
+            points,
+            sample_rate: 44100.0,
+            curve_table: table.clone(),
+            table,
+            incoming: Handoff::new(),
+            dc_blocks: [DCBlock::new(DC_CUTOFF_HZ); NUM_CHANNELS],
+        }
+    }
+}
+
+impl From<Waveshaper> for WaveshaperSettings {
+    fn from(waveshaper: Waveshaper) -> Self {
+        Self {
+            clip_type: waveshaper.clip_type,
+            drive_db: waveshaper.drive_db,
+            output_db: waveshaper.output_db,
+            mix: waveshaper.mix,
+            points: waveshaper.points,
+        }
+    }
+}
+
+impl PartialEq for Waveshaper {
+    fn eq(&self, other: &Self) -> bool {
+        self.clip_type == other.clip_type
+            && self.drive_db == other.drive_db
+            && self.output_db == other.output_db
+            && self.mix == other.mix
+            && self.points == other.points
+    }
+}
+
+impl Waveshaper {
+    pub fn clip_type(&self) -> ClipType {
+        self.clip_type
+    }
+
+    pub fn drive_db(&self) -> f32 {
+        self.drive_db
+    }
+
+    pub fn output_db(&self) -> f32 {
+        self.output_db
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    pub fn points(&self) -> &[CurvePoint] {
+        &self.points
+    }
+
+    pub fn set_clip_type(&mut self, clip_type: ClipType) {
+        self.clip_type = clip_type;
+    }
+
+    pub fn set_drive_db(&mut self, drive_db: f32) {
+        self.drive_db = drive_db;
+    }
+
+    pub fn set_output_db(&mut self, output_db: f32) {
+        self.output_db = output_db;
+    }
+
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    /// Moves a control point of the user curve and sends the rebuilt lookup table to the
+    /// audio thread. Meant for the editor thread, the points at both ends only move vertically.
+    pub fn set_point(&mut self, index: usize, x: f32, y: f32) {
+        let x = if index == 0 {
+            -1.0
+        } else if index == CURVE_POINTS - 1 {
+            1.0
+        } else {
+            let min = self.points[index - 1].x + MIN_POINT_GAP;
+            let max = self.points[index + 1].x - MIN_POINT_GAP;
+            x.max(min).min(max)
+        };
+        self.points[index] = CurvePoint {
+            x,
+            y: y.clamp(-1.0, 1.0),
+        };
+        self.publish_table();
+    }
+
+    pub fn reset_points(&mut self) {
+        self.points.copy_from_slice(&default_points());
+        self.publish_table();
+    }
+
+    fn publish_table(&mut self) {
+        fill_table(&self.points, &mut self.curve_table);
+        self.incoming.send(self.curve_table.clone());
+    }
+
+    #[inline]
+    fn shape(clip_type: ClipType, table: &[f32], x: f32) -> f32 {
+        match clip_type {
+            ClipType::Custom => {
+                let position = (x.clamp(-1.0, 1.0) + 1.0) * 0.5 * (TABLE_SIZE - 1) as f32;
+                let index = (position as usize).min(TABLE_SIZE - 2);
+                let fraction = position - index as f32;
+                table[index] + (table[index + 1] - table[index]) * fraction
+            }
+            other => other.shape(x),
+        }
+    }
+
+    /// Current transfer curve sampled evenly between -1 and 1, before drive.
+    pub fn transfer_curve(&self, resolution: usize) -> Vec<f32> {
+        (0..resolution)
+            .map(|index| {
+                let x = -1.0 + 2.0 * index as f32 / (resolution - 1).max(1) as f32;
+                Self::shape(self.clip_type, &self.curve_table, x)
+            })
+            .collect()
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for dc_block in self.dc_blocks.iter_mut() {
+            dc_block.set_sample_rate(sample_rate);
+        }
+    }
+}
+
+impl AudioEffect for Waveshaper {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+        self.incoming.receive(&mut self.table);
+
+        let drive = db_to_gain(self.drive_db);
+        let output = db_to_gain(self.output_db);
+
+        let Self {
+            clip_type,
+            mix,
+            table,
+            dc_blocks,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let shaped = Self::shape(*clip_type, table, *sample * drive);
+                let wet = dc_blocks[channel].tick(shaped) * output;
+                *sample += *mix * (wet - *sample);
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        WaveshaperView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        200.0
+    }
+
+    fn prepare(&mut self, _sample_rate: f32) {
+        // Nothing is processing this one yet, a copy may have been made before its table arrived
+        self.incoming.discard();
+        self.table.copy_from_slice(&self.curve_table);
+    }
+}
+
+enum WaveshaperEvent {
+    SetClipType(ClipType),
+    Drag(usize, f32, f32),
+    Reset,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct WaveshaperView {
+    #[msg]
+    drive: f32,
+    #[msg]
+    output: f32,
+    #[msg]
+    mix: f32,
+
+    clip_type: ClipType,
+    curve: Vec<f32>,
+    nodes: Vec<CurveNode>,
+
+    #[lens(ignore)]
+    handle: EffectHandle<Waveshaper>,
+}
+
+impl WaveshaperView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<Waveshaper>) -> Handle<Self> {
+        let mut view = Self {
+            drive: handle.drive_db(),
+            output: handle.output_db(),
+            mix: handle.mix() * 100.0,
+            clip_type: handle.clip_type(),
+            curve: vec![],
+            nodes: vec![],
+            handle: handle.clone(),
+        };
+        view.update_graph();
+
+        view.build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                CurveGraph::new(cx, Self::curve, Self::nodes)
+                    .on_drag(|ex, index, x, y| ex.emit(WaveshaperEvent::Drag(index, x, y)))
+                    .class("waveshaper-graph");
+                VStack::new(cx, |cx| {
+                    HStack::new(cx, |cx| {
+                        for clip_type in ClipType::ALL {
+                            Button::new(
+                                cx,
+                                move |ex| ex.emit(WaveshaperEvent::SetClipType(clip_type)),
+                                |cx| Label::new(cx, clip_type.name()),
+                            )
+                            .class("waveshaper-option")
+                            .toggle_class(
+                                "option-enabled",
+                                Self::clip_type.map(move |selected| *selected == clip_type),
+                            );
+                        }
+                    })
+                    .class("waveshaper-options");
+                    HStack::new(cx, |cx| {
+                        NamedKnob::new(cx, "Drive", Self::drive, false, 0.0..36.0)
+                            .on_changing(|ex, val| ex.emit(Message::Drive(val)));
+                        NamedKnob::new(cx, "Output", Self::output, true, -24.0..12.0)
+                            .on_changing(|ex, val| ex.emit(Message::Output(val)));
+                        NamedKnob::new(cx, "Mix", Self::mix, false, 0.0..100.0)
+                            .on_changing(|ex, val| ex.emit(Message::Mix(val)));
+                        Button::new(
+                            cx,
+                            |ex| ex.emit(WaveshaperEvent::Reset),
+                            |cx| Label::new(cx, "RESET"),
+                        )
+                        .class("waveshaper-option")
+                        .disabled(Self::clip_type.map(|clip_type| *clip_type != ClipType::Custom));
+                    })
+                    .class("waveshaper-knobs");
+                })
+                .class("waveshaper-controls");
+                Label::new(cx, "WAVESHAPER").class("effect-title");
+            });
+        })
+    }
+
+    fn update_graph(&mut self) {
+        self.curve = self.handle.transfer_curve(PREVIEW_RESOLUTION);
+        // Points only show up while the drawn curve is the one playing
+        self.nodes = if self.clip_type == ClipType::Custom {
+            self.handle
+                .points()
+                .iter()
+                .map(|point| CurveNode {
+                    x: point.x,
+                    y: point.y,
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+    }
+}
+
+impl View for WaveshaperView {
+    fn element(&self) -> Option<&'static str> {
+        Some("waveshaper")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Drive(val) => {
+                self.drive = *val;
+                self.handle.set_drive_db(*val);
+            }
+            Message::Output(val) => {
+                self.output = *val;
+                self.handle.set_output_db(*val);
+            }
+            Message::Mix(val) => {
+                self.mix = *val;
+                self.handle.set_mix(*val / 100.0);
+            }
+        });
+
+        event.map(|event, _| match event {
+            WaveshaperEvent::SetClipType(clip_type) => {
+                self.clip_type = *clip_type;
+                self.handle.set_clip_type(*clip_type);
+                self.update_graph();
+            }
+            WaveshaperEvent::Drag(index, x, y) => {
+                self.handle.set_point(*index, *x, *y);
+                self.update_graph();
+            }
+            WaveshaperEvent::Reset => {
+                self.handle.reset_points();
+                self.update_graph();
+            }
+        });
+    }
+}
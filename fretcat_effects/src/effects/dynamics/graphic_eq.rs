@@ -0,0 +1,335 @@
+use crate::prelude::*;
+
+const MAX_BANDS: usize = 10;
+
+// Octave spaced bands, a one octave bandwidth lets neighbours sum without ripple
+const BAND_Q: f32 = std::f32::consts::SQRT_2;
+const BYPASS_FADE_MS: f32 = 10.0;
+
+const SEVEN_BAND_FREQUENCIES: [f32; 7] = [100.0, 200.0, 400.0, 800.0, 1600.0, 3200.0, 6400.0];
+const SEVEN_BAND_LABELS: [&str; 7] = ["100", "200", "400", "800", "1.6k", "3.2k", "6.4k"];
+const TEN_BAND_FREQUENCIES: [f32; 10] = [
+    31.25, 62.5, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+const TEN_BAND_LABELS: [&str; 10] = [
+    "31", "62", "125", "250", "500", "1k", "2k", "4k", "8k", "16k",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum GraphicEqLayout {
+    SevenBand,
+    TenBand,
+}
+
+impl GraphicEqLayout {
+    pub fn frequencies(&self) -> &'static [f32] {
+        match self {
+            GraphicEqLayout::SevenBand => &SEVEN_BAND_FREQUENCIES,
+            GraphicEqLayout::TenBand => &TEN_BAND_FREQUENCIES,
+        }
+    }
+
+    pub fn labels(&self) -> &'static [&'static str] {
+        match self {
+            GraphicEqLayout::SevenBand => &SEVEN_BAND_LABELS,
+            GraphicEqLayout::TenBand => &TEN_BAND_LABELS,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frequencies().len()
+    }
+
+    /// Boost and cut range of every band slider, in dB.
+    pub fn db_range(&self) -> f32 {
+        match self {
+            GraphicEqLayout::SevenBand => 15.0,
+            GraphicEqLayout::TenBand => 12.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphicEq {
+    layout: GraphicEqLayout,
+    gains_db: [f32; MAX_BANDS],
+    level_db: f32,
+    bypassed: bool,
+
+    level: f32,
+    #[serde(skip)]
+    wet: f32,
+    fade_coeff: f32,
+    filters: [[SvfFilter; NUM_CHANNELS]; MAX_BANDS],
+}
+
+impl Default for GraphicEq {
+    fn default() -> Self {
+        let mut eq = Self {
+            layout: GraphicEqLayout::SevenBand,
+            gains_db: [0.0; MAX_BANDS],
+            level_db: 0.0,
+            bypassed: false,
+            level: 1.0,
+            wet: 1.0,
+            fade_coeff: 0.0,
+            filters: [[SvfFilter::new(FilterMode::ConstantQBell, 44100.0, 1000.0, BAND_Q);
+                NUM_CHANNELS]; MAX_BANDS],
+        };
+        eq.configure();
+        eq.set_sample_rate(44100.0);
+        eq
+    }
+}
+
+impl PartialEq for GraphicEq {
+    fn eq(&self, other: &Self) -> bool {
+        self.layout == other.layout
+            && self.gains_db == other.gains_db
+            && self.level_db == other.level_db
+            && self.bypassed == other.bypassed
+    }
+}
+
+impl GraphicEq {
+    pub fn layout(&self) -> GraphicEqLayout {
+        self.layout
+    }
+
+    /// Slider position of `band`, in dB.
+    pub fn band_gain(&self, band: usize) -> f32 {
+        self.gains_db[band]
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level_db
+    }
+
+    pub fn bypassed(&self) -> bool {
+        self.bypassed
+    }
+
+    /// Switching layouts keeps the slider positions, they are just read against the new bands.
+    pub fn set_layout(&mut self, layout: GraphicEqLayout) {
+        self.layout = layout;
+        self.configure();
+    }
+
+    pub fn set_band_gain(&mut self, band: usize, gain_db: f32) {
+        let range = self.layout.db_range();
+        self.gains_db[band] = gain_db.clamp(-range, range);
+        let gain = db_to_gain(self.gains_db[band]);
+        self.filters[band]
+            .iter_mut()
+            .for_each(|filter| filter.set_gain(gain));
+    }
+
+    pub fn set_level(&mut self, level_db: f32) {
+        self.level_db = level_db;
+        self.level = db_to_gain(level_db);
+    }
+
+    pub fn set_bypassed(&mut self, bypassed: bool) {
+        self.bypassed = bypassed;
+    }
+
+    fn configure(&mut self) {
+        let range = self.layout.db_range();
+        for (band, frequency) in self.layout.frequencies().iter().enumerate() {
+            self.gains_db[band] = self.gains_db[band].clamp(-range, range);
+            let gain = db_to_gain(self.gains_db[band]);
+            self.filters[band].iter_mut().for_each(|filter| {
+                filter.set_gain(gain);
+                filter.set_cutoff(*frequency);
+            });
+        }
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.fade_coeff = one_pole_coeff(BYPASS_FADE_MS, sample_rate);
+        self.filters
+            .iter_mut()
+            .flatten()
+            .for_each(|filter| filter.set_sample_rate(sample_rate));
+    }
+}
+
+impl AudioEffect for GraphicEq {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.filters[0][0].sample_rate() != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        let target = if self.bypassed { 0.0 } else { 1.0 };
+        if self.bypassed && self.wet < 1e-4 {
+            self.wet = 0.0;
+            return;
+        }
+
+        let Self {
+            layout,
+            level,
+            wet,
+            fade_coeff,
+            filters,
+            ..
+        } = self;
+        let filters = &mut filters[..layout.len()];
+
+        // Crossfade instead of cutting straight to the dry signal so the footswitch doesn't click
+        input_buffer.process_individual(|left, right| {
+            let (mut wet_left, mut wet_right) = (*left, *right);
+            for [left_filter, right_filter] in filters.iter_mut() {
+                wet_left = left_filter.tick(wet_left);
+                wet_right = right_filter.tick(wet_right);
+            }
+
+            *wet = target + *fade_coeff * (*wet - target);
+            *left += *wet * (wet_left * *level - *left);
+            *right += *wet * (wet_right * *level - *right);
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        GraphicEqView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        200.0
+    }
+}
+
+enum GraphicEqEvent {
+    Band(usize, f32),
+    Layout(GraphicEqLayout),
+    Bypass,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct GraphicEqView {
+    #[msg]
+    level: f32,
+
+    gains: Vec<f32>,
+    layout: GraphicEqLayout,
+    bypassed: bool,
+
+    #[lens(ignore)]
+    handle: EffectHandle<GraphicEq>,
+}
+
+impl GraphicEqView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<GraphicEq>) -> Handle<Self> {
+        Self {
+            level: handle.level(),
+            gains: handle.gains_db.to_vec(),
+            layout: handle.layout(),
+            bypassed: handle.bypassed(),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                VStack::new(cx, |cx| {
+                    for (layout, name) in [
+                        (GraphicEqLayout::SevenBand, "7 BAND"),
+                        (GraphicEqLayout::TenBand, "10 BAND"),
+                    ] {
+                        Button::new(
+                            cx,
+                            move |ex| ex.emit(GraphicEqEvent::Layout(layout)),
+                            move |cx| Label::new(cx, name),
+                        )
+                        .class("geq-option")
+                        .toggle_class("geq-selected", Self::layout.map(move |l| *l == layout));
+                    }
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(GraphicEqEvent::Bypass),
+                        |cx| {
+                            Label::new(
+                                cx,
+                                Self::bypassed.map(|bypassed| if *bypassed { "BYPASS" } else { "ON" }),
+                            )
+                        },
+                    )
+                    .class("geq-option")
+                    .toggle_class("geq-selected", Self::bypassed.map(|bypassed| !*bypassed));
+                })
+                .class("geq-options");
+
+                Binding::new(cx, Self::layout, |cx, layout| {
+                    let layout = layout.get(cx);
+                    let range = layout.db_range();
+                    HStack::new(cx, |cx| {
+                        for (band, name) in layout.labels().iter().enumerate() {
+                            band_slider(
+                                cx,
+                                Self::gains.map(move |gains| gains[band]),
+                                range,
+                                *name,
+                                move |ex, val| ex.emit(GraphicEqEvent::Band(band, val)),
+                            );
+                        }
+                    })
+                    .class("geq-bands")
+                    .disabled(Self::bypassed);
+                });
+
+                band_slider(cx, Self::level, 15.0, "LEVEL", |ex, val| {
+                    ex.emit(Message::Level(val))
+                })
+                .class("geq-level")
+                .disabled(Self::bypassed);
+            });
+            Label::new(cx, "GRAPHIC EQ").class("effect-title");
+        })
+    }
+}
+
+fn band_slider<L: Lens<Target = f32>>(
+    cx: &mut Context,
+    lens: L,
+    range: f32,
+    name: &'static str,
+    on_changing: impl Fn(&mut EventContext, f32) + 'static,
+) -> Handle<VStack> {
+    VStack::new(cx, move |cx| {
+        Label::new(cx, lens.clone().map(|gain| format!("{:+.1}", gain))).class("geq-value");
+        Slider::new(cx, lens)
+            .range(-range..range)
+            .on_changing(on_changing);
+        Label::new(cx, name).class("geq-band-name");
+    })
+    .class("geq-band")
+}
+
+impl View for GraphicEqView {
+    fn element(&self) -> Option<&'static str> {
+        Some("graphic-eq")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Level(val) => {
+                self.level = *val;
+                self.handle.set_level(*val);
+            }
+        });
+
+        event.map(|event, _| match event {
+            GraphicEqEvent::Band(band, val) => {
+                self.handle.set_band_gain(*band, *val);
+                self.gains[*band] = self.handle.band_gain(*band);
+            }
+            GraphicEqEvent::Layout(layout) => {
+                self.handle.set_layout(*layout);
+                self.layout = *layout;
+                self.gains = self.handle.gains_db.to_vec();
+            }
+            GraphicEqEvent::Bypass => {
+                self.bypassed = !self.bypassed;
+                self.handle.set_bypassed(self.bypassed);
+            }
+        });
+    }
+}
@@ -10,6 +10,12 @@ pub use high_pass::HighPass;
 mod low_pass;
 pub use low_pass::LowPass;
 
+mod graphic_eq;
+pub use graphic_eq::{GraphicEq, GraphicEqLayout};
+
+mod parametric_eq;
+pub use parametric_eq::{EqBand, ParametricEq};
+
 mod noise_gate;
 pub use noise_gate::NoiseGate;
 
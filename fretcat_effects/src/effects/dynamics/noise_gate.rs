@@ -1,76 +1,207 @@
-use serde::{Deserialize, Serialize};
+use crate::prelude::*;
 
-use crate::{effects::AudioEffect, frame::Frame, NUM_CHANNELS};
+// The detector catches peaks instantly and lets go over this time,
+// long enough to ride over the cycles of a low E string
+const DETECTOR_RELEASE_MS: f32 = 20.0;
 
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NoiseGate {
-    gates: [Gate; NUM_CHANNELS],
+    enabled: bool,
+    threshold_db: f32,
+    hysteresis_db: f32,
+    attack_ms: f32,
+    hold_ms: f32,
+    release_ms: f32,
+    range_db: f32,
+    sample_rate: f32,
+    gate: Gate,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 struct Gate {
-    threshold_db: f32,
-    attack_seconds: f32,
-    release_seconds: f32,
-    is_gate_open: bool,
+    open_threshold: f32,
+    close_threshold: f32,
+    floor_gain: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    detector_coeff: f32,
+    hold_samples: usize,
+
+    envelope: f32,
+    hold_counter: usize,
+    is_open: bool,
     current_gain: f32,
 }
 
-impl Default for Gate {
+impl Default for NoiseGate {
     fn default() -> Self {
-        Self {
-            attack_seconds: 0.2,
-            release_seconds: 0.2,
-            threshold_db: -12.0,
-            is_gate_open: false,
-            current_gain: 0.0,
-        }
+        let mut gate = Self {
+            enabled: false,
+            threshold_db: -60.0,
+            hysteresis_db: 6.0,
+            attack_ms: 1.0,
+            hold_ms: 50.0,
+            release_ms: 150.0,
+            range_db: 80.0,
+            sample_rate: 44100.0,
+            gate: Gate {
+                current_gain: 1.0,
+                ..Default::default()
+            },
+        };
+        gate.recalculate();
+        gate
+    }
+}
+
+impl PartialEq for NoiseGate {
+    fn eq(&self, other: &Self) -> bool {
+        self.enabled == other.enabled
+            && self.threshold_db == other.threshold_db
+            && self.hysteresis_db == other.hysteresis_db
+            && self.attack_ms == other.attack_ms
+            && self.hold_ms == other.hold_ms
+            && self.release_ms == other.release_ms
+            && self.range_db == other.range_db
     }
 }
 
 impl NoiseGate {
-    pub fn set_attack_seconds(&mut self, seconds: f32) {
-        self.gates
-            .iter_mut()
-            .for_each(|gate| gate.attack_seconds = seconds);
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold_db
+    }
+
+    pub fn hysteresis(&self) -> f32 {
+        self.hysteresis_db
+    }
+
+    pub fn attack_ms(&self) -> f32 {
+        self.attack_ms
+    }
+
+    pub fn hold_ms(&self) -> f32 {
+        self.hold_ms
+    }
+
+    pub fn release_ms(&self) -> f32 {
+        self.release_ms
+    }
+
+    pub fn range(&self) -> f32 {
+        self.range_db
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.gate.current_gain = 1.0;
+        self.gate.is_open = true;
+    }
+
+    pub fn set_threshold(&mut self, threshold_db: f32) {
+        self.threshold_db = threshold_db;
+        self.recalculate();
+    }
+
+    pub fn set_hysteresis(&mut self, hysteresis_db: f32) {
+        self.hysteresis_db = hysteresis_db.max(0.0);
+        self.recalculate();
+    }
+
+    pub fn set_attack_ms(&mut self, attack_ms: f32) {
+        self.attack_ms = attack_ms.max(0.0);
+        self.recalculate();
+    }
+
+    pub fn set_hold_ms(&mut self, hold_ms: f32) {
+        self.hold_ms = hold_ms.max(0.0);
+        self.recalculate();
     }
 
-    pub fn set_release_seconds(&mut self, seconds: f32) {
-        self.gates
-            .iter_mut()
-            .for_each(|gate| gate.release_seconds = seconds);
+    pub fn set_release_ms(&mut self, release_ms: f32) {
+        self.release_ms = release_ms.max(0.0);
+        self.recalculate();
     }
 
-    pub fn set_threshold(&mut self, threshold: f32) {
-        self.gates
-            .iter_mut()
-            .for_each(|gate| gate.threshold_db = threshold);
+    /// How far down the signal is pushed while the gate is closed, in dB.
+    /// Small values turn the gate into a gentle expander.
+    pub fn set_range(&mut self, range_db: f32) {
+        self.range_db = range_db.max(0.0);
+        self.recalculate();
     }
 
-    fn process_channel(gate: &mut Gate, sample: f32) -> f32 {
-        let input_db = sample.abs().log10() * 20.0;
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.recalculate();
+    }
 
-        if input_db >= gate.threshold_db {
-            gate.is_gate_open = true;
+    fn recalculate(&mut self) {
+        let gate = &mut self.gate;
+        gate.open_threshold = db_to_gain(self.threshold_db);
+        gate.close_threshold = db_to_gain(self.threshold_db - self.hysteresis_db);
+        gate.floor_gain = db_to_gain(-self.range_db);
+        gate.attack_coeff = one_pole_coeff(self.attack_ms, self.sample_rate);
+        gate.release_coeff = one_pole_coeff(self.release_ms, self.sample_rate);
+        gate.detector_coeff = one_pole_coeff(DETECTOR_RELEASE_MS, self.sample_rate);
+        gate.hold_samples = (self.hold_ms * 0.001 * self.sample_rate) as usize;
+    }
+
+    #[inline]
+    fn tick(gate: &mut Gate, level: f32) -> f32 {
+        gate.envelope = if level > gate.envelope {
+            level
         } else {
-            if gate.is_gate_open {
-                gate.current_gain = (1.0 - gate.release_seconds) * gate.current_gain;
+            level + gate.detector_coeff * (gate.envelope - level)
+        };
+
+        if gate.envelope >= gate.open_threshold {
+            gate.is_open = true;
+            gate.hold_counter = gate.hold_samples;
+        } else if gate.envelope < gate.close_threshold {
+            if gate.hold_counter > 0 {
+                gate.hold_counter -= 1;
             } else {
-                gate.current_gain = gate.attack_seconds * (input_db - gate.threshold_db);
+                gate.is_open = false;
             }
+        } else if gate.is_open {
+            gate.hold_counter = gate.hold_samples;
         }
 
-        let output = sample * 10.0_f32.powf(gate.current_gain / 20.0);
+        let target = if gate.is_open { 1.0 } else { gate.floor_gain };
+        let coeff = if target > gate.current_gain {
+            gate.attack_coeff
+        } else {
+            gate.release_coeff
+        };
+        gate.current_gain = target + coeff * (gate.current_gain - target);
 
-        output
+        gate.current_gain
     }
 }
 
 impl AudioEffect for NoiseGate {
-    fn process(&mut self, input_buffer: &mut Frame, _transport: &nih_plug::prelude::Transport) {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        if !self.enabled {
+            return;
+        }
+
+        let gate = &mut self.gate;
         input_buffer.process_individual(|left, right| {
-            *left = Self::process_channel(&mut self.gates[0], *left);
-            *right = Self::process_channel(&mut self.gates[1], *right);
+            // Both channels share one detector so the stereo image doesn't wander
+            let gain = Self::tick(gate, left.abs().max(right.abs()));
+            *left *= gain;
+            *right *= gain;
         })
     }
 }
@@ -0,0 +1,408 @@
+use crate::prelude::*;
+
+const NUM_BANDS: usize = 7;
+const HIGH_PASS_BAND: usize = 0;
+const LOW_PASS_BAND: usize = NUM_BANDS - 1;
+const BAND_NAMES: [&str; NUM_BANDS] = ["HP", "1", "2", "3", "4", "5", "LP"];
+const BAND_MODES: [FilterMode; 5] = [
+    FilterMode::Bell,
+    FilterMode::LowShelf,
+    FilterMode::HighShelf,
+    FilterMode::Notch,
+    FilterMode::Peak,
+];
+
+const DB_RANGE: f32 = 24.0;
+const RESPONSE_RESOLUTION: usize = 256;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EqBand {
+    enabled: bool,
+    gain_db: f32,
+    filters: [SvfFilter; NUM_CHANNELS],
+}
+
+impl PartialEq for EqBand {
+    fn eq(&self, other: &Self) -> bool {
+        self.enabled == other.enabled
+            && self.gain_db == other.gain_db
+            && self.mode() == other.mode()
+            && self.frequency() == other.frequency()
+            && self.q() == other.q()
+    }
+}
+
+impl EqBand {
+    fn new(mode: FilterMode, frequency: f32, q: f32, enabled: bool) -> Self {
+        Self {
+            enabled,
+            gain_db: 0.0,
+            filters: [SvfFilter::new(mode, 44100.0, frequency, q); NUM_CHANNELS],
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn mode(&self) -> FilterMode {
+        self.filters[0].mode()
+    }
+
+    pub fn frequency(&self) -> f32 {
+        self.filters[0].cutoff()
+    }
+
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+
+    pub fn q(&self) -> f32 {
+        self.filters[0].q()
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.filters.iter_mut().for_each(|filter| filter.reset());
+    }
+
+    pub fn set_mode(&mut self, mode: FilterMode) {
+        self.filters
+            .iter_mut()
+            .for_each(|filter| filter.set_mode(mode));
+    }
+
+    pub fn set_frequency(&mut self, frequency: f32) {
+        let frequency = frequency.clamp(GRAPH_MIN_FREQ, GRAPH_MAX_FREQ);
+        self.filters
+            .iter_mut()
+            .for_each(|filter| filter.set_cutoff(frequency));
+    }
+
+    pub fn set_gain_db(&mut self, gain_db: f32) {
+        self.gain_db = gain_db.clamp(-DB_RANGE, DB_RANGE);
+        let gain = db_to_gain(self.gain_db);
+        self.filters
+            .iter_mut()
+            .for_each(|filter| filter.set_gain(gain));
+    }
+
+    pub fn set_q(&mut self, q: f32) {
+        self.filters
+            .iter_mut()
+            .for_each(|filter| filter.set_q(q.max(0.1)));
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.filters
+            .iter_mut()
+            .for_each(|filter| filter.set_sample_rate(sample_rate));
+    }
+
+    fn magnitude_db(&self, frequency: f32) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+
+        gain_to_db(self.filters[0].response(frequency).norm())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ParametricEq {
+    bands: [EqBand; NUM_BANDS],
+}
+
+impl Default for ParametricEq {
+    fn default() -> Self {
+        Self {
+            bands: [
+                EqBand::new(FilterMode::Highpass, 80.0, 0.707, false),
+                EqBand::new(FilterMode::LowShelf, 100.0, 0.707, true),
+                EqBand::new(FilterMode::Bell, 400.0, 1.0, true),
+                EqBand::new(FilterMode::Bell, 1000.0, 1.0, true),
+                EqBand::new(FilterMode::Bell, 3000.0, 1.0, true),
+                EqBand::new(FilterMode::HighShelf, 8000.0, 0.707, true),
+                EqBand::new(FilterMode::Lowpass, 12000.0, 0.707, false),
+            ],
+        }
+    }
+}
+
+impl ParametricEq {
+    pub fn band(&self, band: usize) -> &EqBand {
+        &self.bands[band]
+    }
+
+    pub fn band_mut(&mut self, band: usize) -> &mut EqBand {
+        &mut self.bands[band]
+    }
+
+    /// Summed magnitude response of every enabled band, in dB.
+    pub fn response(&self, frequencies: &[f32]) -> Vec<f32> {
+        frequencies
+            .iter()
+            .map(|frequency| {
+                self.bands
+                    .iter()
+                    .map(|band| band.magnitude_db(*frequency))
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+impl AudioEffect for ParametricEq {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.bands[0].filters[0].sample_rate() != transport.sample_rate {
+            self.bands
+                .iter_mut()
+                .for_each(|band| band.set_sample_rate(transport.sample_rate));
+        }
+
+        input_buffer.process_individual(|left, right| {
+            for band in self.bands.iter_mut().filter(|band| band.enabled) {
+                *left = band.filters[0].tick(*left);
+                *right = band.filters[1].tick(*right);
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        ParametricEqView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        200.0
+    }
+}
+
+fn mode_name(mode: FilterMode) -> &'static str {
+    match mode {
+        FilterMode::Highpass => "HIGH PASS",
+        FilterMode::Lowpass => "LOW PASS",
+        FilterMode::BandPass => "BAND PASS",
+        FilterMode::Notch => "NOTCH",
+        FilterMode::Peak => "PEAK",
+        FilterMode::AllPass => "ALL PASS",
+        FilterMode::Bell | FilterMode::ConstantQBell => "BELL",
+        FilterMode::LowShelf => "LOW SHELF",
+        FilterMode::HighShelf => "HIGH SHELF",
+    }
+}
+
+enum ParametricEqEvent {
+    Select(usize),
+    Drag(usize, f32, f32),
+    CycleMode,
+    Toggle,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct ParametricEqView {
+    #[msg]
+    frequency: f32,
+    #[msg]
+    gain: f32,
+    #[msg]
+    q: f32,
+
+    selected: usize,
+    mode: FilterMode,
+    enabled: bool,
+    revision: u64,
+
+    response: Vec<f32>,
+    nodes: Vec<GraphNode>,
+
+    #[lens(ignore)]
+    handle: EffectHandle<ParametricEq>,
+}
+
+impl ParametricEqView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<ParametricEq>) -> Handle<Self> {
+        let selected = 3;
+        let band = *handle.band(selected);
+        let mut view = Self {
+            frequency: band.frequency(),
+            gain: band.gain_db(),
+            q: band.q(),
+            selected,
+            mode: band.mode(),
+            enabled: band.enabled(),
+            revision: 0,
+            response: vec![],
+            nodes: vec![],
+            handle: handle.clone(),
+        };
+        view.update_graph();
+
+        view.build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                ResponseGraph::new(cx, Self::response, Self::nodes, DB_RANGE)
+                    .on_drag(|ex, band, frequency, gain| {
+                        ex.emit(ParametricEqEvent::Drag(band, frequency, gain))
+                    })
+                    .class("eq-graph");
+                VStack::new(cx, |cx| {
+                    HStack::new(cx, |cx| {
+                        for (index, name) in BAND_NAMES.into_iter().enumerate() {
+                            Button::new(
+                                cx,
+                                move |ex| ex.emit(ParametricEqEvent::Select(index)),
+                                move |cx| Label::new(cx, name),
+                            )
+                            .class("band-btn")
+                            .toggle_class(
+                                "band-selected",
+                                Self::selected.map(move |selected| *selected == index),
+                            );
+                        }
+                    })
+                    .class("band-selector");
+                    Binding::new(cx, Self::revision, |cx, _| {
+                        HStack::new(cx, |cx| {
+                            VStack::new(cx, |cx| {
+                                Button::new(
+                                    cx,
+                                    |ex| ex.emit(ParametricEqEvent::Toggle),
+                                    |cx| {
+                                        Label::new(
+                                            cx,
+                                            Self::enabled
+                                                .map(|enabled| if *enabled { "ON" } else { "OFF" }),
+                                        )
+                                    },
+                                )
+                                .class("band-option")
+                                .toggle_class("band-enabled", Self::enabled);
+                                Button::new(
+                                    cx,
+                                    |ex| ex.emit(ParametricEqEvent::CycleMode),
+                                    |cx| Label::new(cx, Self::mode.map(|mode| mode_name(*mode))),
+                                )
+                                .class("band-option");
+                            })
+                            .class("band-options");
+                            NamedKnob::new(
+                                cx,
+                                "Freq",
+                                Self::frequency,
+                                false,
+                                GRAPH_MIN_FREQ..GRAPH_MAX_FREQ,
+                            )
+                            .on_changing(|ex, val| ex.emit(Message::Frequency(val)));
+                            NamedKnob::new(cx, "Gain", Self::gain, true, -DB_RANGE..DB_RANGE)
+                                .on_changing(|ex, val| ex.emit(Message::Gain(val)))
+                                .disabled(Self::mode.map(|mode| !mode.has_gain()));
+                            NamedKnob::new(cx, "Q", Self::q, false, 0.1..10.0)
+                                .on_changing(|ex, val| ex.emit(Message::Q(val)));
+                        })
+                        .class("band-controls");
+                    });
+                })
+                .class("eq-controls");
+                Label::new(cx, "PARAMETRIC EQ").class("effect-title");
+            });
+        })
+    }
+
+    fn sync_band(&mut self) {
+        let band = self.handle.band(self.selected);
+        self.frequency = band.frequency();
+        self.gain = band.gain_db();
+        self.q = band.q();
+        self.mode = band.mode();
+        self.enabled = band.enabled();
+    }
+
+    fn update_graph(&mut self) {
+        self.response = self
+            .handle
+            .response(&graph_frequencies(RESPONSE_RESOLUTION));
+        self.nodes = self
+            .handle
+            .bands
+            .iter()
+            .enumerate()
+            .map(|(index, band)| GraphNode {
+                frequency: band.frequency(),
+                gain_db: if band.mode().has_gain() {
+                    band.gain_db()
+                } else {
+                    0.0
+                },
+                enabled: band.enabled(),
+                selected: index == self.selected,
+            })
+            .collect();
+    }
+}
+
+impl View for ParametricEqView {
+    fn element(&self) -> Option<&'static str> {
+        Some("parametric-eq")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Frequency(val) => {
+                self.frequency = *val;
+                self.handle.band_mut(self.selected).set_frequency(*val);
+                self.update_graph();
+            }
+            Message::Gain(val) => {
+                self.gain = *val;
+                self.handle.band_mut(self.selected).set_gain_db(*val);
+                self.update_graph();
+            }
+            Message::Q(val) => {
+                self.q = *val;
+                self.handle.band_mut(self.selected).set_q(*val);
+                self.update_graph();
+            }
+        });
+
+        event.map(|event, _| match event {
+            ParametricEqEvent::Select(band) => {
+                self.selected = *band;
+                self.sync_band();
+                self.update_graph();
+                self.revision += 1;
+            }
+            ParametricEqEvent::Drag(band, frequency, gain) => {
+                let eq_band = self.handle.band_mut(*band);
+                eq_band.set_frequency(*frequency);
+                if eq_band.mode().has_gain() {
+                    eq_band.set_gain_db(*gain);
+                }
+                self.selected = *band;
+                self.sync_band();
+                self.update_graph();
+                self.revision += 1;
+            }
+            ParametricEqEvent::CycleMode => {
+                if self.selected == HIGH_PASS_BAND || self.selected == LOW_PASS_BAND {
+                    return;
+                }
+
+                let current = BAND_MODES
+                    .iter()
+                    .position(|mode| *mode == self.mode)
+                    .unwrap_or(0);
+                let mode = BAND_MODES[(current + 1) % BAND_MODES.len()];
+                self.handle.band_mut(self.selected).set_mode(mode);
+                self.sync_band();
+                self.update_graph();
+                self.revision += 1;
+            }
+            ParametricEqEvent::Toggle => {
+                let enabled = !self.enabled;
+                self.handle.band_mut(self.selected).set_enabled(enabled);
+                self.sync_band();
+                self.update_graph();
+                self.revision += 1;
+            }
+        });
+    }
+}
@@ -13,6 +13,12 @@ mod dynamics;
 pub use dynamics::*;
 mod reverb;
 pub use reverb::*;
+mod modulation;
+pub use modulation::*;
+mod amp;
+pub use amp::*;
+mod pitch;
+pub use pitch::*;
 
 mod input_simulator;
 pub use input_simulator::InputSimulator;
@@ -24,6 +30,10 @@ pub struct PreFX(pub &'static str);
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, Data)]
 pub struct PostFX(pub &'static str);
 
+/// Emitted by effect views for failures the user should be told about.
+#[derive(Debug, Clone)]
+pub struct EffectError(pub String);
+
 use crate::{frame::Frame, effect_handle::EffectHandle};
 
 pub trait AudioEffect: fmt::Debug + Send + Sync + DynClone + DowncastSync {
@@ -33,6 +43,19 @@ pub trait AudioEffect: fmt::Debug + Send + Sync + DynClone + DowncastSync {
     fn height(&self) -> f32 {
         0.0
     }
+    /// Samples the effect delays its output by, so the host can compensate for it.
+    fn latency(&self) -> u32 {
+        0
+    }
+    /// Called away from the audio thread with the rate the host will run at, before any
+    /// processing at it. Buffers that depend on the sample rate get allocated here.
+    #[allow(unused_variables)]
+    fn prepare(&mut self, sample_rate: f32) {}
+    /// MIDI control change from the host, `value` goes from 0 to 1.
+    #[allow(unused_variables)]
+    fn midi_cc(&mut self, cc: u8, value: f32) {}
+    /// Called on the audio thread before the first block after the effect is switched back on.
+    fn reactivate(&mut self) {}
 }
 
 impl_downcast!(AudioEffect);
@@ -0,0 +1,365 @@
+use crate::prelude::*;
+
+const MAX_VOICES: usize = 4;
+const MAX_DEPTH_MS: f32 = 8.0;
+const MAX_DELAY_MS: f32 = 30.0;
+
+// Bucket brigade chips lose the top end before and after the delay and hiss a little
+const BBD_CUTOFF_HZ: f32 = 6000.0;
+const BBD_NOISE: f32 = 0.0005;
+
+#[derive(Serialize, Deserialize)]
+struct ChorusSettings {
+    rate_hz: f32,
+    depth: f32,
+    delay_ms: f32,
+    mix: f32,
+    voices: usize,
+    vintage: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "ChorusSettings", into = "ChorusSettings")]
+pub struct Chorus {
+    rate_hz: f32,
+    depth: f32,
+    delay_ms: f32,
+    mix: f32,
+    voices: usize,
+    vintage: bool,
+
+    sample_rate: f32,
+    lfos: [Lfo; MAX_VOICES],
+    lines: [ModulatedDelay; NUM_CHANNELS],
+    pre_filters: [SvfFilter; NUM_CHANNELS],
+    post_filters: [SvfFilter; NUM_CHANNELS],
+    noise_seed: u32,
+}
+
+impl Default for Chorus {
+    fn default() -> Self {
+        ChorusSettings {
+            rate_hz: 0.8,
+            depth: 0.5,
+            delay_ms: 12.0,
+            mix: 0.5,
+            voices: 1,
+            vintage: false,
+        }
+        .into()
+    }
+}
+
+impl From<ChorusSettings> for Chorus {
+    fn from(settings: ChorusSettings) -> Self {
+        let max_delay_ms = MAX_DELAY_MS + MAX_DEPTH_MS + 1.0;
+        let mut chorus = Self {
+            rate_hz: settings.rate_hz,
+            depth: settings.depth,
+            delay_ms: settings.delay_ms,
+            mix: settings.mix,
+            voices: 1,
+            vintage: settings.vintage,
+            sample_rate: 44100.0,
+            lfos: [Lfo::default(); MAX_VOICES],
+            lines: [
+                ModulatedDelay::new(max_delay_ms),
+                ModulatedDelay::new(max_delay_ms),
+            ],
+            pre_filters: [SvfFilter::new(FilterMode::Lowpass, 44100.0, BBD_CUTOFF_HZ, 0.707);
+                NUM_CHANNELS],
+            post_filters: [SvfFilter::new(FilterMode::Lowpass, 44100.0, BBD_CUTOFF_HZ, 0.707);
+                NUM_CHANNELS],
+            noise_seed: 0x1234_5678,
+        };
+
+        // Presets can be edited by hand, so everything goes through the same limits as the knobs
+        chorus.set_depth(settings.depth);
+        chorus.set_delay_ms(settings.delay_ms);
+        chorus.set_mix(settings.mix);
+        chorus.set_voices(settings.voices);
+        chorus.set_rate(settings.rate_hz);
+        chorus
+    }
+}
+
+impl From<Chorus> for ChorusSettings {
+    fn from(chorus: Chorus) -> Self {
+        Self {
+            rate_hz: chorus.rate_hz,
+            depth: chorus.depth,
+            delay_ms: chorus.delay_ms,
+            mix: chorus.mix,
+            voices: chorus.voices,
+            vintage: chorus.vintage,
+        }
+    }
+}
+
+impl PartialEq for Chorus {
+    fn eq(&self, other: &Self) -> bool {
+        self.rate_hz == other.rate_hz
+            && self.depth == other.depth
+            && self.delay_ms == other.delay_ms
+            && self.mix == other.mix
+            && self.voices == other.voices
+            && self.vintage == other.vintage
+    }
+}
+
+impl Chorus {
+    pub fn rate(&self) -> f32 {
+        self.rate_hz
+    }
+
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    pub fn delay_ms(&self) -> f32 {
+        self.delay_ms
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    pub fn voices(&self) -> usize {
+        self.voices
+    }
+
+    pub fn vintage(&self) -> bool {
+        self.vintage
+    }
+
+    pub fn set_rate(&mut self, rate_hz: f32) {
+        self.rate_hz = rate_hz.max(0.0);
+        self.configure_lfos();
+    }
+
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth.clamp(0.0, 1.0);
+    }
+
+    pub fn set_delay_ms(&mut self, delay_ms: f32) {
+        self.delay_ms = delay_ms.clamp(1.0, MAX_DELAY_MS);
+    }
+
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    pub fn set_voices(&mut self, voices: usize) {
+        self.voices = voices.clamp(1, MAX_VOICES);
+        self.configure_lfos();
+    }
+
+    /// Bucket brigade mode, darker and noisier with a triangle sweep like the old analog pedals.
+    pub fn set_vintage(&mut self, vintage: bool) {
+        self.vintage = vintage;
+        self.pre_filters
+            .iter_mut()
+            .chain(self.post_filters.iter_mut())
+            .for_each(|filter| filter.reset());
+        self.configure_lfos();
+    }
+
+    fn configure_lfos(&mut self) {
+        let shape = if self.vintage {
+            LfoShape::Triangle
+        } else {
+            LfoShape::Sine
+        };
+
+        // Voices are spread evenly around the cycle, left and right a quarter cycle apart
+        let voices = self.voices;
+        for (voice, lfo) in self.lfos.iter_mut().enumerate() {
+            lfo.set_shape(shape);
+            lfo.set_rate(LfoRate::Free(self.rate_hz));
+            lfo.set_phase_offset(voice as f32 / voices as f32);
+            lfo.set_stereo_spread(0.25);
+        }
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.lines.iter_mut().for_each(|line| line.reset());
+        self.lfos
+            .iter_mut()
+            .for_each(|lfo| lfo.set_sample_rate(sample_rate));
+        self.pre_filters
+            .iter_mut()
+            .chain(self.post_filters.iter_mut())
+            .for_each(|filter| filter.set_sample_rate(sample_rate));
+    }
+
+    #[inline]
+    fn noise(seed: &mut u32) -> f32 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 17;
+        *seed ^= *seed << 5;
+        (*seed as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+impl AudioEffect for Chorus {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        let sample_rate = transport.sample_rate;
+        let base_delay = self.delay_ms * 0.001 * sample_rate;
+        let depth = self.depth * MAX_DEPTH_MS * 0.001 * sample_rate;
+        let voice_gain = 1.0 / (self.voices as f32).sqrt();
+
+        let Self {
+            mix,
+            voices,
+            vintage,
+            lfos,
+            lines,
+            pre_filters,
+            post_filters,
+            noise_seed,
+            ..
+        } = self;
+        let lfos = &mut lfos[..*voices];
+        lfos.iter_mut().for_each(|lfo| lfo.update(transport));
+
+        input_buffer.process_individual(|left, right| {
+            let input = [*left, *right];
+            let mut wet = [0.0; NUM_CHANNELS];
+
+            for lfo in lfos.iter_mut() {
+                let modulation = lfo.tick();
+                for channel in 0..NUM_CHANNELS {
+                    let delay = base_delay + depth * 0.5 * (modulation[channel] + 1.0);
+                    wet[channel] += lines[channel].read(delay);
+                }
+            }
+
+            for channel in 0..NUM_CHANNELS {
+                wet[channel] *= voice_gain;
+
+                let write = if *vintage {
+                    wet[channel] = post_filters[channel]
+                        .tick(wet[channel] + BBD_NOISE * Self::noise(noise_seed));
+                    // The compander in front of the chips rounds off hot signals
+                    pre_filters[channel].tick(input[channel]).tanh()
+                } else {
+                    input[channel]
+                };
+                lines[channel].write(write);
+            }
+
+            *left = input[0] + *mix * (wet[0] - input[0]);
+            *right = input[1] + *mix * (wet[1] - input[1]);
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        ChorusView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        100.0
+    }
+}
+
+enum ChorusEvent {
+    ToggleVintage,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct ChorusView {
+    #[msg]
+    rate: f32,
+    #[msg]
+    depth: f32,
+    #[msg]
+    delay: f32,
+    #[msg]
+    mix: f32,
+    #[msg]
+    voices: f32,
+
+    vintage: bool,
+
+    #[lens(ignore)]
+    handle: EffectHandle<Chorus>,
+}
+
+impl ChorusView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<Chorus>) -> Handle<Self> {
+        Self {
+            rate: handle.rate(),
+            depth: handle.depth() * 100.0,
+            delay: handle.delay_ms(),
+            mix: handle.mix() * 100.0,
+            voices: handle.voices() as f32,
+            vintage: handle.vintage(),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                NamedKnob::new(cx, "Rate", Self::rate, false, 0.05..5.0)
+                    .on_changing(|ex, val| ex.emit(Message::Rate(val)));
+                NamedKnob::new(cx, "Depth", Self::depth, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Depth(val)));
+                NamedKnob::new(cx, "Delay", Self::delay, false, 5.0..MAX_DELAY_MS)
+                    .on_changing(|ex, val| ex.emit(Message::Delay(val)));
+                NamedKnob::new(cx, "Mix", Self::mix, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Mix(val)));
+                NamedKnob::new(cx, "Voices", Self::voices, false, 1.0..MAX_VOICES as f32)
+                    .on_changing(|ex, val| ex.emit(Message::Voices(val)));
+                Button::new(
+                    cx,
+                    |ex| ex.emit(ChorusEvent::ToggleVintage),
+                    |cx| Label::new(cx, "VINTAGE"),
+                )
+                .class("chorus-vintage")
+                .toggle_class("vintage-enabled", Self::vintage);
+                Label::new(cx, "CHORUS").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for ChorusView {
+    fn element(&self) -> Option<&'static str> {
+        Some("chorus")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Rate(val) => {
+                self.rate = *val;
+                self.handle.set_rate(*val);
+            }
+            Message::Depth(val) => {
+                self.depth = *val;
+                self.handle.set_depth(*val / 100.0);
+            }
+            Message::Delay(val) => {
+                self.delay = *val;
+                self.handle.set_delay_ms(*val);
+            }
+            Message::Mix(val) => {
+                self.mix = *val;
+                self.handle.set_mix(*val / 100.0);
+            }
+            Message::Voices(val) => {
+                self.voices = *val;
+                self.handle.set_voices(val.round() as usize);
+            }
+        });
+
+        event.map(|event, _| match event {
+            ChorusEvent::ToggleVintage => {
+                self.vintage = !self.vintage;
+                self.handle.set_vintage(self.vintage);
+            }
+        });
+    }
+}
@@ -0,0 +1,376 @@
+use crate::prelude::*;
+
+const MAX_MANUAL_MS: f32 = 5.0;
+const MAX_SWEEP_MS: f32 = 5.0;
+const MAX_FEEDBACK: f32 = 0.95;
+const DEFAULT_DIVISION: usize = 4;
+
+#[derive(Serialize, Deserialize)]
+struct FlangerSettings {
+    rate_hz: f32,
+    synced: bool,
+    division: usize,
+    depth: f32,
+    manual_ms: f32,
+    feedback: f32,
+    mix: f32,
+    through_zero: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "FlangerSettings", into = "FlangerSettings")]
+pub struct Flanger {
+    rate_hz: f32,
+    synced: bool,
+    division: usize,
+    depth: f32,
+    manual_ms: f32,
+    feedback: f32,
+    mix: f32,
+    through_zero: bool,
+
+    sample_rate: f32,
+    lfo: Lfo,
+    lines: [ModulatedDelay; NUM_CHANNELS],
+}
+
+impl Default for Flanger {
+    fn default() -> Self {
+        FlangerSettings {
+            rate_hz: 0.25,
+            synced: false,
+            division: DEFAULT_DIVISION,
+            depth: 0.7,
+            manual_ms: 1.0,
+            feedback: 0.5,
+            mix: 0.5,
+            through_zero: false,
+        }
+        .into()
+    }
+}
+
+impl From<FlangerSettings> for Flanger {
+    fn from(settings: FlangerSettings) -> Self {
+        let max_delay_ms = MAX_MANUAL_MS + 2.0 * MAX_SWEEP_MS + 1.0;
+        let mut flanger = Self {
+            rate_hz: settings.rate_hz,
+            synced: settings.synced,
+            division: DEFAULT_DIVISION,
+            depth: settings.depth,
+            manual_ms: settings.manual_ms,
+            feedback: settings.feedback,
+            mix: settings.mix,
+            through_zero: settings.through_zero,
+            sample_rate: 44100.0,
+            lfo: Lfo::new(LfoShape::Triangle, LfoRate::Free(0.25)),
+            lines: [
+                ModulatedDelay::new(max_delay_ms),
+                ModulatedDelay::new(max_delay_ms),
+            ],
+        };
+        flanger.lfo.set_stereo_spread(0.25);
+
+        // Presets can be edited by hand, so everything goes through the same limits as the knobs
+        flanger.set_rate(settings.rate_hz);
+        flanger.set_division(settings.division);
+        flanger.set_depth(settings.depth);
+        flanger.set_manual_ms(settings.manual_ms);
+        flanger.set_feedback(settings.feedback);
+        flanger.set_mix(settings.mix);
+        flanger
+    }
+}
+
+impl From<Flanger> for FlangerSettings {
+    fn from(flanger: Flanger) -> Self {
+        Self {
+            rate_hz: flanger.rate_hz,
+            synced: flanger.synced,
+            division: flanger.division,
+            depth: flanger.depth,
+            manual_ms: flanger.manual_ms,
+            feedback: flanger.feedback,
+            mix: flanger.mix,
+            through_zero: flanger.through_zero,
+        }
+    }
+}
+
+impl PartialEq for Flanger {
+    fn eq(&self, other: &Self) -> bool {
+        self.rate_hz == other.rate_hz
+            && self.synced == other.synced
+            && self.division == other.division
+            && self.depth == other.depth
+            && self.manual_ms == other.manual_ms
+            && self.feedback == other.feedback
+            && self.mix == other.mix
+            && self.through_zero == other.through_zero
+    }
+}
+
+impl Flanger {
+    pub fn rate(&self) -> f32 {
+        self.rate_hz
+    }
+
+    pub fn synced(&self) -> bool {
+        self.synced
+    }
+
+    /// Index into [`SYNC_DIVISIONS`] used while synced.
+    pub fn division(&self) -> usize {
+        self.division
+    }
+
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    pub fn manual_ms(&self) -> f32 {
+        self.manual_ms
+    }
+
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    pub fn through_zero(&self) -> bool {
+        self.through_zero
+    }
+
+    pub fn set_rate(&mut self, rate_hz: f32) {
+        self.rate_hz = rate_hz.max(0.0);
+        self.configure_lfo();
+    }
+
+    pub fn set_synced(&mut self, synced: bool) {
+        self.synced = synced;
+        self.configure_lfo();
+    }
+
+    pub fn set_division(&mut self, division: usize) {
+        self.division = division.min(SYNC_DIVISIONS.len() - 1);
+        self.configure_lfo();
+    }
+
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth.clamp(0.0, 1.0);
+    }
+
+    pub fn set_manual_ms(&mut self, manual_ms: f32) {
+        self.manual_ms = manual_ms.clamp(0.0, MAX_MANUAL_MS);
+    }
+
+    /// Negative values invert the regenerated signal, moving the peaks to where the notches were.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(-MAX_FEEDBACK, MAX_FEEDBACK);
+    }
+
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    /// Delays the dry signal too so the sweep can pass it, cancelling completely at the crossing.
+    pub fn set_through_zero(&mut self, through_zero: bool) {
+        self.through_zero = through_zero;
+        self.lines.iter_mut().for_each(|line| line.reset());
+    }
+
+    fn configure_lfo(&mut self) {
+        let rate = if self.synced {
+            LfoRate::Synced(SYNC_DIVISIONS[self.division].0)
+        } else {
+            LfoRate::Free(self.rate_hz)
+        };
+        self.lfo.set_rate(rate);
+    }
+}
+
+impl AudioEffect for Flanger {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate != transport.sample_rate {
+            self.sample_rate = transport.sample_rate;
+            self.lfo.set_sample_rate(transport.sample_rate);
+        }
+        self.lfo.update(transport);
+
+        let samples_per_ms = 0.001 * self.sample_rate;
+        let manual = self.manual_ms * samples_per_ms;
+        let sweep = self.depth * MAX_SWEEP_MS * samples_per_ms;
+
+        let Self {
+            feedback,
+            mix,
+            through_zero,
+            lfo,
+            lines,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            let modulation = lfo.tick();
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let line = &mut lines[channel];
+                let (dry, delay) = if *through_zero {
+                    // Both taps sit behind the input and the wet one swings either side of the dry one
+                    let reference = manual + sweep + 2.0;
+                    (line.read(reference), reference + sweep * modulation[channel])
+                } else {
+                    (*sample, 2.0 + manual + sweep * 0.5 * (modulation[channel] + 1.0))
+                };
+
+                let wet = line.read(delay);
+                line.write(*sample + *feedback * wet);
+                *sample = dry + *mix * (wet - dry);
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        FlangerView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        100.0
+    }
+}
+
+enum FlangerEvent {
+    ToggleSync,
+    CycleDivision,
+    ToggleThroughZero,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct FlangerView {
+    #[msg]
+    rate: f32,
+    #[msg]
+    depth: f32,
+    #[msg]
+    manual: f32,
+    #[msg]
+    feedback: f32,
+    #[msg]
+    mix: f32,
+
+    synced: bool,
+    division: usize,
+    through_zero: bool,
+
+    #[lens(ignore)]
+    handle: EffectHandle<Flanger>,
+}
+
+impl FlangerView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<Flanger>) -> Handle<Self> {
+        Self {
+            rate: handle.rate(),
+            depth: handle.depth() * 100.0,
+            manual: handle.manual_ms(),
+            feedback: handle.feedback() * 100.0,
+            mix: handle.mix() * 100.0,
+            synced: handle.synced(),
+            division: handle.division(),
+            through_zero: handle.through_zero(),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                NamedKnob::new(cx, "Rate", Self::rate, false, 0.05..5.0)
+                    .on_changing(|ex, val| ex.emit(Message::Rate(val)))
+                    .disabled(Self::synced);
+                NamedKnob::new(cx, "Depth", Self::depth, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Depth(val)));
+                NamedKnob::new(cx, "Manual", Self::manual, false, 0.0..MAX_MANUAL_MS)
+                    .on_changing(|ex, val| ex.emit(Message::Manual(val)));
+                NamedKnob::new(cx, "Feedback", Self::feedback, true, -95.0..95.0)
+                    .on_changing(|ex, val| ex.emit(Message::Feedback(val)));
+                NamedKnob::new(cx, "Mix", Self::mix, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Mix(val)));
+                VStack::new(cx, |cx| {
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(FlangerEvent::ToggleSync),
+                        |cx| Label::new(cx, "SYNC"),
+                    )
+                    .class("flanger-option")
+                    .toggle_class("option-enabled", Self::synced);
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(FlangerEvent::CycleDivision),
+                        |cx| {
+                            Label::new(
+                                cx,
+                                Self::division.map(|division| SYNC_DIVISIONS[*division].1),
+                            )
+                        },
+                    )
+                    .class("flanger-option")
+                    .disabled(Self::synced.map(|synced| !*synced));
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(FlangerEvent::ToggleThroughZero),
+                        |cx| Label::new(cx, "THRU 0"),
+                    )
+                    .class("flanger-option")
+                    .toggle_class("option-enabled", Self::through_zero);
+                })
+                .class("flanger-options");
+                Label::new(cx, "FLANGER").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for FlangerView {
+    fn element(&self) -> Option<&'static str> {
+        Some("flanger")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Rate(val) => {
+                self.rate = *val;
+                self.handle.set_rate(*val);
+            }
+            Message::Depth(val) => {
+                self.depth = *val;
+                self.handle.set_depth(*val / 100.0);
+            }
+            Message::Manual(val) => {
+                self.manual = *val;
+                self.handle.set_manual_ms(*val);
+            }
+            Message::Feedback(val) => {
+                self.feedback = *val;
+                self.handle.set_feedback(*val / 100.0);
+            }
+            Message::Mix(val) => {
+                self.mix = *val;
+                self.handle.set_mix(*val / 100.0);
+            }
+        });
+
+        event.map(|event, _| match event {
+            FlangerEvent::ToggleSync => {
+                self.synced = !self.synced;
+                self.handle.set_synced(self.synced);
+            }
+            FlangerEvent::CycleDivision => {
+                self.division = (self.division + 1) % SYNC_DIVISIONS.len();
+                self.handle.set_division(self.division);
+            }
+            FlangerEvent::ToggleThroughZero => {
+                self.through_zero = !self.through_zero;
+                self.handle.set_through_zero(self.through_zero);
+            }
+        });
+    }
+}
@@ -0,0 +1,14 @@
+mod chorus;
+pub use chorus::Chorus;
+
+mod flanger;
+pub use flanger::Flanger;
+
+mod phaser;
+pub use phaser::{Phaser, PhaserVoicing};
+
+mod tremolo;
+pub use tremolo::{Tremolo, TremoloMode};
+
+mod vibrato;
+pub use vibrato::{Vibrato, VibratoVoicing};
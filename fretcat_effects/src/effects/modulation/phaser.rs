@@ -0,0 +1,445 @@
+use crate::prelude::*;
+
+// A second order all-pass with a Q of one half is two first order stages in series
+const STAGES_PER_SECTION: usize = 2;
+const SECTION_Q: f32 = 0.5;
+const STAGE_OPTIONS: [usize; 4] = [4, 6, 8, 12];
+const MAX_SECTIONS: usize = 12 / STAGES_PER_SECTION;
+
+const MAX_SWEEP_OCTAVES: f32 = 3.0;
+const MAX_FEEDBACK: f32 = 0.9;
+// Sweeping every sample is wasted work, the ear can't follow it that fast
+const CONTROL_INTERVAL: usize = 16;
+
+// The orange pedal's JFETs sweep roughly this range and it was built without feedback
+const SCRIPT_STAGES: usize = 4;
+const SCRIPT_CENTER_HZ: f32 = 600.0;
+const SCRIPT_OCTAVES: f32 = 1.7;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum PhaserVoicing {
+    Modern,
+    Script,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PhaserSettings {
+    voicing: PhaserVoicing,
+    stages: usize,
+    rate_hz: f32,
+    depth: f32,
+    center_hz: f32,
+    feedback: f32,
+    mix: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "PhaserSettings", into = "PhaserSettings")]
+pub struct Phaser {
+    voicing: PhaserVoicing,
+    stages: usize,
+    rate_hz: f32,
+    depth: f32,
+    center_hz: f32,
+    feedback: f32,
+    mix: f32,
+
+    sample_rate: f32,
+    lfo: Lfo,
+    sections: [[SvfFilter; MAX_SECTIONS]; NUM_CHANNELS],
+    last_output: [f32; NUM_CHANNELS],
+    control_counter: usize,
+}
+
+impl Default for Phaser {
+    fn default() -> Self {
+        PhaserSettings {
+            voicing: PhaserVoicing::Modern,
+            stages: 4,
+            rate_hz: 0.5,
+            depth: 0.7,
+            center_hz: 800.0,
+            feedback: 0.4,
+            mix: 0.5,
+        }
+        .into()
+    }
+}
+
+impl From<PhaserSettings> for Phaser {
+    fn from(settings: PhaserSettings) -> Self {
+        let mut phaser = Self {
+            voicing: settings.voicing,
+            stages: STAGE_OPTIONS[0],
+            rate_hz: settings.rate_hz,
+            depth: settings.depth,
+            center_hz: settings.center_hz,
+            feedback: settings.feedback,
+            mix: settings.mix,
+            sample_rate: 44100.0,
+            lfo: Lfo::new(LfoShape::Triangle, LfoRate::Free(0.5)),
+            sections: [[SvfFilter::new(FilterMode::AllPass, 44100.0, 800.0, SECTION_Q);
+                MAX_SECTIONS]; NUM_CHANNELS],
+            last_output: [0.0; NUM_CHANNELS],
+            control_counter: 0,
+        };
+
+        // Presets can be edited by hand, so everything goes through the same limits as the knobs
+        phaser.set_stages(settings.stages);
+        phaser.set_rate(settings.rate_hz);
+        phaser.set_depth(settings.depth);
+        phaser.set_center(settings.center_hz);
+        phaser.set_feedback(settings.feedback);
+        phaser.set_mix(settings.mix);
+        phaser
+    }
+}
+
+impl From<Phaser> for PhaserSettings {
+    fn from(phaser: Phaser) -> Self {
+        Self {
+            voicing: phaser.voicing,
+            stages: phaser.stages,
+            rate_hz: phaser.rate_hz,
+            depth: phaser.depth,
+            center_hz: phaser.center_hz,
+            feedback: phaser.feedback,
+            mix: phaser.mix,
+        }
+    }
+}
+
+impl PartialEq for Phaser {
+    fn eq(&self, other: &Self) -> bool {
+        self.voicing == other.voicing
+            && self.stages == other.stages
+            && self.rate_hz == other.rate_hz
+            && self.depth == other.depth
+            && self.center_hz == other.center_hz
+            && self.feedback == other.feedback
+            && self.mix == other.mix
+    }
+}
+
+impl Phaser {
+    pub fn voicing(&self) -> PhaserVoicing {
+        self.voicing
+    }
+
+    pub fn stages(&self) -> usize {
+        self.stages
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate_hz
+    }
+
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    pub fn center(&self) -> f32 {
+        self.center_hz
+    }
+
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    pub fn set_voicing(&mut self, voicing: PhaserVoicing) {
+        self.voicing = voicing;
+        self.reset();
+    }
+
+    /// Rounded to the closest of 4, 6, 8 or 12 stages.
+    pub fn set_stages(&mut self, stages: usize) {
+        self.stages = *STAGE_OPTIONS
+            .iter()
+            .min_by_key(|option| option.abs_diff(stages))
+            .unwrap_or(&STAGE_OPTIONS[0]);
+        self.reset();
+    }
+
+    pub fn set_rate(&mut self, rate_hz: f32) {
+        self.rate_hz = rate_hz.max(0.0);
+        self.lfo.set_rate(LfoRate::Free(self.rate_hz));
+    }
+
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth.clamp(0.0, 1.0);
+    }
+
+    pub fn set_center(&mut self, center_hz: f32) {
+        self.center_hz = center_hz.clamp(20.0, 20000.0);
+    }
+
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(-MAX_FEEDBACK, MAX_FEEDBACK);
+    }
+
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    /// Lowest and highest frequency the notches sweep between, in Hz.
+    pub fn sweep_range(&self) -> (f32, f32) {
+        let (center, octaves) = self.sweep();
+        (center * 2f32.powf(-octaves), center * 2f32.powf(octaves))
+    }
+
+    fn sweep(&self) -> (f32, f32) {
+        match self.voicing {
+            PhaserVoicing::Modern => (self.center_hz, self.depth * MAX_SWEEP_OCTAVES),
+            PhaserVoicing::Script => (SCRIPT_CENTER_HZ, self.depth * SCRIPT_OCTAVES),
+        }
+    }
+
+    fn active_sections(&self) -> usize {
+        match self.voicing {
+            PhaserVoicing::Modern => self.stages / STAGES_PER_SECTION,
+            PhaserVoicing::Script => SCRIPT_STAGES / STAGES_PER_SECTION,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.sections
+            .iter_mut()
+            .flatten()
+            .for_each(|section| section.reset());
+        self.last_output = [0.0; NUM_CHANNELS];
+    }
+}
+
+impl AudioEffect for Phaser {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate != transport.sample_rate {
+            self.sample_rate = transport.sample_rate;
+            self.lfo.set_sample_rate(transport.sample_rate);
+            self.sections
+                .iter_mut()
+                .flatten()
+                .for_each(|section| section.set_sample_rate(transport.sample_rate));
+        }
+        self.lfo.update(transport);
+
+        let (center, octaves) = self.sweep();
+        let active_sections = self.active_sections();
+        let max_frequency = self.sample_rate * 0.45;
+        let (feedback, mix) = match self.voicing {
+            PhaserVoicing::Modern => (self.feedback, self.mix),
+            PhaserVoicing::Script => (0.0, 0.5),
+        };
+        let script = self.voicing == PhaserVoicing::Script;
+
+        let Self {
+            lfo,
+            sections,
+            last_output,
+            control_counter,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            let modulation = lfo.tick();
+
+            if *control_counter == 0 {
+                for (channel, channel_sections) in sections.iter_mut().enumerate() {
+                    let mut position = modulation[channel];
+                    if script {
+                        // The JFET bias makes the sweep linger at the bottom and rush through the top
+                        position = 2.0 * (0.5 * (position + 1.0)).powf(1.6) - 1.0;
+                    }
+                    let frequency = (center * 2f32.powf(octaves * position)).clamp(20.0, max_frequency);
+                    channel_sections[..active_sections]
+                        .iter_mut()
+                        .for_each(|section| section.set_cutoff(frequency));
+                }
+            }
+            *control_counter = (*control_counter + 1) % CONTROL_INTERVAL;
+
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let dry = *sample;
+                let wet = sections[channel][..active_sections]
+                    .iter_mut()
+                    .fold(dry + feedback * last_output[channel], |signal, section| {
+                        section.tick(signal)
+                    });
+                last_output[channel] = wet;
+                *sample = dry + mix * (wet - dry);
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        PhaserView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        100.0
+    }
+}
+
+fn format_frequency(frequency: f32) -> String {
+    if frequency >= 1000.0 {
+        format!("{:.1}k", frequency / 1000.0)
+    } else {
+        format!("{:.0}", frequency)
+    }
+}
+
+/// Position of `frequency` across the audible range, for drawing the sweep bar.
+fn sweep_percentage(frequency: f32) -> f32 {
+    100.0 * (frequency / 20.0).ln().clamp(0.0, 1000f32.ln()) / 1000f32.ln()
+}
+
+enum PhaserEvent {
+    CycleStages,
+    ToggleScript,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct PhaserView {
+    #[msg]
+    rate: f32,
+    #[msg]
+    depth: f32,
+    #[msg]
+    center: f32,
+    #[msg]
+    feedback: f32,
+    #[msg]
+    mix: f32,
+
+    stages: usize,
+    script: bool,
+    sweep_range: (f32, f32),
+
+    #[lens(ignore)]
+    handle: EffectHandle<Phaser>,
+}
+
+impl PhaserView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<Phaser>) -> Handle<Self> {
+        Self {
+            rate: handle.rate(),
+            depth: handle.depth() * 100.0,
+            center: handle.center(),
+            feedback: handle.feedback() * 100.0,
+            mix: handle.mix() * 100.0,
+            stages: handle.stages(),
+            script: handle.voicing() == PhaserVoicing::Script,
+            sweep_range: handle.sweep_range(),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                NamedKnob::new(cx, "Rate", Self::rate, false, 0.05..8.0)
+                    .on_changing(|ex, val| ex.emit(Message::Rate(val)));
+                NamedKnob::new(cx, "Depth", Self::depth, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Depth(val)));
+                HStack::new(cx, |cx| {
+                    NamedKnob::new(cx, "Center", Self::center, false, 100.0..4000.0)
+                        .on_changing(|ex, val| ex.emit(Message::Center(val)));
+                    NamedKnob::new(cx, "Feedback", Self::feedback, true, -90.0..90.0)
+                        .on_changing(|ex, val| ex.emit(Message::Feedback(val)));
+                    NamedKnob::new(cx, "Mix", Self::mix, false, 0.0..100.0)
+                        .on_changing(|ex, val| ex.emit(Message::Mix(val)));
+                })
+                .class("phaser-modern-knobs")
+                .disabled(Self::script);
+                VStack::new(cx, |cx| {
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(PhaserEvent::CycleStages),
+                        |cx| Label::new(cx, Self::stages.map(|stages| format!("{} STAGE", stages))),
+                    )
+                    .class("phaser-option")
+                    .disabled(Self::script);
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(PhaserEvent::ToggleScript),
+                        |cx| Label::new(cx, "SCRIPT"),
+                    )
+                    .class("phaser-option")
+                    .toggle_class("option-enabled", Self::script);
+                    Label::new(
+                        cx,
+                        Self::sweep_range.map(|(low, high)| {
+                            format!("{} - {} Hz", format_frequency(*low), format_frequency(*high))
+                        }),
+                    )
+                    .class("sweep-label");
+                    ZStack::new(cx, |cx| {
+                        Element::new(cx)
+                            .class("sweep-range")
+                            .left(Self::sweep_range.map(|(low, _)| Percentage(sweep_percentage(*low))))
+                            .width(Self::sweep_range.map(|(low, high)| {
+                                Percentage(sweep_percentage(*high) - sweep_percentage(*low))
+                            }));
+                    })
+                    .class("sweep-bar");
+                })
+                .class("phaser-options");
+                Label::new(cx, "PHASER").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for PhaserView {
+    fn element(&self) -> Option<&'static str> {
+        Some("phaser")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Rate(val) => {
+                self.rate = *val;
+                self.handle.set_rate(*val);
+            }
+            Message::Depth(val) => {
+                self.depth = *val;
+                self.handle.set_depth(*val / 100.0);
+                self.sweep_range = self.handle.sweep_range();
+            }
+            Message::Center(val) => {
+                self.center = *val;
+                self.handle.set_center(*val);
+                self.sweep_range = self.handle.sweep_range();
+            }
+            Message::Feedback(val) => {
+                self.feedback = *val;
+                self.handle.set_feedback(*val / 100.0);
+            }
+            Message::Mix(val) => {
+                self.mix = *val;
+                self.handle.set_mix(*val / 100.0);
+            }
+        });
+
+        event.map(|event, _| match event {
+            PhaserEvent::CycleStages => {
+                let current = STAGE_OPTIONS
+                    .iter()
+                    .position(|stages| *stages == self.stages)
+                    .unwrap_or(0);
+                self.handle
+                    .set_stages(STAGE_OPTIONS[(current + 1) % STAGE_OPTIONS.len()]);
+                self.stages = self.handle.stages();
+            }
+            PhaserEvent::ToggleScript => {
+                self.script = !self.script;
+                self.handle.set_voicing(if self.script {
+                    PhaserVoicing::Script
+                } else {
+                    PhaserVoicing::Modern
+                });
+                self.sweep_range = self.handle.sweep_range();
+            }
+        });
+    }
+}
@@ -0,0 +1,407 @@
+use crate::prelude::*;
+
+const DEFAULT_DIVISION: usize = 2;
+const HARMONIC_CROSSOVER_HZ: f32 = 800.0;
+// Square waves click without a little smoothing on the gain
+const GAIN_SMOOTHING_MS: f32 = 1.0;
+const MAX_SHAPE_DRIVE: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum TremoloMode {
+    Sine,
+    Triangle,
+    Square,
+    /// The low and high bands pulse against each other, like the brown Fender amps.
+    Harmonic,
+}
+
+impl TremoloMode {
+    const ALL: [TremoloMode; 4] = [
+        TremoloMode::Sine,
+        TremoloMode::Triangle,
+        TremoloMode::Square,
+        TremoloMode::Harmonic,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TremoloMode::Sine => "SINE",
+            TremoloMode::Triangle => "TRIANGLE",
+            TremoloMode::Square => "SQUARE",
+            TremoloMode::Harmonic => "HARMONIC",
+        }
+    }
+
+    fn lfo_shape(&self) -> LfoShape {
+        match self {
+            TremoloMode::Sine | TremoloMode::Harmonic => LfoShape::Sine,
+            TremoloMode::Triangle => LfoShape::Triangle,
+            TremoloMode::Square => LfoShape::Square,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TremoloSettings {
+    mode: TremoloMode,
+    rate_hz: f32,
+    synced: bool,
+    division: usize,
+    depth: f32,
+    shape: f32,
+    pan: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "TremoloSettings", into = "TremoloSettings")]
+pub struct Tremolo {
+    mode: TremoloMode,
+    rate_hz: f32,
+    synced: bool,
+    division: usize,
+    depth: f32,
+    shape: f32,
+    pan: f32,
+
+    sample_rate: f32,
+    lfo: Lfo,
+    gains: [[f32; 2]; NUM_CHANNELS],
+    smoothing_coeff: f32,
+    // Linkwitz-Riley halves add back up flat, so the split leaves the tone alone at zero depth
+    crossovers: [LinkwitzRiley; NUM_CHANNELS],
+}
+
+impl Default for Tremolo {
+    fn default() -> Self {
+        TremoloSettings {
+            mode: TremoloMode::Sine,
+            rate_hz: 4.0,
+            synced: false,
+            division: DEFAULT_DIVISION,
+            depth: 0.6,
+            shape: 0.0,
+            pan: 0.0,
+        }
+        .into()
+    }
+}
+
+impl From<TremoloSettings> for Tremolo {
+    fn from(settings: TremoloSettings) -> Self {
+        let mut tremolo = Self {
+            mode: settings.mode,
+            rate_hz: settings.rate_hz,
+            synced: settings.synced,
+            division: DEFAULT_DIVISION,
+            depth: settings.depth,
+            shape: settings.shape,
+            pan: settings.pan,
+            sample_rate: 44100.0,
+            lfo: Lfo::default(),
+            gains: [[1.0; 2]; NUM_CHANNELS],
+            smoothing_coeff: one_pole_coeff(GAIN_SMOOTHING_MS, 44100.0),
+            crossovers: [LinkwitzRiley::new(HARMONIC_CROSSOVER_HZ, 44100.0); NUM_CHANNELS],
+        };
+
+        // Presets can be edited by hand, so everything goes through the same limits as the knobs
+        tremolo.set_rate(settings.rate_hz);
+        tremolo.set_division(settings.division);
+        tremolo.set_depth(settings.depth);
+        tremolo.set_shape(settings.shape);
+        tremolo.set_pan(settings.pan);
+        tremolo
+    }
+}
+
+impl From<Tremolo> for TremoloSettings {
+    fn from(tremolo: Tremolo) -> Self {
+        Self {
+            mode: tremolo.mode,
+            rate_hz: tremolo.rate_hz,
+            synced: tremolo.synced,
+            division: tremolo.division,
+            depth: tremolo.depth,
+            shape: tremolo.shape,
+            pan: tremolo.pan,
+        }
+    }
+}
+
+impl PartialEq for Tremolo {
+    fn eq(&self, other: &Self) -> bool {
+        self.mode == other.mode
+            && self.rate_hz == other.rate_hz
+            && self.synced == other.synced
+            && self.division == other.division
+            && self.depth == other.depth
+            && self.shape == other.shape
+            && self.pan == other.pan
+    }
+}
+
+impl Tremolo {
+    pub fn mode(&self) -> TremoloMode {
+        self.mode
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate_hz
+    }
+
+    pub fn synced(&self) -> bool {
+        self.synced
+    }
+
+    /// Index into [`SYNC_DIVISIONS`] used while synced.
+    pub fn division(&self) -> usize {
+        self.division
+    }
+
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    pub fn shape(&self) -> f32 {
+        self.shape
+    }
+
+    pub fn pan(&self) -> f32 {
+        self.pan
+    }
+
+    pub fn set_mode(&mut self, mode: TremoloMode) {
+        self.mode = mode;
+        self.configure_lfo();
+    }
+
+    pub fn set_rate(&mut self, rate_hz: f32) {
+        self.rate_hz = rate_hz.max(0.0);
+        self.configure_lfo();
+    }
+
+    pub fn set_synced(&mut self, synced: bool) {
+        self.synced = synced;
+        self.configure_lfo();
+    }
+
+    pub fn set_division(&mut self, division: usize) {
+        self.division = division.min(SYNC_DIVISIONS.len() - 1);
+        self.configure_lfo();
+    }
+
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth.clamp(0.0, 1.0);
+    }
+
+    /// Squares off the wave, from the plain wave at 0 to a hard chop at 1.
+    pub fn set_shape(&mut self, shape: f32) {
+        self.shape = shape.clamp(0.0, 1.0);
+    }
+
+    /// At 0 both sides pulse together, at 1 they alternate and the sound moves across the field.
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan = pan.clamp(0.0, 1.0);
+        self.lfo.set_stereo_spread(0.5 * self.pan);
+    }
+
+    fn configure_lfo(&mut self) {
+        let rate = if self.synced {
+            LfoRate::Synced(SYNC_DIVISIONS[self.division].0)
+        } else {
+            LfoRate::Free(self.rate_hz)
+        };
+        self.lfo.set_rate(rate);
+        self.lfo.set_shape(self.mode.lfo_shape());
+        self.lfo.set_stereo_spread(0.5 * self.pan);
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.smoothing_coeff = one_pole_coeff(GAIN_SMOOTHING_MS, sample_rate);
+        self.lfo.set_sample_rate(sample_rate);
+        self.crossovers
+            .iter_mut()
+            .for_each(|crossover| crossover.set_sample_rate(sample_rate));
+    }
+}
+
+impl AudioEffect for Tremolo {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+        self.lfo.update(transport);
+
+        let drive = 1.0 + self.shape * MAX_SHAPE_DRIVE;
+        let normalize = 1.0 / drive.tanh();
+        let harmonic = self.mode == TremoloMode::Harmonic;
+
+        let Self {
+            depth,
+            lfo,
+            gains,
+            smoothing_coeff,
+            crossovers,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            let modulation = lfo.tick();
+
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let wave = (modulation[channel] * drive).tanh() * normalize;
+                // 0 is the bottom of the dip and 1 is full volume
+                let position = 0.5 * (wave + 1.0);
+                let targets = [1.0 - *depth * (1.0 - position), 1.0 - *depth * position];
+
+                let channel_gains = &mut gains[channel];
+                for (gain, target) in channel_gains.iter_mut().zip(targets) {
+                    *gain = target + *smoothing_coeff * (*gain - target);
+                }
+
+                *sample = if harmonic {
+                    let (low, high) = crossovers[channel].split(*sample);
+                    low * channel_gains[0] + high * channel_gains[1]
+                } else {
+                    *sample * channel_gains[0]
+                };
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        TremoloView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        100.0
+    }
+}
+
+enum TremoloEvent {
+    CycleMode,
+    ToggleSync,
+    CycleDivision,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct TremoloView {
+    #[msg]
+    rate: f32,
+    #[msg]
+    depth: f32,
+    #[msg]
+    shape: f32,
+    #[msg]
+    pan: f32,
+
+    mode: TremoloMode,
+    synced: bool,
+    division: usize,
+
+    #[lens(ignore)]
+    handle: EffectHandle<Tremolo>,
+}
+
+impl TremoloView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<Tremolo>) -> Handle<Self> {
+        Self {
+            rate: handle.rate(),
+            depth: handle.depth() * 100.0,
+            shape: handle.shape() * 100.0,
+            pan: handle.pan() * 100.0,
+            mode: handle.mode(),
+            synced: handle.synced(),
+            division: handle.division(),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                NamedKnob::new(cx, "Rate", Self::rate, false, 0.1..15.0)
+                    .on_changing(|ex, val| ex.emit(Message::Rate(val)))
+                    .disabled(Self::synced);
+                NamedKnob::new(cx, "Depth", Self::depth, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Depth(val)));
+                NamedKnob::new(cx, "Shape", Self::shape, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Shape(val)));
+                NamedKnob::new(cx, "Pan", Self::pan, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Pan(val)));
+                VStack::new(cx, |cx| {
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(TremoloEvent::CycleMode),
+                        |cx| Label::new(cx, Self::mode.map(|mode| mode.name())),
+                    )
+                    .class("tremolo-option");
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(TremoloEvent::ToggleSync),
+                        |cx| Label::new(cx, "SYNC"),
+                    )
+                    .class("tremolo-option")
+                    .toggle_class("option-enabled", Self::synced);
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(TremoloEvent::CycleDivision),
+                        |cx| {
+                            Label::new(
+                                cx,
+                                Self::division.map(|division| SYNC_DIVISIONS[*division].1),
+                            )
+                        },
+                    )
+                    .class("tremolo-option")
+                    .disabled(Self::synced.map(|synced| !*synced));
+                })
+                .class("tremolo-options");
+                Label::new(cx, "TREMOLO").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for TremoloView {
+    fn element(&self) -> Option<&'static str> {
+        Some("tremolo")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Rate(val) => {
+                self.rate = *val;
+                self.handle.set_rate(*val);
+            }
+            Message::Depth(val) => {
+                self.depth = *val;
+                self.handle.set_depth(*val / 100.0);
+            }
+            Message::Shape(val) => {
+                self.shape = *val;
+                self.handle.set_shape(*val / 100.0);
+            }
+            Message::Pan(val) => {
+                self.pan = *val;
+                self.handle.set_pan(*val / 100.0);
+            }
+        });
+
+        event.map(|event, _| match event {
+            TremoloEvent::CycleMode => {
+                let current = TremoloMode::ALL
+                    .iter()
+                    .position(|mode| *mode == self.mode)
+                    .unwrap_or(0);
+                self.mode = TremoloMode::ALL[(current + 1) % TremoloMode::ALL.len()];
+                self.handle.set_mode(self.mode);
+            }
+            TremoloEvent::ToggleSync => {
+                self.synced = !self.synced;
+                self.handle.set_synced(self.synced);
+            }
+            TremoloEvent::CycleDivision => {
+                self.division = (self.division + 1) % SYNC_DIVISIONS.len();
+                self.handle.set_division(self.division);
+            }
+        });
+    }
+}
@@ -0,0 +1,391 @@
+use std::f32::consts::PI;
+
+use crate::prelude::*;
+
+const MAX_DEPTH_MS: f32 = 5.0;
+const BASE_DELAY_MS: f32 = 1.0;
+const ENGAGE_FADE_MS: f32 = 10.0;
+const CONTROL_INTERVAL: usize = 16;
+
+// The Uni-Vibe's phase shift capacitors are staggered over a wide range,
+// which is what keeps its sweep from sounding like a regular phaser
+const UNI_VIBE_STAGES: [f32; 4] = [1.0, 0.45, 2.2, 0.15];
+const UNI_VIBE_MIN_HZ: f32 = 120.0;
+const UNI_VIBE_OCTAVES: f32 = 3.0;
+// The bulb heats quickly and cools slowly
+const LAMP_ATTACK_MS: f32 = 8.0;
+const LAMP_RELEASE_MS: f32 = 45.0;
+// Light dependent resistors don't respond linearly to the light falling on them
+const PHOTOCELL_CURVE: f32 = 0.7;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum VibratoVoicing {
+    /// Pitch vibrato from a modulated delay.
+    Vibrato,
+    /// Phase shift vibrato swept by a lamp and photocells.
+    UniVibe,
+}
+
+/// First order all-pass, the building block of the Uni-Vibe's phase shift network.
+#[derive(Debug, Clone, Copy, Default)]
+struct PhaseStage {
+    coeff: f32,
+    x1: f32,
+    y1: f32,
+}
+
+impl PhaseStage {
+    fn set_frequency(&mut self, frequency: f32, sample_rate: f32) {
+        let t = (PI * frequency / sample_rate).tan();
+        self.coeff = (t - 1.0) / (t + 1.0);
+    }
+
+    #[inline]
+    fn tick(&mut self, input: f32) -> f32 {
+        let output = self.coeff * input + self.x1 - self.coeff * self.y1;
+        self.x1 = input;
+        self.y1 = output;
+        output
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct VibratoSettings {
+    voicing: VibratoVoicing,
+    rate_hz: f32,
+    depth: f32,
+    rise_ms: f32,
+    engaged: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "VibratoSettings", into = "VibratoSettings")]
+pub struct Vibrato {
+    voicing: VibratoVoicing,
+    rate_hz: f32,
+    depth: f32,
+    rise_ms: f32,
+    engaged: bool,
+
+    sample_rate: f32,
+    lfo: Lfo,
+    lines: [ModulatedDelay; NUM_CHANNELS],
+    stages: [[PhaseStage; UNI_VIBE_STAGES.len()]; NUM_CHANNELS],
+    lamp: f32,
+    rise: f32,
+    wet: f32,
+    control_counter: usize,
+}
+
+impl Default for Vibrato {
+    fn default() -> Self {
+        VibratoSettings {
+            voicing: VibratoVoicing::Vibrato,
+            rate_hz: 5.0,
+            depth: 0.3,
+            rise_ms: 0.0,
+            engaged: true,
+        }
+        .into()
+    }
+}
+
+impl From<VibratoSettings> for Vibrato {
+    fn from(settings: VibratoSettings) -> Self {
+        let max_delay_ms = BASE_DELAY_MS + MAX_DEPTH_MS + 1.0;
+        let mut vibrato = Self {
+            voicing: settings.voicing,
+            rate_hz: settings.rate_hz,
+            depth: settings.depth,
+            rise_ms: settings.rise_ms,
+            engaged: settings.engaged,
+            sample_rate: 44100.0,
+            lfo: Lfo::new(LfoShape::Sine, LfoRate::Free(5.0)),
+            lines: [
+                ModulatedDelay::new(max_delay_ms),
+                ModulatedDelay::new(max_delay_ms),
+            ],
+            stages: [[PhaseStage::default(); UNI_VIBE_STAGES.len()]; NUM_CHANNELS],
+            lamp: 0.0,
+            rise: 1.0,
+            // A disengaged vibrato starts out dry instead of fading out on the first notes
+            wet: if settings.engaged { 1.0 } else { 0.0 },
+            control_counter: 0,
+        };
+
+        vibrato.set_rate(settings.rate_hz);
+        vibrato.set_depth(settings.depth);
+        vibrato.set_rise_ms(settings.rise_ms);
+        vibrato
+    }
+}
+
+impl From<Vibrato> for VibratoSettings {
+    fn from(vibrato: Vibrato) -> Self {
+        Self {
+            voicing: vibrato.voicing,
+            rate_hz: vibrato.rate_hz,
+            depth: vibrato.depth,
+            rise_ms: vibrato.rise_ms,
+            engaged: vibrato.engaged,
+        }
+    }
+}
+
+impl PartialEq for Vibrato {
+    fn eq(&self, other: &Self) -> bool {
+        self.voicing == other.voicing
+            && self.rate_hz == other.rate_hz
+            && self.depth == other.depth
+            && self.rise_ms == other.rise_ms
+            && self.engaged == other.engaged
+    }
+}
+
+impl Vibrato {
+    pub fn voicing(&self) -> VibratoVoicing {
+        self.voicing
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate_hz
+    }
+
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    pub fn rise_ms(&self) -> f32 {
+        self.rise_ms
+    }
+
+    pub fn engaged(&self) -> bool {
+        self.engaged
+    }
+
+    pub fn set_voicing(&mut self, voicing: VibratoVoicing) {
+        self.voicing = voicing;
+    }
+
+    pub fn set_rate(&mut self, rate_hz: f32) {
+        self.rate_hz = rate_hz.max(0.0);
+        self.lfo.set_rate(LfoRate::Free(self.rate_hz));
+    }
+
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth.clamp(0.0, 1.0);
+    }
+
+    /// How long the depth takes to swell in after engaging, like a player easing into a vibrato.
+    pub fn set_rise_ms(&mut self, rise_ms: f32) {
+        self.rise_ms = rise_ms.max(0.0);
+    }
+
+    /// Engaging restarts the rise from no depth at all.
+    pub fn set_engaged(&mut self, engaged: bool) {
+        if engaged && !self.engaged {
+            self.rise = 0.0;
+            self.lfo.reset();
+        }
+        self.engaged = engaged;
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.lfo.set_sample_rate(sample_rate);
+    }
+}
+
+impl AudioEffect for Vibrato {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+        self.lfo.update(transport);
+
+        let sample_rate = self.sample_rate;
+        let samples_per_ms = 0.001 * sample_rate;
+        let base_delay = BASE_DELAY_MS * samples_per_ms + 2.0;
+        let depth_samples = self.depth * MAX_DEPTH_MS * samples_per_ms;
+        let rise_step = if self.rise_ms > 0.0 {
+            1.0 / (self.rise_ms * samples_per_ms)
+        } else {
+            1.0
+        };
+        let fade_coeff = one_pole_coeff(ENGAGE_FADE_MS, sample_rate);
+        let lamp_attack = one_pole_coeff(LAMP_ATTACK_MS, sample_rate);
+        let lamp_release = one_pole_coeff(LAMP_RELEASE_MS, sample_rate);
+        let target = if self.engaged { 1.0 } else { 0.0 };
+        let uni_vibe = self.voicing == VibratoVoicing::UniVibe;
+
+        let Self {
+            depth,
+            lfo,
+            lines,
+            stages,
+            lamp,
+            rise,
+            wet,
+            control_counter,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            let modulation = lfo.tick();
+            *rise = (*rise + rise_step).min(1.0);
+            *wet = target + fade_coeff * (*wet - target);
+
+            if uni_vibe {
+                let light = 0.5 * (modulation[0] + 1.0);
+                let coeff = if light > *lamp { lamp_attack } else { lamp_release };
+                *lamp = light + coeff * (*lamp - light);
+
+                if *control_counter == 0 {
+                    let sweep = *depth * *rise * lamp.powf(PHOTOCELL_CURVE);
+                    let frequency = UNI_VIBE_MIN_HZ * 2f32.powf(UNI_VIBE_OCTAVES * sweep);
+                    for channel_stages in stages.iter_mut() {
+                        for (stage, ratio) in channel_stages.iter_mut().zip(UNI_VIBE_STAGES) {
+                            stage.set_frequency(
+                                (frequency * ratio).min(sample_rate * 0.45),
+                                sample_rate,
+                            );
+                        }
+                    }
+                }
+                *control_counter = (*control_counter + 1) % CONTROL_INTERVAL;
+            }
+
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let dry = *sample;
+                let line = &mut lines[channel];
+                line.write(dry);
+
+                let processed = if uni_vibe {
+                    stages[channel]
+                        .iter_mut()
+                        .fold(dry, |signal, stage| stage.tick(signal))
+                } else {
+                    let position = 0.5 * (modulation[channel] + 1.0);
+                    line.read(base_delay + depth_samples * *rise * position)
+                };
+                *sample = dry + *wet * (processed - dry);
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        VibratoView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        100.0
+    }
+
+    /// Coming out of bypass rises from no depth, the same as the ON switch.
+    fn reactivate(&mut self) {
+        if self.engaged {
+            self.rise = 0.0;
+            self.lfo.reset();
+        }
+    }
+}
+
+enum VibratoEvent {
+    ToggleUniVibe,
+    ToggleEngaged,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct VibratoView {
+    #[msg]
+    rate: f32,
+    #[msg]
+    depth: f32,
+    #[msg]
+    rise: f32,
+
+    uni_vibe: bool,
+    engaged: bool,
+
+    #[lens(ignore)]
+    handle: EffectHandle<Vibrato>,
+}
+
+impl VibratoView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<Vibrato>) -> Handle<Self> {
+        Self {
+            rate: handle.rate(),
+            depth: handle.depth() * 100.0,
+            rise: handle.rise_ms(),
+            uni_vibe: handle.voicing() == VibratoVoicing::UniVibe,
+            engaged: handle.engaged(),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                NamedKnob::new(cx, "Rate", Self::rate, false, 0.5..12.0)
+                    .on_changing(|ex, val| ex.emit(Message::Rate(val)));
+                NamedKnob::new(cx, "Depth", Self::depth, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Depth(val)));
+                NamedKnob::new(cx, "Rise", Self::rise, false, 0.0..2000.0)
+                    .on_changing(|ex, val| ex.emit(Message::Rise(val)));
+                VStack::new(cx, |cx| {
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(VibratoEvent::ToggleEngaged),
+                        |cx| Label::new(cx, "ON"),
+                    )
+                    .class("vibrato-option")
+                    .toggle_class("option-enabled", Self::engaged);
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(VibratoEvent::ToggleUniVibe),
+                        |cx| Label::new(cx, "UNI-VIBE"),
+                    )
+                    .class("vibrato-option")
+                    .toggle_class("option-enabled", Self::uni_vibe);
+                })
+                .class("vibrato-options");
+                Label::new(cx, "VIBRATO").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for VibratoView {
+    fn element(&self) -> Option<&'static str> {
+        Some("vibrato")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Rate(val) => {
+                self.rate = *val;
+                self.handle.set_rate(*val);
+            }
+            Message::Depth(val) => {
+                self.depth = *val;
+                self.handle.set_depth(*val / 100.0);
+            }
+            Message::Rise(val) => {
+                self.rise = *val;
+                self.handle.set_rise_ms(*val);
+            }
+        });
+
+        event.map(|event, _| match event {
+            VibratoEvent::ToggleUniVibe => {
+                self.uni_vibe = !self.uni_vibe;
+                self.handle.set_voicing(if self.uni_vibe {
+                    VibratoVoicing::UniVibe
+                } else {
+                    VibratoVoicing::Vibrato
+                });
+            }
+            VibratoEvent::ToggleEngaged => {
+                self.engaged = !self.engaged;
+                self.handle.set_engaged(self.engaged);
+            }
+        });
+    }
+}
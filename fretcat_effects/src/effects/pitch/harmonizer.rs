@@ -0,0 +1,561 @@
+use std::f32::consts::FRAC_PI_4;
+
+use crate::prelude::*;
+
+const NUM_VOICES: usize = 2;
+const MIN_FREQUENCY: f32 = 70.0;
+const MAX_FREQUENCY: f32 = 1500.0;
+const REFERENCE_HZ: f32 = 440.0;
+const GRAIN_MS: f32 = 30.0;
+const MAX_STEPS: i32 = 7;
+const MAX_DETUNE_CENTS: f32 = 50.0;
+const MAJOR: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+const INTERVAL_NAMES: [&str; 2 * MAX_STEPS as usize + 1] = [
+    "OCT DOWN", "7TH DOWN", "6TH DOWN", "5TH DOWN", "4TH DOWN", "3RD DOWN", "2ND DOWN", "UNISON",
+    "2ND UP", "3RD UP", "4TH UP", "5TH UP", "6TH UP", "7TH UP", "OCT UP",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Data)]
+pub enum Scale {
+    Major,
+    Minor,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
+}
+
+impl Scale {
+    pub const ALL: [Scale; 7] = [
+        Scale::Major,
+        Scale::Minor,
+        Scale::Dorian,
+        Scale::Phrygian,
+        Scale::Lydian,
+        Scale::Mixolydian,
+        Scale::Locrian,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scale::Major => "MAJOR",
+            Scale::Minor => "MINOR",
+            Scale::Dorian => "DORIAN",
+            Scale::Phrygian => "PHRYGIAN",
+            Scale::Lydian => "LYDIAN",
+            Scale::Mixolydian => "MIXOLYDIAN",
+            Scale::Locrian => "LOCRIAN",
+        }
+    }
+
+    /// Semitones of every degree above the root.
+    pub fn degrees(&self) -> [i32; 7] {
+        // Every mode is the major scale started from another one of its degrees
+        let mode = match self {
+            Scale::Major => 0,
+            Scale::Dorian => 1,
+            Scale::Phrygian => 2,
+            Scale::Lydian => 3,
+            Scale::Mixolydian => 4,
+            Scale::Minor => 5,
+            Scale::Locrian => 6,
+        };
+        std::array::from_fn(|degree| {
+            let index = mode + degree;
+            MAJOR[index % 7] + 12 * (index / 7) as i32 - MAJOR[mode]
+        })
+    }
+
+    /// Semitones between `note` and the note `steps` degrees away in the scale on `key`.
+    /// Notes outside the scale move like the degree right under them.
+    pub fn interval(&self, key: usize, note: i32, steps: i32) -> i32 {
+        let degrees = self.degrees();
+        let relative = (note - key as i32).rem_euclid(12);
+        let degree = degrees
+            .iter()
+            .rposition(|semitones| *semitones <= relative)
+            .unwrap_or(0) as i32;
+
+        let target = degree + steps;
+        degrees[target.rem_euclid(7) as usize] + 12 * target.div_euclid(7)
+            - degrees[degree as usize]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct HarmonyVoice {
+    enabled: bool,
+    steps: i32,
+    level: f32,
+    pan: f32,
+    detune: f32,
+}
+
+impl HarmonyVoice {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Scale degrees above the played note, negative ones go under it.
+    pub fn steps(&self) -> i32 {
+        self.steps
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    pub fn pan(&self) -> f32 {
+        self.pan
+    }
+
+    /// Detune in cents, a few of them thicken up a unison or a twin lead.
+    pub fn detune(&self) -> f32 {
+        self.detune
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn set_steps(&mut self, steps: i32) {
+        self.steps = steps.clamp(-MAX_STEPS, MAX_STEPS);
+    }
+
+    pub fn set_level(&mut self, level: f32) {
+        self.level = level.clamp(0.0, 1.0);
+    }
+
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan = pan.clamp(-1.0, 1.0);
+    }
+
+    pub fn set_detune(&mut self, detune: f32) {
+        self.detune = detune.clamp(-MAX_DETUNE_CENTS, MAX_DETUNE_CENTS);
+    }
+
+    /// Equal power gains for the left and right channel.
+    fn gains(&self) -> (f32, f32) {
+        if !self.enabled {
+            return (0.0, 0.0);
+        }
+
+        let angle = (self.pan + 1.0) * FRAC_PI_4;
+        (self.level * angle.cos(), self.level * angle.sin())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct HarmonizerSettings {
+    key: usize,
+    scale: Scale,
+    dry: f32,
+    voices: [HarmonyVoice; NUM_VOICES],
+}
+
+/// Adds voices a number of scale degrees away from the played note, so the intervals
+/// follow the key instead of staying parallel. Meant for single note lines.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "HarmonizerSettings", into = "HarmonizerSettings")]
+pub struct Harmonizer {
+    key: usize,
+    scale: Scale,
+    dry: f32,
+    voices: [HarmonyVoice; NUM_VOICES],
+
+    sample_rate: f32,
+    detector: PitchDetector,
+    shifters: [GrainShifter; NUM_VOICES],
+}
+
+impl Default for Harmonizer {
+    fn default() -> Self {
+        HarmonizerSettings {
+            key: 9,
+            scale: Scale::Minor,
+            dry: 1.0,
+            voices: [
+                HarmonyVoice {
+                    enabled: true,
+                    steps: 2,
+                    level: 0.8,
+                    pan: 0.5,
+                    detune: 0.0,
+                },
+                HarmonyVoice {
+                    enabled: false,
+                    steps: 4,
+                    level: 0.6,
+                    pan: -0.5,
+                    detune: 0.0,
+                },
+            ],
+        }
+        .into()
+    }
+}
+
+impl From<HarmonizerSettings> for Harmonizer {
+    fn from(settings: HarmonizerSettings) -> Self {
+        let mut harmonizer = Self {
+            key: settings.key % 12,
+            scale: settings.scale,
+            dry: settings.dry,
+            voices: settings.voices,
+            sample_rate: 44100.0,
+            detector: PitchDetector::new(MIN_FREQUENCY, MAX_FREQUENCY),
+            shifters: [GrainShifter::new(GRAIN_MS), GrainShifter::new(GRAIN_MS)],
+        };
+        harmonizer.set_sample_rate(44100.0);
+
+        // Presets can be edited by hand, so everything goes through the same limits as the knobs
+        harmonizer.set_dry(settings.dry);
+        for (voice, settings) in harmonizer.voices.iter_mut().zip(settings.voices) {
+            voice.set_steps(settings.steps);
+            voice.set_level(settings.level);
+            voice.set_pan(settings.pan);
+            voice.set_detune(settings.detune);
+        }
+        harmonizer
+    }
+}
+
+impl From<Harmonizer> for HarmonizerSettings {
+    fn from(harmonizer: Harmonizer) -> Self {
+        Self {
+            key: harmonizer.key,
+            scale: harmonizer.scale,
+            dry: harmonizer.dry,
+            voices: harmonizer.voices,
+        }
+    }
+}
+
+impl PartialEq for Harmonizer {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+            && self.scale == other.scale
+            && self.dry == other.dry
+            && self.voices == other.voices
+    }
+}
+
+impl Harmonizer {
+    #[inline]
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Root of the key as a pitch class, 0 is C.
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    pub fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    pub fn dry(&self) -> f32 {
+        self.dry
+    }
+
+    pub fn voice(&self, voice: usize) -> &HarmonyVoice {
+        &self.voices[voice]
+    }
+
+    pub fn voice_mut(&mut self, voice: usize) -> &mut HarmonyVoice {
+        &mut self.voices[voice]
+    }
+
+    /// Last pitch heard in Hz, if any.
+    pub fn detected(&self) -> Option<f32> {
+        self.detector.frequency()
+    }
+
+    #[inline]
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.detector.set_sample_rate(sample_rate);
+        for shifter in self.shifters.iter_mut() {
+            shifter.set_grain(GRAIN_MS, sample_rate);
+        }
+    }
+
+    pub fn set_key(&mut self, key: usize) {
+        self.key = key % 12;
+    }
+
+    pub fn set_scale(&mut self, scale: Scale) {
+        self.scale = scale;
+    }
+
+    pub fn set_dry(&mut self, dry: f32) {
+        self.dry = dry.clamp(0.0, 1.0);
+    }
+}
+
+impl AudioEffect for Harmonizer {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate() != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        let gains = self.voices.map(|voice| voice.gains());
+        let Self {
+            key,
+            scale,
+            dry,
+            voices,
+            detector,
+            shifters,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            let input = (*left + *right) * 0.5;
+
+            // The shift only changes with a new note, slides and vibrato on it carry over
+            if detector.push(input) {
+                if let Some(frequency) = detector.frequency() {
+                    let note = frequency_to_note(frequency, REFERENCE_HZ).round() as i32;
+                    for (voice, shifter) in voices.iter().zip(shifters.iter_mut()) {
+                        let semitones = scale.interval(*key, note, voice.steps) as f32
+                            + voice.detune / 100.0;
+                        shifter.set_ratio(2.0_f32.powf(semitones / 12.0));
+                    }
+                }
+            }
+
+            let (mut wet_left, mut wet_right) = (0.0, 0.0);
+            for (shifter, (gain_left, gain_right)) in shifters.iter_mut().zip(gains) {
+                let (_, wet) = shifter.tick(input);
+                wet_left += wet * gain_left;
+                wet_right += wet * gain_right;
+            }
+
+            *left = *left * *dry + wet_left;
+            *right = *right * *dry + wet_right;
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        HarmonizerView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        200.0
+    }
+}
+
+enum HarmonizerEvent {
+    CycleKey,
+    CycleScale,
+    Select(usize),
+    Toggle,
+    Step(i32),
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct HarmonizerView {
+    #[msg]
+    dry: f32,
+    #[msg]
+    level: f32,
+    #[msg]
+    pan: f32,
+    #[msg]
+    detune: f32,
+
+    key: usize,
+    scale: Scale,
+    selected: usize,
+    enabled: bool,
+    steps: i32,
+    revision: u64,
+
+    #[lens(ignore)]
+    handle: EffectHandle<Harmonizer>,
+}
+
+impl HarmonizerView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<Harmonizer>) -> Handle<Self> {
+        let voice = *handle.voice(0);
+        Self {
+            dry: handle.dry() * 100.0,
+            level: voice.level() * 100.0,
+            pan: voice.pan() * 100.0,
+            detune: voice.detune(),
+            key: handle.key(),
+            scale: handle.scale(),
+            selected: 0,
+            enabled: voice.enabled(),
+            steps: voice.steps(),
+            revision: 0,
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                VStack::new(cx, |cx| {
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(HarmonizerEvent::CycleKey),
+                        |cx| Label::new(cx, Self::key.map(|key| NOTE_NAMES[*key])),
+                    )
+                    .class("harmonizer-option");
+                    Button::new(
+                        cx,
+                        |ex| ex.emit(HarmonizerEvent::CycleScale),
+                        |cx| Label::new(cx, Self::scale.map(|scale| scale.name())),
+                    )
+                    .class("harmonizer-option");
+                    NamedKnob::new(cx, "Dry", Self::dry, false, 0.0..100.0)
+                        .on_changing(|ex, val| ex.emit(Message::Dry(val)));
+                })
+                .class("harmonizer-options");
+                VStack::new(cx, |cx| {
+                    HStack::new(cx, |cx| {
+                        for (index, name) in ["VOICE 1", "VOICE 2"].into_iter().enumerate() {
+                            Button::new(
+                                cx,
+                                move |ex| ex.emit(HarmonizerEvent::Select(index)),
+                                move |cx| Label::new(cx, name),
+                            )
+                            .class("band-btn")
+                            .toggle_class(
+                                "band-selected",
+                                Self::selected.map(move |selected| *selected == index),
+                            );
+                        }
+                    })
+                    .class("band-selector");
+                    Binding::new(cx, Self::revision, |cx, _| {
+                        HStack::new(cx, |cx| {
+                            VStack::new(cx, |cx| {
+                                Button::new(
+                                    cx,
+                                    |ex| ex.emit(HarmonizerEvent::Toggle),
+                                    |cx| {
+                                        Label::new(
+                                            cx,
+                                            Self::enabled
+                                                .map(|enabled| if *enabled { "ON" } else { "OFF" }),
+                                        )
+                                    },
+                                )
+                                .class("band-option")
+                                .toggle_class("band-enabled", Self::enabled);
+                                HStack::new(cx, |cx| {
+                                    Button::new(
+                                        cx,
+                                        |ex| ex.emit(HarmonizerEvent::Step(-1)),
+                                        |cx| Label::new(cx, "-"),
+                                    )
+                                    .class("step-btn");
+                                    Label::new(
+                                        cx,
+                                        Self::steps.map(|steps| {
+                                            INTERVAL_NAMES[(*steps + MAX_STEPS) as usize]
+                                        }),
+                                    )
+                                    .class("interval-name");
+                                    Button::new(
+                                        cx,
+                                        |ex| ex.emit(HarmonizerEvent::Step(1)),
+                                        |cx| Label::new(cx, "+"),
+                                    )
+                                    .class("step-btn");
+                                })
+                                .class("interval-selector");
+                            })
+                            .class("band-options");
+                            NamedKnob::new(cx, "Level", Self::level, false, 0.0..100.0)
+                                .on_changing(|ex, val| ex.emit(Message::Level(val)));
+                            NamedKnob::new(cx, "Pan", Self::pan, true, -100.0..100.0)
+                                .on_changing(|ex, val| ex.emit(Message::Pan(val)));
+                            NamedKnob::new(
+                                cx,
+                                "Detune",
+                                Self::detune,
+                                true,
+                                -MAX_DETUNE_CENTS..MAX_DETUNE_CENTS,
+                            )
+                            .on_changing(|ex, val| ex.emit(Message::Detune(val)));
+                        })
+                        .class("band-controls");
+                    });
+                })
+                .class("harmonizer-controls");
+                Label::new(cx, "HARMONIZER").class("effect-title");
+            });
+        })
+    }
+
+    fn sync_voice(&mut self) {
+        let voice = *self.handle.voice(self.selected);
+        self.level = voice.level() * 100.0;
+        self.pan = voice.pan() * 100.0;
+        self.detune = voice.detune();
+        self.enabled = voice.enabled();
+        self.steps = voice.steps();
+    }
+}
+
+impl View for HarmonizerView {
+    fn element(&self) -> Option<&'static str> {
+        Some("harmonizer")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Dry(val) => {
+                self.dry = *val;
+                self.handle.set_dry(*val / 100.0);
+            }
+            Message::Level(val) => {
+                self.level = *val;
+                self.handle.voice_mut(self.selected).set_level(*val / 100.0);
+            }
+            Message::Pan(val) => {
+                self.pan = *val;
+                self.handle.voice_mut(self.selected).set_pan(*val / 100.0);
+            }
+            Message::Detune(val) => {
+                self.detune = *val;
+                self.handle.voice_mut(self.selected).set_detune(*val);
+            }
+        });
+
+        event.map(|event, _| match event {
+            HarmonizerEvent::CycleKey => {
+                self.key = (self.key + 1) % NOTE_NAMES.len();
+                self.handle.set_key(self.key);
+            }
+            HarmonizerEvent::CycleScale => {
+                let current = Scale::ALL
+                    .iter()
+                    .position(|scale| *scale == self.scale)
+                    .unwrap_or(0);
+                self.scale = Scale::ALL[(current + 1) % Scale::ALL.len()];
+                self.handle.set_scale(self.scale);
+            }
+            HarmonizerEvent::Select(voice) => {
+                self.selected = *voice;
+                self.sync_voice();
+                self.revision += 1;
+            }
+            HarmonizerEvent::Toggle => {
+                let enabled = !self.enabled;
+                self.handle.voice_mut(self.selected).set_enabled(enabled);
+                self.sync_voice();
+                self.revision += 1;
+            }
+            HarmonizerEvent::Step(delta) => {
+                let steps = self.steps + delta;
+                self.handle.voice_mut(self.selected).set_steps(steps);
+                self.sync_voice();
+                self.revision += 1;
+            }
+        });
+    }
+}
@@ -0,0 +1,11 @@
+mod octaver;
+pub use octaver::Octaver;
+
+mod pitch_shifter;
+pub use pitch_shifter::PitchShifter;
+
+mod harmonizer;
+pub use harmonizer::{Harmonizer, HarmonyVoice, Scale};
+
+mod tuner;
+pub use tuner::{Tuner, TunerReading};
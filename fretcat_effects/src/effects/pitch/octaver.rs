@@ -0,0 +1,265 @@
+use crate::prelude::*;
+
+// Keeps the tracker on the fundamental, harmonics would make the flip-flops skip
+const TRACKING_CUTOFF_HZ: f32 = 800.0;
+const ENVELOPE_RELEASE_MS: f32 = 50.0;
+// Schmitt trigger thresholds as a fraction of the envelope
+const HYSTERESIS: f32 = 0.1;
+// Below this the tracker holds its state instead of toggling on noise
+const NOISE_FLOOR: f32 = 0.001;
+const RECTIFIER_DC_CUTOFF_HZ: f32 = 30.0;
+
+/// Per channel analog octave state, one Schmitt trigger feeding two divide by two stages.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct OctaveTracker {
+    filters: [ButterLowpass; 2],
+    envelope: f32,
+    triggered: bool,
+    sub: bool,
+    sub2: bool,
+    rectifier_dc: DCBlock,
+}
+
+impl Default for OctaveTracker {
+    fn default() -> Self {
+        Self {
+            filters: [ButterLowpass::new(TRACKING_CUTOFF_HZ); 2],
+            envelope: 0.0,
+            triggered: false,
+            sub: false,
+            sub2: false,
+            rectifier_dc: DCBlock::new(RECTIFIER_DC_CUTOFF_HZ),
+        }
+    }
+}
+
+impl OctaveTracker {
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        for filter in self.filters.iter_mut() {
+            filter.set_sample_rate(sample_rate);
+        }
+        self.rectifier_dc.set_sample_rate(sample_rate);
+    }
+
+    /// Returns the octave up, one octave down and two octaves down signals.
+    #[inline]
+    fn tick(&mut self, input: f32, release: f32) -> (f32, f32, f32) {
+        let tracked = self.filters[1].tick(self.filters[0].tick(input));
+
+        let level = tracked.abs();
+        self.envelope = if level > self.envelope {
+            level
+        } else {
+            level + release * (self.envelope - level)
+        };
+
+        if self.envelope > NOISE_FLOOR {
+            let threshold = self.envelope * HYSTERESIS;
+            if !self.triggered && tracked > threshold {
+                self.triggered = true;
+                self.sub = !self.sub;
+                if self.sub {
+                    self.sub2 = !self.sub2;
+                }
+            } else if self.triggered && tracked < -threshold {
+                self.triggered = false;
+            }
+        }
+
+        // Like the flip-flop pedals, the square only switches the polarity of the tracked
+        // signal, so the sub follows the playing dynamics and only steps by the hysteresis
+        let sub = if self.sub { tracked } else { -tracked };
+        let sub2 = if self.sub2 { tracked } else { -tracked };
+        // Full wave rectifying doubles the frequency, the DC it leaves behind gets removed
+        let up = 2.0 * self.rectifier_dc.tick(input.abs());
+
+        (up, sub, sub2)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Octaver {
+    dry: f32,
+    up: f32,
+    sub: f32,
+    sub2: f32,
+
+    sample_rate: f32,
+    trackers: [OctaveTracker; NUM_CHANNELS],
+}
+
+impl Default for Octaver {
+    fn default() -> Self {
+        Self {
+            dry: 1.0,
+            up: 0.0,
+            sub: 0.5,
+            sub2: 0.0,
+            sample_rate: 44100.0,
+            trackers: [OctaveTracker::default(); NUM_CHANNELS],
+        }
+    }
+}
+
+impl PartialEq for Octaver {
+    fn eq(&self, other: &Self) -> bool {
+        self.dry == other.dry
+            && self.up == other.up
+            && self.sub == other.sub
+            && self.sub2 == other.sub2
+    }
+}
+
+impl Octaver {
+    #[inline]
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn dry(&self) -> f32 {
+        self.dry
+    }
+
+    /// Level of the rectified octave up.
+    pub fn up(&self) -> f32 {
+        self.up
+    }
+
+    /// Level of the octave down.
+    pub fn sub(&self) -> f32 {
+        self.sub
+    }
+
+    /// Level of the two octaves down.
+    pub fn sub2(&self) -> f32 {
+        self.sub2
+    }
+
+    #[inline]
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for tracker in self.trackers.iter_mut() {
+            tracker.set_sample_rate(sample_rate);
+        }
+    }
+
+    pub fn set_dry(&mut self, dry: f32) {
+        self.dry = dry.clamp(0.0, 1.0);
+    }
+
+    pub fn set_up(&mut self, up: f32) {
+        self.up = up.clamp(0.0, 1.0);
+    }
+
+    pub fn set_sub(&mut self, sub: f32) {
+        self.sub = sub.clamp(0.0, 1.0);
+    }
+
+    pub fn set_sub2(&mut self, sub2: f32) {
+        self.sub2 = sub2.clamp(0.0, 1.0);
+    }
+}
+
+impl AudioEffect for Octaver {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate() != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        let release = one_pole_coeff(ENVELOPE_RELEASE_MS, self.sample_rate);
+
+        let Self {
+            dry,
+            up,
+            sub,
+            sub2,
+            trackers,
+            ..
+        } = self;
+
+        input_buffer.process_individual(|left, right| {
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let (octave_up, octave_sub, octave_sub2) = trackers[channel].tick(*sample, release);
+                *sample = *sample * *dry
+                    + octave_up * *up
+                    + octave_sub * *sub
+                    + octave_sub2 * *sub2;
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        OctaverView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        100.0
+    }
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct OctaverView {
+    #[msg]
+    dry: f32,
+    #[msg]
+    up: f32,
+    #[msg]
+    sub: f32,
+    #[msg]
+    sub2: f32,
+
+    #[lens(ignore)]
+    handle: EffectHandle<Octaver>,
+}
+
+impl OctaverView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<Octaver>) -> Handle<Self> {
+        Self {
+            dry: handle.dry() * 100.0,
+            up: handle.up() * 100.0,
+            sub: handle.sub() * 100.0,
+            sub2: handle.sub2() * 100.0,
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                NamedKnob::new(cx, "Dry", Self::dry, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Dry(val)));
+                NamedKnob::new(cx, "Up", Self::up, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Up(val)));
+                NamedKnob::new(cx, "-1 Oct", Self::sub, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Sub(val)));
+                NamedKnob::new(cx, "-2 Oct", Self::sub2, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Sub2(val)));
+                Label::new(cx, "OCTAVER").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for OctaverView {
+    fn element(&self) -> Option<&'static str> {
+        Some("octaver")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Dry(val) => {
+                self.dry = *val;
+                self.handle.set_dry(*val / 100.0);
+            }
+            Message::Up(val) => {
+                self.up = *val;
+                self.handle.set_up(*val / 100.0);
+            }
+            Message::Sub(val) => {
+                self.sub = *val;
+                self.handle.set_sub(*val / 100.0);
+            }
+            Message::Sub2(val) => {
+                self.sub2 = *val;
+                self.handle.set_sub2(*val / 100.0);
+            }
+        });
+    }
+}
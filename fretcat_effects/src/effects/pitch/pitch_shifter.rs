@@ -0,0 +1,271 @@
+use crate::prelude::*;
+
+const MAX_SEMITONES: f32 = 24.0;
+const MAX_CENTS: f32 = 50.0;
+// A frame long enough to split the low strings of a bass, about 35 ms of delay at 44.1 kHz
+const FRAME_SIZE: usize = 2048;
+const OVERLAP: usize = 4;
+const GRAIN_MS: f32 = 30.0;
+
+#[derive(Serialize, Deserialize)]
+struct PitchShifterSettings {
+    semitones: f32,
+    cents: f32,
+    low_latency: bool,
+    mix: f32,
+}
+
+/// Polyphonic pitch shifter for dropping or raising the tuning of a whole part.
+/// Low latency mode swaps the phase vocoder for a grain shifter, which warbles more
+/// but only delays by half a grain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "PitchShifterSettings", into = "PitchShifterSettings")]
+pub struct PitchShifter {
+    semitones: f32,
+    cents: f32,
+    low_latency: bool,
+    mix: f32,
+
+    sample_rate: f32,
+    vocoders: [PhaseVocoder; NUM_CHANNELS],
+    grains: [GrainShifter; NUM_CHANNELS],
+}
+
+impl Default for PitchShifter {
+    fn default() -> Self {
+        PitchShifterSettings {
+            semitones: -2.0,
+            cents: 0.0,
+            low_latency: false,
+            mix: 1.0,
+        }
+        .into()
+    }
+}
+
+impl From<PitchShifterSettings> for PitchShifter {
+    fn from(settings: PitchShifterSettings) -> Self {
+        let mut pitch_shifter = Self {
+            semitones: settings.semitones,
+            cents: settings.cents,
+            low_latency: settings.low_latency,
+            mix: settings.mix,
+            sample_rate: 44100.0,
+            vocoders: [
+                PhaseVocoder::new(FRAME_SIZE, OVERLAP),
+                PhaseVocoder::new(FRAME_SIZE, OVERLAP),
+            ],
+            grains: [GrainShifter::new(GRAIN_MS), GrainShifter::new(GRAIN_MS)],
+        };
+        pitch_shifter.set_sample_rate(44100.0);
+        pitch_shifter
+    }
+}
+
+impl From<PitchShifter> for PitchShifterSettings {
+    fn from(pitch_shifter: PitchShifter) -> Self {
+        Self {
+            semitones: pitch_shifter.semitones,
+            cents: pitch_shifter.cents,
+            low_latency: pitch_shifter.low_latency,
+            mix: pitch_shifter.mix,
+        }
+    }
+}
+
+impl PartialEq for PitchShifter {
+    fn eq(&self, other: &Self) -> bool {
+        self.semitones == other.semitones
+            && self.cents == other.cents
+            && self.low_latency == other.low_latency
+            && self.mix == other.mix
+    }
+}
+
+impl PitchShifter {
+    #[inline]
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn semitones(&self) -> f32 {
+        self.semitones
+    }
+
+    pub fn cents(&self) -> f32 {
+        self.cents
+    }
+
+    pub fn low_latency(&self) -> bool {
+        self.low_latency
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    /// Frequency ratio for the current semitones and cents.
+    pub fn ratio(&self) -> f32 {
+        2.0_f32.powf((self.semitones + self.cents / 100.0) / 12.0)
+    }
+
+    #[inline]
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for grain in self.grains.iter_mut() {
+            grain.set_grain(GRAIN_MS, sample_rate);
+        }
+    }
+
+    /// Whole semitones only, the cents take care of anything finer.
+    pub fn set_semitones(&mut self, semitones: f32) {
+        self.semitones = semitones.round().clamp(-MAX_SEMITONES, MAX_SEMITONES);
+    }
+
+    pub fn set_cents(&mut self, cents: f32) {
+        self.cents = cents.clamp(-MAX_CENTS, MAX_CENTS);
+    }
+
+    /// Changes the latency reported to the host as well.
+    pub fn set_low_latency(&mut self, low_latency: bool) {
+        self.low_latency = low_latency;
+        self.vocoders.iter_mut().for_each(PhaseVocoder::reset);
+        self.grains.iter_mut().for_each(GrainShifter::reset);
+    }
+
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+}
+
+impl AudioEffect for PitchShifter {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if self.sample_rate() != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        let ratio = self.ratio();
+        let Self {
+            low_latency,
+            mix,
+            vocoders,
+            grains,
+            ..
+        } = self;
+
+        vocoders.iter_mut().for_each(|vocoder| vocoder.set_ratio(ratio));
+        grains.iter_mut().for_each(|grain| grain.set_ratio(ratio));
+
+        input_buffer.process_individual(|left, right| {
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                let (dry, wet) = if *low_latency {
+                    grains[channel].tick(*sample)
+                } else {
+                    vocoders[channel].tick(*sample)
+                };
+                *sample = dry + *mix * (wet - dry);
+            }
+        });
+    }
+
+    fn view(&self, cx: &mut Context, handle: EffectHandle<dyn AudioEffect>) {
+        PitchShifterView::new(cx, EffectHandle::<Self>::from(handle)).class("base-effect");
+    }
+
+    fn height(&self) -> f32 {
+        100.0
+    }
+
+    fn latency(&self) -> u32 {
+        if self.low_latency {
+            self.grains[0].latency() as u32
+        } else {
+            self.vocoders[0].latency() as u32
+        }
+    }
+}
+
+enum PitchShifterEvent {
+    ToggleLowLatency,
+}
+
+#[derive(Debug, Clone, Lens, Message)]
+struct PitchShifterView {
+    #[msg]
+    semitones: f32,
+    #[msg]
+    cents: f32,
+    #[msg]
+    mix: f32,
+
+    low_latency: bool,
+
+    #[lens(ignore)]
+    handle: EffectHandle<PitchShifter>,
+}
+
+impl PitchShifterView {
+    pub fn new(cx: &mut Context, handle: EffectHandle<PitchShifter>) -> Handle<Self> {
+        Self {
+            semitones: handle.semitones(),
+            cents: handle.cents(),
+            mix: handle.mix() * 100.0,
+            low_latency: handle.low_latency(),
+            handle: handle.clone(),
+        }
+        .build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                Button::new(
+                    cx,
+                    |ex| ex.emit(PitchShifterEvent::ToggleLowLatency),
+                    |cx| Label::new(cx, "LOW LATENCY"),
+                )
+                .class("pitch-shifter-option")
+                .toggle_class("option-enabled", Self::low_latency);
+                NamedKnob::new(
+                    cx,
+                    "Semitones",
+                    Self::semitones,
+                    true,
+                    -MAX_SEMITONES..MAX_SEMITONES,
+                )
+                .on_changing(|ex, val| ex.emit(Message::Semitones(val)));
+                NamedKnob::new(cx, "Cents", Self::cents, true, -MAX_CENTS..MAX_CENTS)
+                    .on_changing(|ex, val| ex.emit(Message::Cents(val)));
+                NamedKnob::new(cx, "Mix", Self::mix, false, 0.0..100.0)
+                    .on_changing(|ex, val| ex.emit(Message::Mix(val)));
+                Label::new(cx, "PITCH SHIFT").class("effect-title");
+            });
+        })
+    }
+}
+
+impl View for PitchShifterView {
+    fn element(&self) -> Option<&'static str> {
+        Some("pitch-shifter")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|event, _| match event {
+            Message::Semitones(val) => {
+                self.semitones = *val;
+                self.handle.set_semitones(*val);
+            }
+            Message::Cents(val) => {
+                self.cents = *val;
+                self.handle.set_cents(*val);
+            }
+            Message::Mix(val) => {
+                self.mix = *val;
+                self.handle.set_mix(*val / 100.0);
+            }
+        });
+
+        event.map(|event, _| match event {
+            PitchShifterEvent::ToggleLowLatency => {
+                self.low_latency = !self.low_latency;
+                self.handle.set_low_latency(self.low_latency);
+            }
+        });
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::{
+    atomic::{AtomicI32, AtomicU32, Ordering},
+    Arc,
+};
+
+use crate::prelude::*;
+
+// Low enough for a five string bass tuned down, high enough for harmonics at the 12th fret
+const MIN_FREQUENCY: f32 = 27.5;
+const MAX_FREQUENCY: f32 = 1400.0;
+
+/// What the tuner heard last.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TunerReading {
+    pub frequency: f32,
+    /// Closest MIDI note.
+    pub note: i32,
+    /// Distance to `note`, from -50 to 50.
+    pub cents: f32,
+    /// How periodic the signal was, from 0 to 1.
+    pub confidence: f32,
+}
+
+/// Written by the audio thread and read by the editor, floats are stored as their bits.
+#[derive(Debug, Default)]
+struct TunerState {
+    frequency: AtomicU32,
+    note: AtomicI32,
+    cents: AtomicU32,
+    confidence: AtomicU32,
+}
+
+impl TunerState {
+    fn publish(&self, reading: Option<TunerReading>) {
+        let reading = reading.unwrap_or(TunerReading {
+            frequency: 0.0,
+            note: -1,
+            cents: 0.0,
+            confidence: 0.0,
+        });
+        self.note.store(reading.note, Ordering::Relaxed);
+        self.cents.store(reading.cents.to_bits(), Ordering::Relaxed);
+        self.confidence
+            .store(reading.confidence.to_bits(), Ordering::Relaxed);
+        // Written last, a reader that sees a frequency sees the rest of the reading too
+        self.frequency
+            .store(reading.frequency.to_bits(), Ordering::Release);
+    }
+
+    fn load(&self) -> Option<TunerReading> {
+        let frequency = f32::from_bits(self.frequency.load(Ordering::Acquire));
+        if frequency <= 0.0 {
+            return None;
+        }
+
+        Some(TunerReading {
+            frequency,
+            note: self.note.load(Ordering::Relaxed),
+            cents: f32::from_bits(self.cents.load(Ordering::Relaxed)),
+            confidence: f32::from_bits(self.confidence.load(Ordering::Relaxed)),
+        })
+    }
+}
+
+/// Chromatic tuner that sits in the pre FX. It only listens while enabled, and can mute
+/// the output meanwhile so the band doesn't hear the tuning.
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    enabled: bool,
+    mute: bool,
+    reference: f32,
+
+    sample_rate: f32,
+    detector: PitchDetector,
+    state: Arc<TunerState>,
+}
+
+impl Default for Tuner {
+    fn default() -> Self {
+        let mut tuner = Self {
+            enabled: false,
+            mute: false,
+            reference: 440.0,
+            sample_rate: 44100.0,
+            detector: PitchDetector::new(MIN_FREQUENCY, MAX_FREQUENCY),
+            state: Arc::new(TunerState::default()),
+        };
+        tuner.set_sample_rate(44100.0);
+        tuner
+    }
+}
+
+impl Tuner {
+    pub const MIN_REFERENCE: f32 = 415.0;
+    pub const MAX_REFERENCE: f32 = 466.0;
+
+    #[inline]
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn mute(&self) -> bool {
+        self.mute
+    }
+
+    /// Frequency of A4 in Hz.
+    pub fn reference(&self) -> f32 {
+        self.reference
+    }
+
+    /// Latest reading, `None` for silence or anything without a clear pitch.
+    pub fn reading(&self) -> Option<TunerReading> {
+        self.state.load()
+    }
+
+    #[inline]
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.detector.set_sample_rate(sample_rate);
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.detector.reset();
+            self.state.publish(None);
+        }
+    }
+
+    /// Only silences the output while the tuner is enabled.
+    pub fn set_mute(&mut self, mute: bool) {
+        self.mute = mute;
+    }
+
+    pub fn set_reference(&mut self, reference: f32) {
+        self.reference = reference.clamp(Self::MIN_REFERENCE, Self::MAX_REFERENCE);
+    }
+}
+
+impl AudioEffect for Tuner {
+    fn process(&mut self, input_buffer: &mut Frame, transport: &nih_plug::prelude::Transport) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.sample_rate() != transport.sample_rate {
+            self.set_sample_rate(transport.sample_rate);
+        }
+
+        let Self {
+            mute,
+            detector,
+            ..
+        } = self;
+
+        let mut updated = false;
+        input_buffer.process_individual(|left, right| {
+            updated |= detector.push((*left + *right) * 0.5);
+            if *mute {
+                *left = 0.0;
+                *right = 0.0;
+            }
+        });
+
+        if updated {
+            let reading = self.detector.frequency().map(|frequency| {
+                let exact = frequency_to_note(frequency, self.reference);
+                let note = exact.round();
+                TunerReading {
+                    frequency,
+                    note: note as i32,
+                    cents: (exact - note) * 100.0,
+                    confidence: self.detector.clarity(),
+                }
+            });
+            self.state.publish(reading);
+        }
+    }
+}
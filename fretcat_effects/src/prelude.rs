@@ -2,7 +2,7 @@ pub use super::frame::Frame;
 pub use super::chain::Chain;
 pub use super::common::*;
 pub use super::effect_handle::EffectHandle;
-pub use super::effects::{AudioEffect, PreFX, PostFX};
+pub use super::effects::{AudioEffect, EffectError, PreFX, PostFX};
 pub use super::components::*;
 
 pub use fretcat_macros::Message;
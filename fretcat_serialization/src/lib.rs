@@ -4,7 +4,10 @@ mod tests;
 
 use std::{fs, path::{Path, PathBuf}, sync::Arc, io};
 
-use fretcat_effects::{effects::AudioEffect, Chain};
+use fretcat_effects::{
+    effects::{AudioEffect, NoiseGate, PreFX},
+    Chain,
+};
 use lazy_static::lazy_static;
 use mapper::Mapper;
 use serde::{Deserialize, Serialize};
@@ -38,6 +41,8 @@ pub struct Preset {
     name: String,
     category: PresetCategory,
     effects: Vec<Mapper>,
+    #[serde(default)]
+    noise_gate: NoiseGate,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -105,6 +110,7 @@ impl Preset {
         let preset = serde_json::from_str::<Preset>(&json).unwrap();
 
         self.set_mappers(preset.cloned_mappers());
+        self.set_noise_gate(preset.noise_gate().clone());
     }
 
     pub fn set_name<S: AsRef<str>>(&mut self, name: S) {
@@ -134,6 +140,28 @@ impl Preset {
         self.effects.clone()
     }
 
+    pub fn noise_gate(&self) -> &NoiseGate {
+        &self.noise_gate
+    }
+
+    pub fn set_noise_gate(&mut self, noise_gate: NoiseGate) {
+        self.noise_gate = noise_gate;
+    }
+
+    /// Files the preset's effects point to that couldn't be found.
+    pub fn missing_files(&self) -> Vec<String> {
+        self.effects
+            .iter()
+            .flat_map(|mapper| match mapper {
+                Mapper::CabIr(cab_ir) => cab_ir.missing_irs().map(str::to_owned).collect(),
+                Mapper::NamPlayer(player) => {
+                    player.missing_model().map(str::to_owned).into_iter().collect()
+                }
+                _ => vec![],
+            })
+            .collect()
+    }
+
     pub fn save(&self) -> io::Result<()> {
         let json = serde_json::to_string_pretty(self).unwrap();
 
@@ -188,6 +216,7 @@ impl Default for Preset {
             name: "Untitled".to_owned(),
             category: PresetCategory::default(),
             effects: vec![],
+            noise_gate: NoiseGate::default(),
         }
     }
 }
@@ -203,6 +232,9 @@ impl From<Arc<Chain>> for Preset {
             .collect();
 
         me.effects = mappers;
+        if let Some(noise_gate) = value.get_pre_fx_ref::<NoiseGate>(&PreFX("noise_gate")) {
+            me.noise_gate = noise_gate.clone();
+        }
 
         me
     }
@@ -219,6 +251,9 @@ impl From<&Chain> for Preset {
             .collect();
 
         me.effects = mappers;
+        if let Some(noise_gate) = value.get_pre_fx_ref::<NoiseGate>(&PreFX("noise_gate")) {
+            me.noise_gate = noise_gate.clone();
+        }
 
         me
     }
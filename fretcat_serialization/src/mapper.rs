@@ -1,4 +1,4 @@
-use fretcat_effects::effects::{Fuzz, Overdrive, AudioEffect, StudioReverb, Gain, LowPass, HighPass, BandPass, MonoDelay, TwinDelay, BitCrusher};
+use fretcat_effects::effects::{Fuzz, Overdrive, AudioEffect, StudioReverb, Gain, LowPass, HighPass, BandPass, MonoDelay, TwinDelay, StereoDelay, TapeEcho, Looper, BitCrusher, ParametricEq, GraphicEq, Chorus, Flanger, Phaser, Tremolo, Vibrato, Amp, CabIr, CabSim, NamPlayer, TubeScreamer, Waveshaper, MultibandDistortion, Octaver, PitchShifter, Harmonizer};
 use fretcat_macros::EffectMapper;
 
 use std::sync::Arc;
@@ -11,13 +11,36 @@ pub enum Mapper {
     Fuzz(Fuzz),
     Gain(Gain),
     BitCrusher(BitCrusher),
+    TubeScreamer(TubeScreamer),
+    Waveshaper(Waveshaper),
+    MultibandDistortion(MultibandDistortion),
 
     LowPass(LowPass),
     HighPass(HighPass),
     BandPass(BandPass),
+    ParametricEq(ParametricEq),
+    GraphicEq(GraphicEq),
 
     MonoDelay(MonoDelay),
     TwinDelay(TwinDelay),
+    StereoDelay(StereoDelay),
+    TapeEcho(TapeEcho),
+    Looper(Looper),
+
+    Chorus(Chorus),
+    Flanger(Flanger),
+    Phaser(Phaser),
+    Tremolo(Tremolo),
+    Vibrato(Vibrato),
+
+    Amp(Amp),
+    CabIr(CabIr),
+    CabSim(CabSim),
+    NamPlayer(NamPlayer),
+
+    Octaver(Octaver),
+    PitchShifter(PitchShifter),
+    Harmonizer(Harmonizer),
 
     StudioReverb(StudioReverb),
 }
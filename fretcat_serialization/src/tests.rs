@@ -1,6 +1,6 @@
-use fretcat_effects::Chain;
+use fretcat_effects::{effects::MonoDelay, prelude::MAX_DELAY_SECS, Chain};
 
-use crate::Preset;
+use crate::{mapper::Mapper, Preset};
 
 #[test]
 fn test_preset_serialization() {
@@ -21,4 +21,37 @@ fn already_exist() {
 
     let e = preset.already_exists();
     assert_eq!(e, false);
-}
\ No newline at end of file
+}
+
+#[test]
+fn load_old_bit_crusher() {
+    let json = r#"{"BitCrusher":{"bit_rate":8000.0,"sample_rate":44100.0}}"#;
+
+    let Mapper::BitCrusher(crusher) = serde_json::from_str::<Mapper>(json).unwrap() else {
+        panic!("expected a bit crusher");
+    };
+    assert_eq!(crusher.rate(), 8000.0);
+    assert_eq!(crusher.bits(), 24.0);
+}
+
+#[test]
+fn load_old_mono_delay() {
+    let old_delay = r#"{"feedback":0.6,"delay_time_secs":0.35,"current_write_position":15435,"current_read_position":0,"sample_rate":44100.0,"buffer_size":4,"delay_buffer":[0.0,0.0,0.0,0.0],"max_delay_time":{"secs":1,"nanos":0}}"#;
+    let json = format!(r#"{{"MonoDelay":{{"wet":0.4,"delays":[{old_delay},{old_delay}]}}}}"#);
+
+    let Mapper::MonoDelay(delay) = serde_json::from_str::<Mapper>(&json).unwrap() else {
+        panic!("expected a mono delay");
+    };
+
+    let mut expected = MonoDelay::default();
+    expected.set_wet(0.4);
+    expected.set_feedback(0.6);
+    expected.set_time(0.35);
+    assert_eq!(delay, expected);
+
+    // The old one second buffer size isn't carried over, the lines get the full length
+    let saved = serde_json::to_value(&delay).unwrap();
+    for line in saved["delays"].as_array().unwrap() {
+        assert_eq!(line["max_delay_secs"].as_f64().unwrap() as f32, MAX_DELAY_SECS);
+    }
+}
@@ -12,7 +12,8 @@ use std::{num::NonZeroU32, sync::{Arc, Mutex}};
 pub struct Fretcat {
     params: Arc<FretcatParams>,
     chain: Arc<Chain>,
-    preset: Arc<Mutex<Preset>>
+    preset: Arc<Mutex<Preset>>,
+    latency: u32,
 }
 
 impl Default for Fretcat {
@@ -20,7 +21,8 @@ impl Default for Fretcat {
         Self {
             params: Arc::new(FretcatParams::default()),
             chain: Arc::new(Chain::default()),
-            preset: Arc::new(Mutex::new(Preset::default()))
+            preset: Arc::new(Mutex::new(Preset::default())),
+            latency: 0,
         }
     }
 }
@@ -40,6 +42,8 @@ impl Plugin for Fretcat {
         ..AudioIOLayout::const_default()
     }];
 
+    // Footswitches send control changes, notes aren't used for anything
+    const MIDI_INPUT: MidiConfig = MidiConfig::MidiCCs;
     const SAMPLE_ACCURATE_AUTOMATION: bool = false;
     const HARD_REALTIME_ONLY: bool = true;
 
@@ -60,6 +64,14 @@ impl Plugin for Fretcat {
         _buffer_config: &BufferConfig,
         _context: &mut impl InitContext<Self>,
     ) -> bool {
+        let chain = unsafe {
+            &mut *Arc::as_ptr(&self.chain).cast_mut()
+        };
+        chain.prepare(_buffer_config.sample_rate);
+
+        self.latency = chain.latency();
+        _context.set_latency_samples(self.latency);
+
         true
     }
 
@@ -71,11 +83,24 @@ impl Plugin for Fretcat {
         _aux: &mut AuxiliaryBuffers,
         _context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        let transport = _context.transport();
         let chain = unsafe {
             &mut *Arc::as_ptr(&self.chain).cast_mut()
         };
 
+        let latency = chain.latency();
+        if latency != self.latency {
+            self.latency = latency;
+            _context.set_latency_samples(latency);
+        }
+
+        while let Some(event) = _context.next_event() {
+            if let NoteEvent::MidiCC { cc, value, .. } = event {
+                chain.midi_cc(cc, value);
+            }
+        }
+
+        let transport = _context.transport();
+
         chain.process(buffer.as_slice(), transport);
 
         ProcessStatus::Normal